@@ -0,0 +1,1088 @@
+//! Headless front end to the snapshot engine, for scripts, cron jobs and
+//! machines without a desktop session.
+
+use checkpoint_lib::config::Config;
+use checkpoint_lib::crypto;
+use checkpoint_lib::diff::{self, ChangeKind, SnapshotDiff};
+use checkpoint_lib::game::{AddGameRequest, Game, SaveRoot, DEFAULT_SAVE_ROOT};
+use checkpoint_lib::ludusavi::{Lookup, Manifest};
+use checkpoint_lib::operation::Operation;
+use checkpoint_lib::sessions::SessionLog;
+use checkpoint_lib::snapshot::{
+    self, CreateSnapshotRequest, RestoreOptions, Snapshot, SnapshotKind, VerificationStatus,
+};
+use chrono::{Local, Utc};
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+// clap itself exits with 2 on usage errors
+const EXIT_FAILURE: u8 = 1;
+const EXIT_INCOMPLETE: u8 = 3;
+
+const EXIT_CODES: &str = "Exit codes:
+  0  success
+  1  error
+  2  invalid usage
+  3  a restore did not complete or a snapshot failed verification
+`run` exits with the game's own exit code instead.";
+
+#[derive(Parser)]
+#[command(
+    name = "checkpoint-cli",
+    version,
+    about = "Manage Checkpoint games and save snapshots without the GUI",
+    after_help = EXIT_CODES
+)]
+struct Cli {
+    /// Print machine-readable JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Passphrase of encrypted snapshots, asked for on a terminal when needed and not given
+    #[arg(
+        long,
+        global = true,
+        env = "CHECKPOINT_PASSPHRASE",
+        hide_env_values = true
+    )]
+    passphrase: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List, add and remove games
+    #[command(subcommand)]
+    Games(GamesCommand),
+    /// Create, restore and manage snapshots
+    #[command(subcommand)]
+    Snapshot(SnapshotCommand),
+    /// Encrypt new snapshots with a passphrase
+    #[command(subcommand)]
+    Encryption(EncryptionCommand),
+    /// Manage the variables save locations can start with, like $HOME
+    #[command(subcommand)]
+    Variables(VariablesCommand),
+    /// Import a .zip or .tar.zst archive as a new snapshot
+    Import {
+        /// Game id or name
+        game: String,
+        archive: PathBuf,
+        /// Snapshot name, defaults to the name the snapshot was exported with
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Export a snapshot to a .zip or .tar.zst archive
+    Export {
+        /// Game id or name
+        game: String,
+        /// Snapshot id, id prefix or name
+        snapshot: String,
+        /// Archive to write, its extension picks the format
+        output: PathBuf,
+        /// Export an encrypted snapshot. The archive isn't encrypted
+        #[arg(long)]
+        decrypt: bool,
+    },
+    /// Launch a game, snapshotting its saves before it starts and after it exits
+    ///
+    /// Meant for launcher options, e.g. `checkpoint-cli run "Elden Ring" -- %command%`.
+    /// Exits with the game's exit code.
+    Run {
+        /// Game id or name
+        game: String,
+        /// The command that starts the game
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum GamesCommand {
+    /// List all games
+    List,
+    /// Add a game
+    Add {
+        name: String,
+        /// Folder holding the game's saves
+        #[arg(required_unless_present = "manifest", conflicts_with = "manifest")]
+        save_location: Option<String>,
+        /// Ludusavi manifest to take the save locations and patterns from
+        #[arg(long, value_name = "FILE")]
+        manifest: Option<PathBuf>,
+        /// Additional save folder, e.g. `config=/home/me/.config/game`
+        #[arg(long = "save-root", value_name = "NAME=PATH")]
+        save_roots: Vec<String>,
+        /// Executable name used to detect when the game is running
+        #[arg(long)]
+        exe: Option<String>,
+        /// Only snapshot files matching this pattern
+        #[arg(long = "include", value_name = "PATTERN")]
+        include_patterns: Vec<String>,
+        /// Never snapshot files matching this pattern
+        #[arg(long = "exclude", value_name = "PATTERN")]
+        exclude_patterns: Vec<String>,
+        /// Snapshot automatically when the save folder changes
+        #[arg(long)]
+        watch: bool,
+        /// Snapshot when the game exits
+        #[arg(long)]
+        snapshot_on_exit: bool,
+        /// Snapshot when the game starts
+        #[arg(long)]
+        snapshot_on_start: bool,
+        /// Wine or Proton prefix that %APPDATA%, %LOCALAPPDATA% and %USERPROFILE%
+        /// in save locations resolve against, found automatically when left out
+        #[arg(long, value_name = "PATH")]
+        wine_prefix: Option<String>,
+    },
+    /// List games installed through Steam, Heroic and Lutris, with the save
+    /// folders found for them
+    Discover {
+        /// Add every new game a save folder was found for
+        #[arg(long)]
+        add: bool,
+        /// Ludusavi manifest to take save locations from for the games it knows
+        #[arg(long, value_name = "FILE")]
+        manifest: Option<PathBuf>,
+    },
+    /// Remove a game and all of its snapshots
+    Remove {
+        /// Game id or name
+        game: String,
+        /// Also delete pinned snapshots
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum EncryptionCommand {
+    /// Show whether a passphrase was chosen and whether it was entered
+    Status,
+    /// Encrypt new snapshots of a game, choosing the passphrase the first time
+    Enable {
+        /// Game id or name, every game without a setting of its own when left out
+        game: Option<String>,
+    },
+    /// Stop encrypting new snapshots. Existing snapshots stay encrypted
+    Disable {
+        /// Game id or name, every game without a setting of its own when left out
+        game: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum VariablesCommand {
+    /// List the built-in and custom variables
+    List,
+    /// Define a custom variable, save locations under its folder switch to it
+    Set {
+        /// Variable name, used as $NAME
+        name: String,
+        /// Absolute folder the variable stands for
+        path: String,
+    },
+    /// Remove a custom variable
+    Unset { name: String },
+}
+
+#[derive(Subcommand)]
+enum SnapshotCommand {
+    /// Snapshot a game's save folders
+    Create {
+        /// Game id or name
+        game: String,
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// List a game's snapshots, newest first
+    List {
+        /// Game id or name
+        game: String,
+    },
+    /// Restore a snapshot, backing up the current saves first
+    Restore {
+        /// Game id or name
+        game: String,
+        /// Snapshot id, id prefix or name
+        snapshot: String,
+        /// Restore even if the current saves aren't in any snapshot yet
+        #[arg(long)]
+        force: bool,
+        /// Only restore this file or directory, as listed by `snapshot files`.
+        /// Everything else in the save folders is left alone
+        #[arg(long = "file", value_name = "PATH")]
+        files: Vec<String>,
+        /// Extract the snapshot into this directory instead, leaving the save
+        /// folders alone
+        #[arg(long = "to", value_name = "DIR", conflicts_with = "files")]
+        target_dir: Option<PathBuf>,
+    },
+    /// List the files in a snapshot
+    Files {
+        /// Game id or name
+        game: String,
+        /// Snapshot id, id prefix or name
+        snapshot: String,
+    },
+    /// Show what changed between two snapshots, or since a snapshot was taken
+    Diff {
+        /// Game id or name
+        game: String,
+        /// Snapshot id, id prefix or name
+        from: String,
+        /// Snapshot to compare with, the current save folders when left out
+        to: Option<String>,
+    },
+    /// Delete a snapshot
+    Delete {
+        /// Game id or name
+        game: String,
+        /// Snapshot id, id prefix or name
+        snapshot: String,
+        /// Delete the snapshot even if it is pinned
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check a snapshot's files against their recorded checksums
+    Verify {
+        /// Game id or name
+        game: String,
+        /// Snapshot id, id prefix or name
+        snapshot: String,
+    },
+    /// Rename a snapshot
+    Rename {
+        /// Game id or name
+        game: String,
+        /// Snapshot id, id prefix or name
+        snapshot: String,
+        new_name: String,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match run(&cli) {
+        // on a terminal the passphrase is asked for instead of failing
+        Err(e) if e == crypto::LOCKED && cli.passphrase.is_none() && is_interactive() => {
+            Config::load()
+                .and_then(|config| ask_passphrase(&config.backup_location))
+                .and_then(|_| run(&cli))
+        }
+        result => result,
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            if cli.json {
+                println!("{}", serde_json::json!({ "error": e }));
+            } else {
+                eprintln!("error: {}", e);
+            }
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<ExitCode, String> {
+    let mut config = Config::load_and_migrate()?;
+
+    if let Some(passphrase) = &cli.passphrase {
+        if crypto::status(&config.backup_location)?.set_up {
+            crypto::unlock(&config.backup_location, passphrase)?;
+        }
+    }
+
+    match &cli.command {
+        Command::Games(command) => games(cli, command, &mut config),
+        Command::Snapshot(command) => snapshots(cli, command, &mut config),
+        Command::Encryption(command) => encryption(cli, command, &mut config),
+        Command::Variables(command) => variables(cli, command, &mut config),
+        Command::Import {
+            game,
+            archive,
+            name,
+        } => {
+            let game = find_game(&config, game)?;
+            let data = std::fs::read(archive)
+                .map_err(|e| format!("Failed to read {}: {}", archive.display(), e))?;
+
+            let snapshot = snapshot::import_snapshot(
+                &game.id,
+                name.as_deref(),
+                &data,
+                &config.backup_location,
+                &config.store_settings(&game.id)?,
+                &Operation::untracked(),
+            )?;
+            checkpoint_lib::retention::apply(
+                &game.id,
+                &config.retention_policy(&game.id),
+                &config.backup_location,
+            );
+
+            output(cli, &snapshot, || {
+                println!("Imported {} as {}", archive.display(), describe(&snapshot))
+            })
+        }
+        Command::Export {
+            game,
+            snapshot,
+            output: path,
+            decrypt,
+        } => {
+            let game = find_game(&config, game)?;
+            let snapshot = find_snapshot(&game.id, snapshot, &config.backup_location)?;
+            snapshot::export_snapshot(
+                &snapshot.id,
+                &game.id,
+                path,
+                &config.backup_location,
+                *decrypt,
+                &Operation::untracked(),
+            )
+            .map_err(|e| match e.as_str() {
+                snapshot::ENCRYPTED_EXPORT => format!("{}, pass --decrypt to export it anyway", e),
+                _ => e,
+            })?;
+
+            output(
+                cli,
+                &serde_json::json!({ "snapshot_id": snapshot.id, "path": path }),
+                || println!("Exported {} to {}", describe(&snapshot), path.display()),
+            )
+        }
+        Command::Run { game, command } => {
+            let game = find_game(&config, game)?;
+
+            // asked for before the game starts, not after it exits
+            if config.encryption(&game.id)
+                && !crypto::status(&config.backup_location)?.unlocked
+                && is_interactive()
+            {
+                ask_passphrase(&config.backup_location)?;
+            }
+
+            let before = launch_snapshot(&config, game, "Before playing");
+            let status = std::process::Command::new(&command[0])
+                .args(&command[1..])
+                .status()
+                .map_err(|e| format!("Failed to launch {}: {}", command[0], e))?;
+            let after = launch_snapshot(&config, game, "After playing");
+
+            let code = exit_code(&status);
+            output(
+                cli,
+                &serde_json::json!({ "exit_code": code, "before": before, "after": after }),
+                || {},
+            )?;
+            // ExitCode only carries a u8, the game's code is passed on as is
+            std::process::exit(code)
+        }
+    }
+}
+
+// a failed snapshot is reported but doesn't keep the game from starting or
+// hide its exit code
+fn launch_snapshot(config: &Config, game: &Game, label: &str) -> Option<Snapshot> {
+    let request = CreateSnapshotRequest {
+        game_id: game.id.clone(),
+        name: Some(format!(
+            "{} {}",
+            label,
+            Utc::now().format("%Y-%m-%d %H:%M:%S")
+        )),
+        kind: SnapshotKind::Auto,
+    };
+
+    // the game owns stdout, so progress goes to stderr
+    match snapshot::create_snapshot(
+        config,
+        &request,
+        session_id(&game.id),
+        &Operation::untracked(),
+    ) {
+        Ok(snapshot) => {
+            checkpoint_lib::retention::apply(
+                &game.id,
+                &config.retention_policy(&game.id),
+                &config.backup_location,
+            );
+            eprintln!("checkpoint: created {}", describe(&snapshot));
+            Some(snapshot)
+        }
+        Err(e) => {
+            eprintln!("checkpoint: snapshot of {} failed: {}", game.name, e);
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+fn exit_code(status: &std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    // a game killed by a signal exits like it would from a shell
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(EXIT_FAILURE.into())
+}
+
+#[cfg(not(unix))]
+fn exit_code(status: &std::process::ExitStatus) -> i32 {
+    status.code().unwrap_or(EXIT_FAILURE.into())
+}
+
+fn is_interactive() -> bool {
+    std::io::stdin().is_terminal()
+}
+
+fn ask_passphrase(backup_location: &str) -> Result<String, String> {
+    let passphrase = rpassword::prompt_password("Passphrase: ")
+        .map_err(|e| format!("Failed to read passphrase: {}", e))?;
+    crypto::unlock(backup_location, &passphrase)?;
+    Ok(passphrase)
+}
+
+fn encryption(
+    cli: &Cli,
+    command: &EncryptionCommand,
+    config: &mut Config,
+) -> Result<ExitCode, String> {
+    let (game, enabled) = match command {
+        EncryptionCommand::Status => {
+            let status = crypto::status(&config.backup_location)?;
+            return output(cli, &status, || {
+                println!(
+                    "{}",
+                    match (status.set_up, status.unlocked) {
+                        (false, _) => "No passphrase chosen yet",
+                        (true, false) => "Locked, the passphrase wasn't entered",
+                        (true, true) => "Unlocked",
+                    }
+                )
+            });
+        }
+        EncryptionCommand::Enable { game } => (game, true),
+        EncryptionCommand::Disable { game } => (game, false),
+    };
+
+    if enabled {
+        let passphrase = match &cli.passphrase {
+            Some(passphrase) => passphrase.clone(),
+            None if crypto::status(&config.backup_location)?.set_up => {
+                ask_passphrase(&config.backup_location)?
+            }
+            None => {
+                let passphrase = rpassword::prompt_password("New passphrase: ")
+                    .map_err(|e| format!("Failed to read passphrase: {}", e))?;
+                let repeated = rpassword::prompt_password("Repeat passphrase: ")
+                    .map_err(|e| format!("Failed to read passphrase: {}", e))?;
+                if passphrase != repeated {
+                    return Err("Passphrases don't match".to_string());
+                }
+                passphrase
+            }
+        };
+        crypto::set_up(&config.backup_location, &passphrase)?;
+    }
+
+    let target = match game {
+        Some(game) => {
+            let game_id = find_game(config, game)?.id.clone();
+            let game = config
+                .games
+                .iter_mut()
+                .find(|g| g.id == game_id)
+                .ok_or("Game not found")?;
+            game.encryption = Some(enabled);
+            game.name.clone()
+        }
+        None => {
+            config.encryption = enabled;
+            "every game".to_string()
+        }
+    };
+    config.save()?;
+
+    output(cli, &serde_json::json!({ "encryption": enabled }), || {
+        if enabled {
+            println!("New snapshots of {} are encrypted", target)
+        } else {
+            println!("New snapshots of {} are no longer encrypted", target)
+        }
+    })
+}
+
+fn variables(
+    cli: &Cli,
+    command: &VariablesCommand,
+    config: &mut Config,
+) -> Result<ExitCode, String> {
+    let mut custom = config.path_variables.clone();
+    match command {
+        VariablesCommand::List => {}
+        VariablesCommand::Set { name, path } => {
+            custom.insert(name.clone(), path.clone());
+        }
+        VariablesCommand::Unset { name } => {
+            if custom.remove(name).is_none() {
+                return Err(format!("No custom variable named {}", name));
+            }
+        }
+    }
+    if custom != config.path_variables {
+        config.set_path_variables(custom)?;
+        config.save()?;
+    }
+
+    let variables = config.path_variables();
+    output(cli, &variables.list(), || {
+        for variable in variables.list() {
+            let note = if variable.custom { "  (custom)" } else { "" };
+            println!("${}  {}{}", variable.name, variable.path, note);
+        }
+    })
+}
+
+fn games(cli: &Cli, command: &GamesCommand, config: &mut Config) -> Result<ExitCode, String> {
+    match command {
+        GamesCommand::List => output(cli, &config.games, || {
+            for game in &config.games {
+                println!("{}  {}", short_id(&game.id), game.name);
+                for root in &game.save_roots {
+                    println!("    {}: {}", root.name, root.path);
+                }
+            }
+        }),
+        GamesCommand::Add {
+            name,
+            save_location,
+            manifest,
+            save_roots,
+            exe,
+            include_patterns,
+            exclude_patterns,
+            watch,
+            snapshot_on_exit,
+            snapshot_on_start,
+            wine_prefix,
+        } => {
+            let mut request = AddGameRequest {
+                name: name.clone(),
+                save_location: save_location.clone().unwrap_or_default(),
+                exe_name: exe.clone(),
+                cover_image: None,
+                save_roots: Vec::new(),
+                include_patterns: include_patterns.clone(),
+                exclude_patterns: exclude_patterns.clone(),
+                wine_prefix: wine_prefix.clone(),
+                steam_app_id: None,
+                watch_saves: *watch,
+                snapshot_on_exit: *snapshot_on_exit,
+                snapshot_on_start: *snapshot_on_start,
+            };
+
+            if let Some(manifest) = manifest {
+                let found = Manifest::load(manifest)?.lookup(&Lookup {
+                    name,
+                    steam_app_id: None,
+                    install_dir: None,
+                    windows: wine_prefix.is_some()
+                        || exe
+                            .as_ref()
+                            .is_some_and(|exe| exe.to_lowercase().ends_with(".exe")),
+                })?;
+                found.apply(&mut request);
+            } else if !save_roots.is_empty() {
+                request.save_roots.push(SaveRoot {
+                    name: DEFAULT_SAVE_ROOT.to_string(),
+                    path: request.save_location.clone(),
+                });
+            }
+            for root in save_roots {
+                let (name, path) = root
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid save root '{}', expected NAME=PATH", root))?;
+                request.save_roots.push(SaveRoot {
+                    name: name.to_string(),
+                    path: path.to_string(),
+                });
+            }
+
+            let mut game = Game::from_request(request)?;
+            game.make_portable(&config.path_variables());
+
+            config.games.push(game.clone());
+            config.save()?;
+
+            output(cli, &game, || {
+                println!("Added {} ({})", game.name, short_id(&game.id))
+            })
+        }
+        GamesCommand::Discover { add, manifest } => {
+            let manifest = manifest.as_deref().map(Manifest::load).transpose()?;
+            let found = checkpoint_lib::discovery::discover_games(
+                &config.games,
+                &config.path_variables(),
+                manifest.as_ref(),
+            );
+            // on stderr, the JSON output stays parseable
+            for warning in &found.warnings {
+                eprintln!("warning: {}", warning);
+            }
+
+            if !add {
+                return output(cli, &found, || {
+                    for game in &found.games {
+                        let note = if game.already_added {
+                            "  (added)"
+                        } else if game.save_roots.is_empty() {
+                            "  (no save folder found)"
+                        } else {
+                            ""
+                        };
+                        println!(
+                            "{} {}  {}{}",
+                            game.source.label(),
+                            game.source_id,
+                            game.name,
+                            note
+                        );
+                        if let Some(prefix) = &game.wine_prefix {
+                            println!("    prefix: {}", prefix);
+                        }
+                        for root in &game.save_roots {
+                            println!("    {}: {}", root.name, root.path);
+                        }
+                    }
+                });
+            }
+
+            let mut added = found
+                .games
+                .iter()
+                .filter(|game| !game.already_added && !game.save_roots.is_empty())
+                .map(|game| Game::from_request(game.to_request()?))
+                .collect::<Result<Vec<Game>, String>>()?;
+            let variables = config.path_variables();
+            for game in &mut added {
+                game.make_portable(&variables);
+            }
+            config.games.extend(added.iter().cloned());
+            config.save()?;
+
+            output(cli, &added, || {
+                for game in &added {
+                    println!("Added {} ({})", game.name, short_id(&game.id));
+                }
+                if added.is_empty() {
+                    println!("No new games to add");
+                }
+            })
+        }
+        GamesCommand::Remove { game, force } => {
+            let game = find_game(config, game)?.clone();
+
+            if !force {
+                snapshot::ensure_nothing_pinned(Some(&game.id), &config.backup_location)?;
+            }
+
+            config.games.retain(|g| g.id != game.id);
+            config.save()?;
+
+            let mut sessions = SessionLog::load()?;
+            sessions.remove_game(&game.id);
+            sessions.save()?;
+
+            snapshot::delete_game_snapshots(&game.id, &config.backup_location)?;
+
+            output(cli, &serde_json::json!({ "removed": game.id }), || {
+                println!("Removed {}", game.name)
+            })
+        }
+    }
+}
+
+fn snapshots(
+    cli: &Cli,
+    command: &SnapshotCommand,
+    config: &mut Config,
+) -> Result<ExitCode, String> {
+    match command {
+        SnapshotCommand::Create { game, name } => {
+            let game = find_game(config, game)?;
+            let request = CreateSnapshotRequest {
+                game_id: game.id.clone(),
+                name: name.clone(),
+                kind: SnapshotKind::Manual,
+            };
+
+            let snapshot = snapshot::create_snapshot(
+                config,
+                &request,
+                session_id(&game.id),
+                &Operation::untracked(),
+            )?;
+            checkpoint_lib::retention::apply(
+                &game.id,
+                &config.retention_policy(&game.id),
+                &config.backup_location,
+            );
+
+            output(cli, &snapshot, || {
+                println!("Created {}", describe(&snapshot))
+            })
+        }
+        SnapshotCommand::List { game } => {
+            let game = find_game(config, game)?;
+            let snapshots = snapshot::list_snapshots(&game.id, &config.backup_location)?;
+
+            output(cli, &snapshots, || {
+                for snapshot in &snapshots {
+                    let mut flags = Vec::new();
+                    if snapshot.pinned {
+                        flags.push("pinned");
+                    }
+                    match snapshot.kind {
+                        SnapshotKind::Manual => {}
+                        SnapshotKind::Auto => flags.push("auto"),
+                        SnapshotKind::AutoBackup => flags.push("auto-backup"),
+                    }
+
+                    println!(
+                        "{}  {}  {:>9}  {:>5} files  {}{}",
+                        short_id(&snapshot.id),
+                        snapshot
+                            .timestamp
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M"),
+                        format_size(snapshot.size),
+                        snapshot.file_count,
+                        display_name(snapshot),
+                        if flags.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" [{}]", flags.join(", "))
+                        }
+                    );
+                }
+            })
+        }
+        SnapshotCommand::Restore {
+            game,
+            snapshot,
+            force,
+            files,
+            target_dir,
+        } => {
+            let game = find_game(config, game)?.resolved_with(&config.path_variables())?;
+            let snapshot = find_snapshot(&game.id, snapshot, &config.backup_location)?;
+
+            let result = if files.is_empty() {
+                snapshot::restore_snapshot(
+                    &snapshot.id,
+                    &game,
+                    &config.backup_location,
+                    &RestoreOptions {
+                        overwrite_unsaved: *force,
+                        target_dir: target_dir
+                            .as_ref()
+                            .map(|dir| dir.to_string_lossy().to_string()),
+                    },
+                    &config.store_settings(&game.id)?,
+                    session_id(&game.id),
+                    &Operation::untracked(),
+                )?
+            } else {
+                snapshot::restore_files(
+                    &snapshot.id,
+                    &game,
+                    files,
+                    &config.backup_location,
+                    &config.store_settings(&game.id)?,
+                    session_id(&game.id),
+                    &Operation::untracked(),
+                )?
+            };
+            if result.backed_up_current {
+                checkpoint_lib::retention::apply(
+                    &game.id,
+                    &config.retention_policy(&game.id),
+                    &config.backup_location,
+                );
+            }
+
+            if result.success && target_dir.is_none() {
+                if let Some(game) = config.games.iter_mut().find(|g| g.id == game.id) {
+                    game.last_restored_snapshot_id = Some(snapshot.id.clone());
+                    game.last_restored_at = Some(Utc::now());
+                }
+                config.save()?;
+            }
+
+            output(cli, &result, || {
+                println!("{}", result.message);
+                if let Some(changes) = &result.unsaved_changes {
+                    print_diff(changes);
+                    println!("Run again with --force to restore anyway.");
+                }
+            })?;
+            Ok(if result.success {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(EXIT_INCOMPLETE)
+            })
+        }
+        SnapshotCommand::Files { game, snapshot } => {
+            let game = find_game(config, game)?;
+            let snapshot = find_snapshot(&game.id, snapshot, &config.backup_location)?;
+            let files = snapshot::list_snapshot_files(game, &snapshot.id, &config.backup_location)?;
+
+            output(cli, &files, || {
+                for file in &files {
+                    println!("{:>9}  {}", format_size(file.size), file.path);
+                }
+            })
+        }
+        SnapshotCommand::Diff { game, from, to } => {
+            let game = &find_game(config, game)?.resolved_with(&config.path_variables())?;
+            let from = find_snapshot(&game.id, from, &config.backup_location)?;
+
+            let diff = match to {
+                Some(to) => {
+                    let to = find_snapshot(&game.id, to, &config.backup_location)?;
+                    diff::diff_snapshots(game, &from.id, &to.id, &config.backup_location)?
+                }
+                None => diff::diff_with_live(game, &from.id, &config.backup_location)?,
+            };
+
+            output(cli, &diff, || {
+                if diff.is_empty() {
+                    println!("No changes");
+                } else {
+                    print_diff(&diff);
+                }
+            })
+        }
+        SnapshotCommand::Delete {
+            game,
+            snapshot,
+            force,
+        } => {
+            let game = find_game(config, game)?;
+            let snapshot = find_snapshot(&game.id, snapshot, &config.backup_location)?;
+            snapshot::delete_snapshot(&snapshot.id, &game.id, &config.backup_location, *force)?;
+
+            output(cli, &serde_json::json!({ "deleted": snapshot.id }), || {
+                println!("Deleted {}", describe(&snapshot))
+            })
+        }
+        SnapshotCommand::Verify { game, snapshot } => {
+            let game = find_game(config, game)?;
+            let snapshot = find_snapshot(&game.id, snapshot, &config.backup_location)?;
+            let report =
+                snapshot::verify_snapshot(&snapshot.id, &game.id, &config.backup_location)?;
+
+            output(cli, &report, || {
+                match report.status {
+                    VerificationStatus::Valid => {
+                        println!("{} files verified, no problems found", report.checked_files)
+                    }
+                    VerificationStatus::Unverifiable => {
+                        println!("Snapshot predates checksums and can't be verified")
+                    }
+                    VerificationStatus::Corrupted => {
+                        println!("Snapshot is damaged:")
+                    }
+                }
+                for path in &report.missing {
+                    println!("  missing    {}", path);
+                }
+                for path in &report.corrupted {
+                    println!("  corrupted  {}", path);
+                }
+                for path in &report.extra {
+                    println!("  unexpected {}", path);
+                }
+            })?;
+            Ok(if report.status == VerificationStatus::Corrupted {
+                ExitCode::from(EXIT_INCOMPLETE)
+            } else {
+                ExitCode::SUCCESS
+            })
+        }
+        SnapshotCommand::Rename {
+            game,
+            snapshot,
+            new_name,
+        } => {
+            let game = find_game(config, game)?;
+            let snapshot = find_snapshot(&game.id, snapshot, &config.backup_location)?;
+            snapshot::rename_snapshot(&snapshot.id, &game.id, new_name, &config.backup_location)?;
+
+            output(
+                cli,
+                &serde_json::json!({ "snapshot_id": snapshot.id, "name": new_name }),
+                || println!("Renamed {} to {}", describe(&snapshot), new_name),
+            )
+        }
+    }
+}
+
+// prints `value` as JSON, or runs `human` for the text output
+fn output<T: Serialize>(cli: &Cli, value: &T, human: impl FnOnce()) -> Result<ExitCode, String> {
+    if cli.json {
+        let json = serde_json::to_string_pretty(value)
+            .map_err(|e| format!("Failed to serialize output: {}", e))?;
+        println!("{}", json);
+    } else {
+        human();
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Finds a game by id or, case-insensitively, by name.
+fn find_game<'a>(config: &'a Config, query: &str) -> Result<&'a Game, String> {
+    if let Some(game) = config.games.iter().find(|g| g.id == query) {
+        return Ok(game);
+    }
+
+    let matches: Vec<&Game> = config
+        .games
+        .iter()
+        .filter(|g| g.name.eq_ignore_ascii_case(query))
+        .collect();
+
+    match matches.as_slice() {
+        [game] => Ok(game),
+        [] => Err(format!("No game named '{}'", query)),
+        _ => Err(format!(
+            "Several games are named '{}', use the game id instead",
+            query
+        )),
+    }
+}
+
+/// Finds a snapshot by id, unique id prefix or name.
+fn find_snapshot(game_id: &str, query: &str, backup_location: &str) -> Result<Snapshot, String> {
+    let snapshots = snapshot::list_snapshots(game_id, backup_location)?;
+
+    if let Some(snapshot) = snapshots.iter().find(|s| s.id == query) {
+        return Ok(snapshot.clone());
+    }
+
+    let mut matches: Vec<&Snapshot> = snapshots
+        .iter()
+        .filter(|s| s.id.starts_with(query))
+        .collect();
+    if matches.is_empty() {
+        matches = snapshots.iter().filter(|s| s.name == query).collect();
+    }
+
+    match matches.as_slice() {
+        [snapshot] => Ok((*snapshot).clone()),
+        [] => Err(format!("No snapshot matches '{}'", query)),
+        _ => Err(format!(
+            "'{}' matches {} snapshots, use a longer id",
+            query,
+            matches.len()
+        )),
+    }
+}
+
+// the play session recorded by the app that a snapshot taken now belongs to
+fn session_id(game_id: &str) -> Option<String> {
+    let log = SessionLog::load().ok()?;
+    log.session_at(game_id, Utc::now()).map(|s| s.id.clone())
+}
+
+fn print_diff(diff: &SnapshotDiff) {
+    for change in &diff.changes {
+        let marker = match change.change {
+            ChangeKind::Added => '+',
+            ChangeKind::Removed => '-',
+            ChangeKind::Modified => '~',
+        };
+        println!(
+            "  {} {}  ({})",
+            marker,
+            change.path,
+            format_delta(change.size_delta)
+        );
+    }
+    println!(
+        "{} added, {} removed, {} modified, {} unchanged ({})",
+        diff.added,
+        diff.removed,
+        diff.modified,
+        diff.unchanged,
+        format_delta(diff.size_delta)
+    );
+}
+
+fn describe(snapshot: &Snapshot) -> String {
+    format!("'{}' ({})", display_name(snapshot), short_id(&snapshot.id))
+}
+
+fn display_name(snapshot: &Snapshot) -> &str {
+    if snapshot.locked {
+        "<encrypted>"
+    } else {
+        &snapshot.name
+    }
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn format_delta(bytes: i64) -> String {
+    let sign = if bytes < 0 { '-' } else { '+' };
+    format!("{}{}", sign, format_size(bytes.unsigned_abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
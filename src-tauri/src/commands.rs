@@ -1,10 +1,71 @@
 use crate::config::Config;
+use crate::crypto::EncryptionStatus;
+use crate::diff::SnapshotDiff;
+use crate::discovery::{DiscoveredGame, Discovery};
+use crate::filter::{FilterPreview, PreviewFile};
 use crate::game::{AddGameRequest, Game, UpdateGameRequest};
-use crate::snapshot::{CreateSnapshotRequest, RestoreResult, Snapshot};
+use crate::ludusavi::{Lookup, Manifest, ManifestMatch};
+use crate::monitor::{MonitorSettings, ProcessMonitor};
+use crate::operation::Operation;
+use crate::paths::PathVariable;
+use crate::retention::{RetentionPlan, RetentionPolicy};
+use crate::sessions::{PlaytimeSummary, Session, SessionLog};
+use crate::snapshot::{
+    CreateSnapshotRequest, RestoreOptions, RestoreResult, Snapshot, VerificationReport,
+};
+use crate::store::CompressionSettings;
+use crate::watcher::{SaveWatcher, WatchPause, WatcherSettings};
 use crate::AppState;
 use base64::{engine::general_purpose, Engine as _};
 use chrono::Utc;
-use tauri::{AppHandle, State};
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+// registers a cancellable operation whose progress is emitted to the frontend
+fn track_operation(
+    app: &AppHandle,
+    state: &State<'_, AppState>,
+    operation_id: Option<&str>,
+) -> Result<Operation, String> {
+    let Some(operation_id) = operation_id else {
+        return Ok(Operation::untracked());
+    };
+
+    let app = app.clone();
+    let operation = Operation::new(operation_id).with_reporter(move |progress| {
+        let _ = app.emit("operation-progress", progress);
+    });
+
+    let mut operations = state.operations.lock().map_err(|e| e.to_string())?;
+    operations.insert(operation_id.to_string(), operation.cancel_flag());
+
+    Ok(operation)
+}
+
+fn untrack_operation(state: &State<'_, AppState>, operation_id: Option<&str>) {
+    if let (Some(operation_id), Ok(mut operations)) = (operation_id, state.operations.lock()) {
+        operations.remove(operation_id);
+    }
+}
+
+// the watcher and the process monitor read the config from disk, let them
+// pick up changes to games and settings
+fn refresh_background_tasks(app: &AppHandle) {
+    if let Some(watcher) = app.try_state::<SaveWatcher>() {
+        watcher.refresh();
+    }
+    if let Some(monitor) = app.try_state::<ProcessMonitor>() {
+        monitor.refresh();
+    }
+}
+
+// the game's save folders are left alone by the watcher while Checkpoint itself
+// writes to them, until the pause is dropped
+fn pause_watcher(app: &AppHandle, game_id: &str) -> Option<WatchPause> {
+    app.try_state::<SaveWatcher>()
+        .map(|watcher| watcher.pause(game_id))
+}
 
 #[tauri::command]
 pub fn get_config(state: State<AppState>) -> Result<Config, String> {
@@ -13,24 +74,81 @@ pub fn get_config(state: State<AppState>) -> Result<Config, String> {
 }
 
 #[tauri::command]
-pub fn set_backup_location(path: String, state: State<AppState>) -> Result<(), String> {
+pub fn set_backup_location(
+    path: String,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<(), String> {
     let mut config = state.config.lock().map_err(|e| e.to_string())?;
     config.backup_location = path;
     config.save()?;
+    refresh_background_tasks(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_watcher_settings(
+    settings: WatcherSettings,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<(), String> {
+    let mut config = state.config.lock().map_err(|e| e.to_string())?;
+    config.watcher = settings;
+    config.save()?;
+    refresh_background_tasks(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_path_variables(state: State<AppState>) -> Result<Vec<PathVariable>, String> {
+    let config = state.config.lock().map_err(|e| e.to_string())?;
+    Ok(config.path_variables().list().to_vec())
+}
+
+/// Replaces the custom path variables. Save locations under a new variable's
+/// folder switch to using it.
+#[tauri::command]
+pub fn set_path_variables(
+    variables: BTreeMap<String, String>,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<PathVariable>, String> {
+    let mut config = state.config.lock().map_err(|e| e.to_string())?;
+    config.set_path_variables(variables)?;
+    config.save()?;
+    refresh_background_tasks(&app);
+    Ok(config.path_variables().list().to_vec())
+}
+
+#[tauri::command]
+pub fn set_monitor_settings(
+    settings: MonitorSettings,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<(), String> {
+    let mut config = state.config.lock().map_err(|e| e.to_string())?;
+    config.monitor = settings;
+    config.save()?;
+    refresh_background_tasks(&app);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn add_game(request: AddGameRequest, state: State<'_, AppState>) -> Result<Game, String> {
+pub async fn add_game(
+    request: AddGameRequest,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Game, String> {
     println!("request: {:?}", request);
     let backup_location = {
         let config = state.config.lock().map_err(|e| e.to_string())?;
         config.backup_location.clone()
     };
 
-    let mut game = Game::new(request.name, request.save_location, request.exe_name, None);
+    let cover_image = request.cover_image.clone();
+    let mut game = Game::from_request(request)?;
 
-    if let Some(cover_data) = request.cover_image {
+    if let Some(cover_data) = cover_image {
         let base64_data = if cover_data.contains(',') {
             cover_data
                 .split(',')
@@ -61,9 +179,11 @@ pub async fn add_game(request: AddGameRequest, state: State<'_, AppState>) -> Re
 
     {
         let mut config = state.config.lock().map_err(|e| e.to_string())?;
+        game.make_portable(&config.path_variables());
         config.games.push(game.clone());
         config.save()?;
     }
+    refresh_background_tasks(&app);
 
     if let Some(ref cover) = game.cover_image {
         if cover == "cover.png" {
@@ -77,6 +197,79 @@ pub async fn add_game(request: AddGameRequest, state: State<'_, AppState>) -> Re
     Ok(game)
 }
 
+/// Games installed by the supported launchers. With a Ludusavi manifest, the
+/// save locations it knows replace the guessed ones.
+#[tauri::command]
+pub async fn discover_games(
+    manifest_path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Discovery, String> {
+    let (games, variables) = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        (config.games.clone(), config.path_variables())
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let manifest = manifest_path
+            .map(|path| Manifest::load(std::path::Path::new(&path)))
+            .transpose()?;
+        Ok(crate::discovery::discover_games(
+            &games,
+            &variables,
+            manifest.as_ref(),
+        ))
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?
+}
+
+/// Looks a game up in a Ludusavi manifest for its save locations and patterns,
+/// to fill in the add game form.
+#[tauri::command]
+pub async fn lookup_save_manifest(
+    manifest_path: String,
+    name: String,
+    steam_app_id: Option<String>,
+    windows: bool,
+) -> Result<ManifestMatch, String> {
+    tokio::task::spawn_blocking(move || {
+        Manifest::load(std::path::Path::new(&manifest_path))?.lookup(&Lookup {
+            name: &name,
+            steam_app_id: steam_app_id.as_deref(),
+            install_dir: None,
+            windows,
+        })
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?
+}
+
+/// Adds the discovered games the user accepted, with the save roots they confirmed.
+#[tauri::command]
+pub fn add_discovered_games(
+    games: Vec<DiscoveredGame>,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<Game>, String> {
+    let mut added = games
+        .iter()
+        .map(|discovered| Game::from_request(discovered.to_request()?))
+        .collect::<Result<Vec<Game>, String>>()?;
+
+    {
+        let mut config = state.config.lock().map_err(|e| e.to_string())?;
+        let variables = config.path_variables();
+        for game in &mut added {
+            game.make_portable(&variables);
+        }
+        config.games.extend(added.iter().cloned());
+        config.save()?;
+    }
+    refresh_background_tasks(&app);
+
+    Ok(added)
+}
+
 #[tauri::command]
 pub fn list_games(state: State<AppState>) -> Result<Vec<Game>, String> {
     let config = state.config.lock().map_err(|e| e.to_string())?;
@@ -99,22 +292,33 @@ pub fn list_games(state: State<AppState>) -> Result<Vec<Game>, String> {
 }
 
 #[tauri::command]
-pub fn delete_game(game_id: String, state: State<AppState>) -> Result<(), String> {
+pub fn delete_game(
+    game_id: String,
+    force: Option<bool>,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<(), String> {
     let mut config = state.config.lock().map_err(|e| e.to_string())?;
+
+    if !force.unwrap_or(false) {
+        crate::snapshot::ensure_nothing_pinned(Some(&game_id), &config.backup_location)?;
+    }
+
     config.games.retain(|g| g.id != game_id);
     config.save()?;
+    refresh_background_tasks(&app);
 
-    let snapshot_path = std::path::Path::new(&config.backup_location).join(&game_id);
-    if snapshot_path.exists() {
-        std::fs::remove_dir_all(&snapshot_path).map_err(|e| e.to_string())?;
-    }
+    let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+    sessions.remove_game(&game_id);
+    sessions.save()?;
 
-    Ok(())
+    crate::snapshot::delete_game_snapshots(&game_id, &config.backup_location)
 }
 
 #[tauri::command]
 pub async fn update_game(
     request: UpdateGameRequest,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<Game, String> {
     let backup_location = {
@@ -130,15 +334,49 @@ pub async fn update_game(
         .position(|g| g.id == request.game_id)
         .ok_or("Game not found")?;
 
+    if let Some(save_roots) = &request.save_roots {
+        crate::game::validate_save_roots(save_roots)?;
+    }
+    crate::filter::FileFilter::new(
+        request
+            .include_patterns
+            .as_ref()
+            .unwrap_or(&config.games[game_index].include_patterns),
+        request
+            .exclude_patterns
+            .as_ref()
+            .unwrap_or(&config.games[game_index].exclude_patterns),
+    )?;
+
     if let Some(name) = request.name {
         config.games[game_index].name = name;
     }
-    if let Some(save_location) = request.save_location {
-        config.games[game_index].save_location = save_location;
+    if let Some(save_roots) = request.save_roots {
+        config.games[game_index].set_save_roots(save_roots)?;
+    } else if let Some(save_location) = request.save_location {
+        config.games[game_index].set_save_location(save_location);
     }
     if let Some(exe_name) = request.exe_name {
         config.games[game_index].exe_name = Some(exe_name);
     }
+    if let Some(include_patterns) = request.include_patterns {
+        config.games[game_index].include_patterns = include_patterns;
+    }
+    if let Some(exclude_patterns) = request.exclude_patterns {
+        config.games[game_index].exclude_patterns = exclude_patterns;
+    }
+    if let Some(wine_prefix) = request.wine_prefix {
+        config.games[game_index].wine_prefix = Some(wine_prefix).filter(|p| !p.is_empty());
+    }
+    if let Some(watch_saves) = request.watch_saves {
+        config.games[game_index].watch_saves = watch_saves;
+    }
+    if let Some(snapshot_on_exit) = request.snapshot_on_exit {
+        config.games[game_index].snapshot_on_exit = snapshot_on_exit;
+    }
+    if let Some(snapshot_on_start) = request.snapshot_on_start {
+        config.games[game_index].snapshot_on_start = snapshot_on_start;
+    }
 
     if let Some(cover_data) = request.cover_image {
         let game_id = config.games[game_index].id.clone();
@@ -180,8 +418,11 @@ pub async fn update_game(
         }
     }
 
+    let variables = config.path_variables();
+    config.games[game_index].make_portable(&variables);
     let mut updated_game = config.games[game_index].clone();
     config.save()?;
+    refresh_background_tasks(&app);
 
     if let Some(ref cover) = updated_game.cover_image {
         if cover == "cover.png" {
@@ -198,18 +439,32 @@ pub async fn update_game(
 #[tauri::command]
 pub async fn create_snapshot(
     request: CreateSnapshotRequest,
+    operation_id: Option<String>,
+    passphrase: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<Snapshot, String> {
-    let backup_location = {
-        let config = state.config.lock().map_err(|e| e.to_string())?;
-        config.backup_location.clone()
-    };
+    let config = state.config.lock().map_err(|e| e.to_string())?.clone();
+    let sessions = state.sessions.clone();
+    let operation = track_operation(&app, &state, operation_id.as_deref())?;
 
-    tokio::task::spawn_blocking(move || {
-        crate::snapshot::create_snapshot(&request, &backup_location)
+    let result = tokio::task::spawn_blocking(move || {
+        let backup_location = &config.backup_location;
+        unlock_with(passphrase.as_deref(), backup_location)?;
+        let session_id = crate::sessions::current_session(&sessions, &request.game_id);
+        let snapshot = crate::snapshot::create_snapshot(&config, &request, session_id, &operation)?;
+        crate::retention::apply(
+            &request.game_id,
+            &config.retention_policy(&request.game_id),
+            backup_location,
+        );
+        Ok(snapshot)
     })
     .await
-    .map_err(|e| format!("Task failed: {}", e))?
+    .map_err(|e| format!("Task failed: {}", e));
+
+    untrack_operation(&state, operation_id.as_deref());
+    result?
 }
 
 #[tauri::command]
@@ -222,30 +477,58 @@ pub fn list_snapshots(game_id: String, state: State<AppState>) -> Result<Vec<Sna
 pub async fn restore_snapshot(
     snapshot_id: String,
     game_id: String,
+    operation_id: Option<String>,
+    options: Option<RestoreOptions>,
+    passphrase: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<RestoreResult, String> {
-    let (game, backup_location) = {
+    let (game, config) = {
         let config = state.config.lock().map_err(|e| e.to_string())?;
         let game = config
             .games
             .iter()
             .find(|g| g.id == game_id)
-            .cloned()
-            .ok_or("Game not found")?;
-        (game, config.backup_location.clone())
+            .ok_or("Game not found")?
+            .resolved_with(&config.path_variables())?;
+        (game, config.clone())
     };
+    let backup_location = config.backup_location.clone();
+    let policy = config.retention_policy(&game_id);
+    let sessions = state.sessions.clone();
 
-    tokio::task::spawn_blocking(move || {
-        crate::snapshot::restore_snapshot(&snapshot_id, &game, &backup_location)
+    let operation = track_operation(&app, &state, operation_id.as_deref())?;
+    let pause = pause_watcher(&app, &game_id);
+
+    let result = tokio::task::spawn_blocking(move || {
+        unlock_with(passphrase.as_deref(), &backup_location)?;
+        let result = crate::snapshot::restore_snapshot(
+            &snapshot_id,
+            &game,
+            &backup_location,
+            &options.unwrap_or_default(),
+            &config.store_settings(&game.id)?,
+            crate::sessions::current_session(&sessions, &game.id),
+            &operation,
+        )?;
+        if result.backed_up_current {
+            crate::retention::apply(&game.id, &policy, &backup_location);
+        }
+        Ok(result)
     })
     .await
-    .map_err(|e| format!("Task failed: {}", e))?
+    .map_err(|e| format!("Task failed: {}", e));
+
+    drop(pause);
+    untrack_operation(&state, operation_id.as_deref());
+    result?
 }
 
 #[tauri::command]
 pub async fn delete_snapshot(
     snapshot_id: String,
     game_id: String,
+    force: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
     let backup_location = {
@@ -254,7 +537,12 @@ pub async fn delete_snapshot(
     };
 
     tokio::task::spawn_blocking(move || {
-        crate::snapshot::delete_snapshot(&snapshot_id, &game_id, &backup_location)
+        crate::snapshot::delete_snapshot(
+            &snapshot_id,
+            &game_id,
+            &backup_location,
+            force.unwrap_or(false),
+        )
     })
     .await
     .map_err(|e| format!("Task failed: {}", e))?
@@ -272,13 +560,322 @@ pub fn rename_snapshot(
 }
 
 #[tauri::command]
-pub fn verify_snapshot(
+pub fn set_snapshot_pinned(
+    snapshot_id: String,
+    game_id: String,
+    pinned: bool,
+    state: State<AppState>,
+) -> Result<(), String> {
+    let config = state.config.lock().map_err(|e| e.to_string())?;
+    crate::snapshot::set_snapshot_pinned(&snapshot_id, &game_id, pinned, &config.backup_location)
+}
+
+#[tauri::command]
+pub async fn verify_snapshot(
+    snapshot_id: String,
+    game_id: String,
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<VerificationReport, String> {
+    let backup_location = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        config.backup_location.clone()
+    };
+
+    tokio::task::spawn_blocking(move || {
+        unlock_with(passphrase.as_deref(), &backup_location)?;
+        crate::snapshot::verify_snapshot(&snapshot_id, &game_id, &backup_location)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?
+}
+
+/// Restores only the given files or directories of a snapshot.
+#[tauri::command]
+pub async fn restore_snapshot_files(
+    snapshot_id: String,
+    game_id: String,
+    paths: Vec<String>,
+    operation_id: Option<String>,
+    passphrase: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<RestoreResult, String> {
+    let (game, backup_location) = game_and_backup_location(&game_id, &state)?;
+    let config = state.config.lock().map_err(|e| e.to_string())?.clone();
+    let policy = config.retention_policy(&game_id);
+    let sessions = state.sessions.clone();
+    let operation = track_operation(&app, &state, operation_id.as_deref())?;
+    let pause = pause_watcher(&app, &game_id);
+
+    let result = tokio::task::spawn_blocking(move || {
+        unlock_with(passphrase.as_deref(), &backup_location)?;
+        let result = crate::snapshot::restore_files(
+            &snapshot_id,
+            &game,
+            &paths,
+            &backup_location,
+            &config.store_settings(&game.id)?,
+            crate::sessions::current_session(&sessions, &game.id),
+            &operation,
+        )?;
+        if result.backed_up_current {
+            crate::retention::apply(&game.id, &policy, &backup_location);
+        }
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e));
+
+    drop(pause);
+    untrack_operation(&state, operation_id.as_deref());
+    result?
+}
+
+#[tauri::command]
+pub async fn list_snapshot_files(
+    snapshot_id: String,
+    game_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<PreviewFile>, String> {
+    let (game, backup_location) = game_and_backup_location(&game_id, &state)?;
+
+    tokio::task::spawn_blocking(move || {
+        crate::snapshot::list_snapshot_files(&game, &snapshot_id, &backup_location)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?
+}
+
+/// Lists what changed from one snapshot of a game to another.
+#[tauri::command]
+pub async fn diff_snapshots(
+    game_id: String,
+    from_snapshot_id: String,
+    to_snapshot_id: String,
+    state: State<'_, AppState>,
+) -> Result<SnapshotDiff, String> {
+    let (game, backup_location) = game_and_backup_location(&game_id, &state)?;
+
+    tokio::task::spawn_blocking(move || {
+        crate::diff::diff_snapshots(&game, &from_snapshot_id, &to_snapshot_id, &backup_location)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?
+}
+
+/// Lists what changed in the save folders since the snapshot was taken.
+#[tauri::command]
+pub async fn diff_with_current_save(
+    game_id: String,
     snapshot_id: String,
+    state: State<'_, AppState>,
+) -> Result<SnapshotDiff, String> {
+    let (game, backup_location) = game_and_backup_location(&game_id, &state)?;
+
+    tokio::task::spawn_blocking(move || {
+        crate::diff::diff_with_live(&game, &snapshot_id, &backup_location)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?
+}
+
+// the game with its save locations resolved, and the backup location
+fn game_and_backup_location(
+    game_id: &str,
+    state: &State<'_, AppState>,
+) -> Result<(Game, String), String> {
+    let config = state.config.lock().map_err(|e| e.to_string())?;
+    let game = config
+        .games
+        .iter()
+        .find(|g| g.id == game_id)
+        .ok_or("Game not found")?
+        .resolved_with(&config.path_variables())?;
+    Ok((game, config.backup_location.clone()))
+}
+
+// commands working with encrypted snapshots can take the passphrase along instead
+// of a separate `unlock_encryption` call
+fn unlock_with(passphrase: Option<&str>, backup_location: &str) -> Result<(), String> {
+    match passphrase {
+        Some(passphrase) => crate::crypto::unlock(backup_location, passphrase),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub fn get_encryption_status(state: State<AppState>) -> Result<EncryptionStatus, String> {
+    let config = state.config.lock().map_err(|e| e.to_string())?;
+    crate::crypto::status(&config.backup_location)
+}
+
+/// Checks the passphrase and keeps the key in memory until Checkpoint exits, so
+/// encrypted snapshots can be opened and automatic snapshots encrypted.
+#[tauri::command]
+pub async fn unlock_encryption(
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let backup_location = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        config.backup_location.clone()
+    };
+
+    tokio::task::spawn_blocking(move || crate::crypto::unlock(&backup_location, &passphrase))
+        .await
+        .map_err(|e| format!("Task failed: {}", e))?
+}
+
+/// Turns encryption of new snapshots on or off for a game, or globally when
+/// `game_id` is None. A game set to None falls back to the global setting.
+/// Turning it on needs the passphrase, the first time it is chosen for the
+/// backup location. Existing snapshots are left as they are.
+#[tauri::command]
+pub async fn set_encryption(
+    game_id: Option<String>,
+    enabled: Option<bool>,
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let backup_location = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        config.backup_location.clone()
+    };
+
+    if enabled == Some(true) {
+        let passphrase = passphrase.ok_or("A passphrase is needed to turn on encryption")?;
+        tokio::task::spawn_blocking(move || crate::crypto::set_up(&backup_location, &passphrase))
+            .await
+            .map_err(|e| format!("Task failed: {}", e))??;
+    }
+
+    let mut config = state.config.lock().map_err(|e| e.to_string())?;
+
+    match game_id {
+        Some(game_id) => {
+            let game = config
+                .games
+                .iter_mut()
+                .find(|g| g.id == game_id)
+                .ok_or("Game not found")?;
+            game.encryption = enabled;
+        }
+        None => config.encryption = enabled.unwrap_or_default(),
+    }
+
+    config.save()
+}
+
+/// Sets the retention policy of a game, or the global one when `game_id` is None.
+/// A game set to None falls back to the global policy.
+#[tauri::command]
+pub fn set_retention_policy(
+    game_id: Option<String>,
+    policy: Option<RetentionPolicy>,
+    state: State<AppState>,
+) -> Result<(), String> {
+    if let Some(policy) = &policy {
+        policy.validate()?;
+    }
+
+    let mut config = state.config.lock().map_err(|e| e.to_string())?;
+
+    match game_id {
+        Some(game_id) => {
+            let game = config
+                .games
+                .iter_mut()
+                .find(|g| g.id == game_id)
+                .ok_or("Game not found")?;
+            game.retention = policy;
+        }
+        None => config.retention = policy.unwrap_or_default(),
+    }
+
+    config.save()
+}
+
+/// Sets how new snapshots of a game are stored, or the global setting when `game_id`
+/// is None. A game set to None falls back to the global setting. Existing snapshots
+/// are left as they are.
+#[tauri::command]
+pub fn set_compression(
+    game_id: Option<String>,
+    settings: Option<CompressionSettings>,
+    state: State<AppState>,
+) -> Result<(), String> {
+    if let Some(settings) = &settings {
+        settings.validate()?;
+    }
+
+    let mut config = state.config.lock().map_err(|e| e.to_string())?;
+
+    match game_id {
+        Some(game_id) => {
+            let game = config
+                .games
+                .iter_mut()
+                .find(|g| g.id == game_id)
+                .ok_or("Game not found")?;
+            game.compression = settings;
+        }
+        None => config.compression = settings.unwrap_or_default(),
+    }
+
+    config.save()
+}
+
+/// Shows which snapshots `policy` (or the game's current policy) would prune, without deleting anything.
+#[tauri::command]
+pub fn preview_retention(
     game_id: String,
+    policy: Option<RetentionPolicy>,
     state: State<AppState>,
-) -> Result<bool, String> {
+) -> Result<RetentionPlan, String> {
     let config = state.config.lock().map_err(|e| e.to_string())?;
-    crate::snapshot::verify_snapshot(&snapshot_id, &game_id, &config.backup_location)
+    let policy = policy.unwrap_or_else(|| config.retention_policy(&game_id));
+    policy.validate()?;
+
+    let snapshots = crate::snapshot::list_snapshots(&game_id, &config.backup_location)?;
+    let usage = crate::retention::DiskUsage::of(&snapshots, &config.backup_location);
+    Ok(crate::retention::plan(
+        &snapshots,
+        &usage,
+        &policy,
+        Utc::now(),
+    ))
+}
+
+#[tauri::command]
+pub async fn preview_snapshot_files(
+    save_location: String,
+    include_patterns: Vec<String>,
+    exclude_patterns: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<FilterPreview, String> {
+    let save_location = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        config.path_variables().expand(&save_location)?
+    };
+
+    tokio::task::spawn_blocking(move || {
+        crate::filter::preview(&save_location, &include_patterns, &exclude_patterns)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?
+}
+
+#[tauri::command]
+pub fn get_playtime(game_id: String, state: State<AppState>) -> Result<PlaytimeSummary, String> {
+    let sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+    Ok(sessions.summary(&game_id, Utc::now()))
+}
+
+#[tauri::command]
+pub fn list_sessions(game_id: String, state: State<AppState>) -> Result<Vec<Session>, String> {
+    let sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+    Ok(sessions.history(&game_id))
 }
 
 #[tauri::command]
@@ -298,29 +895,127 @@ pub async fn select_folder(app: AppHandle) -> Result<Option<String>, String> {
 #[tauri::command]
 pub async fn import_snapshot(
     game_id: String,
-    name: String,
+    name: Option<String>,
     file_data: Vec<u8>,
+    operation_id: Option<String>,
+    passphrase: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
 ) -> Result<Snapshot, String> {
+    let config = state.config.lock().map_err(|e| e.to_string())?.clone();
+    let backup_location = config.backup_location.clone();
+    let policy = config.retention_policy(&game_id);
+    let operation = track_operation(&app, &state, operation_id.as_deref())?;
+
+    let result = tokio::task::spawn_blocking(move || {
+        unlock_with(passphrase.as_deref(), &backup_location)?;
+        let snapshot = crate::snapshot::import_snapshot(
+            &game_id,
+            name.as_deref(),
+            &file_data,
+            &backup_location,
+            &config.store_settings(&game_id)?,
+            &operation,
+        )?;
+        crate::retention::apply(&game_id, &policy, &backup_location);
+        Ok(snapshot)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e));
+
+    untrack_operation(&state, operation_id.as_deref());
+    result?
+}
+
+#[tauri::command]
+pub fn cancel_operation(operation_id: String, state: State<AppState>) -> Result<bool, String> {
+    let operations = state.operations.lock().map_err(|e| e.to_string())?;
+
+    match operations.get(&operation_id) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::SeqCst);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[tauri::command]
+pub async fn export_snapshot_zip(
+    snapshot_id: String,
+    game_id: String,
+    decrypt: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<u8>, String> {
     let backup_location = {
         let config = state.config.lock().map_err(|e| e.to_string())?;
         config.backup_location.clone()
     };
 
     tokio::task::spawn_blocking(move || {
-        crate::snapshot::import_snapshot(&game_id, &name, &file_data, &backup_location)
+        crate::snapshot::export_snapshot_zip(
+            &snapshot_id,
+            &game_id,
+            &backup_location,
+            decrypt.unwrap_or(false),
+        )
     })
     .await
     .map_err(|e| format!("Task failed: {}", e))?
 }
 
+// each argument is a parameter of the command
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn export_snapshot(
+    snapshot_id: String,
+    game_id: String,
+    path: String,
+    operation_id: Option<String>,
+    passphrase: Option<String>,
+    decrypt: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let backup_location = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        config.backup_location.clone()
+    };
+    let operation = track_operation(&app, &state, operation_id.as_deref())?;
+
+    let result = tokio::task::spawn_blocking(move || {
+        unlock_with(passphrase.as_deref(), &backup_location)?;
+        crate::snapshot::export_snapshot(
+            &snapshot_id,
+            &game_id,
+            std::path::Path::new(&path),
+            &backup_location,
+            decrypt.unwrap_or(false),
+            &operation,
+        )
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e));
+
+    untrack_operation(&state, operation_id.as_deref());
+    result?
+}
+
 #[tauri::command]
-pub fn reset_checkpoint(state: State<AppState>) -> Result<(), String> {
+pub fn reset_checkpoint(
+    force: Option<bool>,
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<(), String> {
     let backup_location = {
         let config = state.config.lock().map_err(|e| e.to_string())?;
         config.backup_location.clone()
     };
 
+    if !force.unwrap_or(false) {
+        crate::snapshot::ensure_nothing_pinned(None, &backup_location)?;
+    }
+
     let backup_path = std::path::Path::new(&backup_location);
     if backup_path.exists() {
         if let Ok(entries) = std::fs::read_dir(backup_path) {
@@ -338,12 +1033,22 @@ pub fn reset_checkpoint(state: State<AppState>) -> Result<(), String> {
         config.games.clear();
         config.save()?;
     }
+    refresh_background_tasks(&app);
+    {
+        let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+        *sessions = SessionLog::default();
+        sessions.save()?;
+    }
 
     Ok(())
 }
 
 #[tauri::command]
-pub async fn open_folder(path: String) -> Result<(), String> {
+pub async fn open_folder(path: String, state: State<'_, AppState>) -> Result<(), String> {
+    let path = {
+        let config = state.config.lock().map_err(|e| e.to_string())?;
+        config.path_variables().expand(&path)?
+    };
     let path_obj = std::path::Path::new(&path);
 
     if !path_obj.exists() {
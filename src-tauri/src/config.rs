@@ -1,5 +1,11 @@
 use crate::game::Game;
+use crate::monitor::MonitorSettings;
+use crate::paths::PathVariables;
+use crate::retention::RetentionPolicy;
+use crate::store::{CompressionSettings, StoreSettings};
+use crate::watcher::WatcherSettings;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 const CONFIG_DIR: &str = ".config/checkpoint";
@@ -43,6 +49,24 @@ fn get_default_backup_location() -> String {
 pub struct Config {
     pub games: Vec<Game>,
     pub backup_location: String,
+    // applies to every game without a policy of its own
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    // applies to every game without compression settings of its own
+    #[serde(default)]
+    pub compression: CompressionSettings,
+    // encrypt new snapshots of every game without a setting of its own. The
+    // passphrase is never stored, see `crypto`
+    #[serde(default)]
+    pub encryption: bool,
+    #[serde(default)]
+    pub watcher: WatcherSettings,
+    #[serde(default)]
+    pub monitor: MonitorSettings,
+    // user-defined variables for save locations, on top of the built-in ones
+    // like $HOME. Name to absolute path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub path_variables: BTreeMap<String, String>,
 }
 
 impl Default for Config {
@@ -52,6 +76,12 @@ impl Default for Config {
         Self {
             games: Vec::new(),
             backup_location,
+            retention: RetentionPolicy::default(),
+            compression: CompressionSettings::default(),
+            encryption: false,
+            watcher: WatcherSettings::default(),
+            monitor: MonitorSettings::default(),
+            path_variables: BTreeMap::new(),
         }
     }
 }
@@ -61,16 +91,30 @@ impl Config {
         let config_path = Self::config_path()?;
 
         if !config_path.exists() {
-            let config = Self::default();
-            config.save()?;
-            return Ok(config);
+            return Ok(Self::default());
         }
 
         let contents = std::fs::read_to_string(&config_path)
             .map_err(|e| format!("Failed to read config: {}", e))?;
 
-        let config: Config = serde_json::from_str(&contents)
-            .map_err(|e| format!("Failed to parse config: {}", e))?;
+        serde_json::from_str(&contents).map_err(|e| format!("Failed to parse config: {}", e))
+    }
+
+    /// Loads the config when the app or the CLI starts, creating it on the first
+    /// run and bringing games saved by older versions up to date. `load` never
+    /// writes, as it is also called from background threads.
+    pub fn load_and_migrate() -> Result<Self, String> {
+        let mut migrated = !Self::config_path()?.exists();
+        let mut config = Self::load()?;
+
+        let variables = config.path_variables();
+        for game in &mut config.games {
+            migrated |= game.migrate_save_roots();
+            migrated |= game.make_portable(&variables);
+        }
+        if migrated {
+            config.save()?;
+        }
 
         Ok(config)
     }
@@ -98,9 +142,76 @@ impl Config {
         Ok(())
     }
 
-    fn config_path() -> Result<PathBuf, String> {
+    /// The game's own retention policy, or the global one when it has none.
+    pub fn retention_policy(&self, game_id: &str) -> RetentionPolicy {
+        self.games
+            .iter()
+            .find(|g| g.id == game_id)
+            .and_then(|g| g.retention.clone())
+            .unwrap_or_else(|| self.retention.clone())
+    }
+
+    /// How new snapshots of the game are stored, its own settings or the global ones.
+    pub fn compression(&self, game_id: &str) -> CompressionSettings {
+        self.games
+            .iter()
+            .find(|g| g.id == game_id)
+            .and_then(|g| g.compression.clone())
+            .unwrap_or_else(|| self.compression.clone())
+    }
+
+    /// Whether new snapshots of the game are encrypted, its own setting or the global one.
+    pub fn encryption(&self, game_id: &str) -> bool {
+        self.games
+            .iter()
+            .find(|g| g.id == game_id)
+            .and_then(|g| g.encryption)
+            .unwrap_or(self.encryption)
+    }
+
+    /// How new snapshots of the game are stored. Encrypted games need the
+    /// passphrase to have been entered.
+    pub fn store_settings(&self, game_id: &str) -> Result<StoreSettings, String> {
+        let key = if self.encryption(game_id) {
+            Some(crate::crypto::key_for(&self.backup_location)?)
+        } else {
+            None
+        };
+        Ok(StoreSettings {
+            compression: self.compression(game_id),
+            key,
+        })
+    }
+
+    /// The built-in and custom variables save locations can use.
+    pub fn path_variables(&self) -> PathVariables {
+        PathVariables::new(&self.path_variables)
+    }
+
+    /// Replaces the custom path variables, moving save locations onto the new
+    /// ones. Doesn't save.
+    pub fn set_path_variables(&mut self, custom: BTreeMap<String, String>) -> Result<(), String> {
+        for (name, path) in &custom {
+            crate::paths::validate(name, path)?;
+        }
+
+        let previous = self.path_variables();
+        self.path_variables = custom;
+        let variables = self.path_variables();
+        for game in &mut self.games {
+            game.rebase_paths(&previous, &variables);
+        }
+        Ok(())
+    }
+
+    /// Directory holding the config and the files kept alongside it.
+    pub fn config_dir() -> Result<PathBuf, String> {
         let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
 
-        Ok(home_dir.join(CONFIG_DIR).join(CONFIG_FILE))
+        Ok(home_dir.join(CONFIG_DIR))
+    }
+
+    fn config_path() -> Result<PathBuf, String> {
+        Ok(Self::config_dir()?.join(CONFIG_FILE))
     }
 }
@@ -0,0 +1,447 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Returned by everything that needs the passphrase before it has been entered.
+pub const LOCKED: &str = "Snapshots are encrypted, enter the passphrase to unlock them";
+
+const KEY_FILE: &str = ".checkpoint-key.json";
+// what the key file's check value decrypts to with the right passphrase
+const CHECK_VALUE: &[u8] = b"checkpoint";
+
+// encrypted objects start with MAGIC, the key id, a flags byte and the nonce prefix
+const MAGIC: &[u8] = b"CPE1";
+const KEY_ID_LEN: usize = 32;
+const NONCE_PREFIX_LEN: usize = 19;
+const HEADER_LEN: usize = MAGIC.len() + KEY_ID_LEN + 1 + NONCE_PREFIX_LEN;
+const FLAG_COMPRESSED: u8 = 1;
+
+// the subkey object ids are derived with is the master key's HMAC of this
+const OBJECT_ID_CONTEXT: &[u8] = b"checkpoint object ids";
+
+// objects are encrypted in chunks, each with its own authentication tag
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+
+// keys derived from a passphrase this run, by key id. They only ever live in memory
+static KEYS: Lazy<Mutex<HashMap<String, EncryptionKey>>> = Lazy::new(Default::default);
+
+#[derive(Clone)]
+pub struct EncryptionKey {
+    id: String,
+    key: Key,
+    // names encrypted objects, so their names don't give away the contents' hashes
+    object_id_key: [u8; 32],
+}
+
+impl EncryptionKey {
+    fn new(id: String, key: Key) -> Self {
+        let object_id_key = hmac_sha256(&key, OBJECT_ID_CONTEXT);
+        Self {
+            id,
+            key,
+            object_id_key,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// What an object with the given SHA-256 is stored as when encrypted with
+    /// this key: a keyed hash of it, which only the key can tie to the contents.
+    pub fn object_id(&self, hash: &str) -> String {
+        hmac_sha256(&self.object_id_key, hash.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&self.key)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EncryptionStatus {
+    // a passphrase was chosen for the backup location
+    pub set_up: bool,
+    // it was entered this run
+    pub unlocked: bool,
+}
+
+// kept next to the snapshots of a backup location. It has what's needed to derive
+// the key from the passphrase and check it, never the key itself
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    id: String,
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    check: String,
+}
+
+fn key_file_path(backup_location: &str) -> PathBuf {
+    Path::new(backup_location).join(KEY_FILE)
+}
+
+fn read_key_file(backup_location: &str) -> Result<Option<KeyFile>, String> {
+    let path = key_file_path(backup_location);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read key file: {}", e))?;
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse key file: {}", e))
+}
+
+pub fn status(backup_location: &str) -> Result<EncryptionStatus, String> {
+    let key_file = read_key_file(backup_location)?;
+
+    Ok(EncryptionStatus {
+        set_up: key_file.is_some(),
+        unlocked: key_file.is_some_and(|file| cached_key(&file.id).is_some()),
+    })
+}
+
+/// Chooses the passphrase of a backup location. When it already has one, the
+/// passphrase has to match it.
+pub fn set_up(backup_location: &str, passphrase: &str) -> Result<(), String> {
+    if read_key_file(backup_location)?.is_some() {
+        return unlock(backup_location, passphrase);
+    }
+
+    if passphrase.is_empty() {
+        return Err("Passphrase can't be empty".to_string());
+    }
+
+    let mut id = [0u8; KEY_ID_LEN / 2];
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut id);
+    OsRng.fill_bytes(&mut salt);
+
+    let params = Params::default();
+    let mut key_file = KeyFile {
+        id: id.iter().map(|b| format!("{:02x}", b)).collect(),
+        salt: general_purpose::STANDARD.encode(salt),
+        memory_kib: params.m_cost(),
+        iterations: params.t_cost(),
+        parallelism: params.p_cost(),
+        check: String::new(),
+    };
+    let key = derive_key(&key_file, passphrase)?;
+    key_file.check = seal(&key, CHECK_VALUE)?;
+
+    fs::create_dir_all(backup_location)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    let contents = serde_json::to_string_pretty(&key_file)
+        .map_err(|e| format!("Failed to serialize key file: {}", e))?;
+    fs::write(key_file_path(backup_location), contents)
+        .map_err(|e| format!("Failed to write key file: {}", e))?;
+
+    remember(key);
+    Ok(())
+}
+
+/// Derives the key of a backup location from its passphrase and keeps it in
+/// memory until Checkpoint exits.
+pub fn unlock(backup_location: &str, passphrase: &str) -> Result<(), String> {
+    let key_file = read_key_file(backup_location)?
+        .ok_or("Encryption is not set up for this backup location")?;
+    let key = derive_key(&key_file, passphrase)?;
+
+    if unseal(&key, &key_file.check).ok().as_deref() != Some(CHECK_VALUE) {
+        return Err("Wrong passphrase".to_string());
+    }
+
+    remember(key);
+    Ok(())
+}
+
+/// The key new snapshots under the backup location are encrypted with.
+pub fn key_for(backup_location: &str) -> Result<EncryptionKey, String> {
+    let key_file = read_key_file(backup_location)?
+        .ok_or("Encryption is not set up for this backup location")?;
+    cached_key(&key_file.id).ok_or_else(|| LOCKED.to_string())
+}
+
+pub fn cached_key(id: &str) -> Option<EncryptionKey> {
+    KEYS.lock().ok()?.get(id).cloned()
+}
+
+fn remember(key: EncryptionKey) {
+    if let Ok(mut keys) = KEYS.lock() {
+        keys.insert(key.id.clone(), key);
+    }
+}
+
+fn derive_key(key_file: &KeyFile, passphrase: &str) -> Result<EncryptionKey, String> {
+    let salt = general_purpose::STANDARD
+        .decode(&key_file.salt)
+        .map_err(|e| format!("Failed to parse key file: {}", e))?;
+    let params = Params::new(
+        key_file.memory_kib,
+        key_file.iterations,
+        key_file.parallelism,
+        Some(32),
+    )
+    .map_err(|e| format!("Failed to parse key file: {}", e))?;
+
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+
+    Ok(EncryptionKey::new(key_file.id.clone(), key))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Encrypts a small value, e.g. snapshot metadata, returning it base64-encoded.
+pub fn seal(key: &EncryptionKey, plaintext: &[u8]) -> Result<String, String> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = key
+        .cipher()
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "Failed to encrypt".to_string())?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(general_purpose::STANDARD.encode(sealed))
+}
+
+pub fn unseal(key: &EncryptionKey, sealed: &str) -> Result<Vec<u8>, String> {
+    let sealed = general_purpose::STANDARD
+        .decode(sealed)
+        .map_err(|e| format!("Failed to decode encrypted data: {}", e))?;
+    if sealed.len() < 24 {
+        return Err("Encrypted data is damaged".to_string());
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(24);
+    key.cipher()
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Encrypted data is damaged".to_string())
+}
+
+/// Encrypts everything written to it into `inner`. `compressed` is recorded in
+/// the header for readers to know what to expect after decrypting.
+pub fn encrypt_writer<W: Write>(
+    key: &EncryptionKey,
+    compressed: bool,
+    mut inner: W,
+) -> io::Result<EncryptWriter<W>> {
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+    OsRng.fill_bytes(&mut nonce_prefix);
+
+    inner.write_all(MAGIC)?;
+    inner.write_all(key.id.as_bytes())?;
+    inner.write_all(&[if compressed { FLAG_COMPRESSED } else { 0 }])?;
+    inner.write_all(&nonce_prefix)?;
+
+    Ok(EncryptWriter {
+        inner,
+        encryptor: EncryptorBE32::from_aead(key.cipher(), GenericArray::from_slice(&nonce_prefix)),
+        buffer: Vec::new(),
+    })
+}
+
+/// Reads the header of an encrypted object, returning a reader of its decrypted
+/// contents and whether they are compressed.
+pub fn decrypt_reader<R: Read>(mut inner: R) -> Result<(DecryptReader<R>, bool), String> {
+    let mut header = [0u8; HEADER_LEN];
+    inner
+        .read_exact(&mut header)
+        .map_err(|e| format!("Failed to read encrypted object: {}", e))?;
+
+    let (magic, rest) = header.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err("Not an encrypted object".to_string());
+    }
+
+    let (key_id, rest) = rest.split_at(KEY_ID_LEN);
+    let key = cached_key(&String::from_utf8_lossy(key_id)).ok_or(LOCKED)?;
+    let (flags, nonce_prefix) = rest.split_at(1);
+
+    let reader = DecryptReader {
+        inner,
+        decryptor: Some(DecryptorBE32::from_aead(
+            key.cipher(),
+            GenericArray::from_slice(nonce_prefix),
+        )),
+        sealed: Vec::new(),
+        plain: Vec::new(),
+        position: 0,
+    };
+    Ok((reader, flags[0] & FLAG_COMPRESSED != 0))
+}
+
+pub struct EncryptWriter<W: Write> {
+    inner: W,
+    encryptor: EncryptorBE32<XChaCha20Poly1305>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    /// Encrypts the last chunk, which is marked as such so truncation is detected.
+    pub fn finish(self) -> io::Result<W> {
+        let Self {
+            mut inner,
+            encryptor,
+            buffer,
+        } = self;
+
+        let sealed = encryptor
+            .encrypt_last(buffer.as_slice())
+            .map_err(|_| io::Error::other("Failed to encrypt"))?;
+        inner.write_all(&sealed)?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+
+        // a full chunk is only written once more data follows, the last one is
+        // encrypted differently
+        while self.buffer.len() > CHUNK_SIZE {
+            let rest = self.buffer.split_off(CHUNK_SIZE);
+            let chunk = std::mem::replace(&mut self.buffer, rest);
+            let sealed = self
+                .encryptor
+                .encrypt_next(chunk.as_slice())
+                .map_err(|_| io::Error::other("Failed to encrypt"))?;
+            self.inner.write_all(&sealed)?;
+        }
+
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct DecryptReader<R: Read> {
+    inner: R,
+    // None once the last chunk was decrypted
+    decryptor: Option<DecryptorBE32<XChaCha20Poly1305>>,
+    sealed: Vec<u8>,
+    plain: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> DecryptReader<R> {
+    // returns false when there is nothing left to decrypt
+    fn next_chunk(&mut self) -> io::Result<bool> {
+        if self.decryptor.is_none() {
+            return Ok(false);
+        }
+
+        // one byte past a full chunk tells whether another chunk follows
+        let wanted = CHUNK_SIZE + TAG_SIZE + 1;
+        (&mut self.inner)
+            .take((wanted - self.sealed.len()) as u64)
+            .read_to_end(&mut self.sealed)?;
+
+        let damaged = || io::Error::new(io::ErrorKind::InvalidData, "Encrypted object is damaged");
+
+        self.plain = if self.sealed.len() == wanted {
+            let rest = self.sealed.split_off(CHUNK_SIZE + TAG_SIZE);
+            let chunk = std::mem::replace(&mut self.sealed, rest);
+            self.decryptor
+                .as_mut()
+                .and_then(|decryptor| decryptor.decrypt_next(chunk.as_slice()).ok())
+                .ok_or_else(damaged)?
+        } else {
+            let chunk = std::mem::take(&mut self.sealed);
+            self.decryptor
+                .take()
+                .and_then(|decryptor| decryptor.decrypt_last(chunk.as_slice()).ok())
+                .ok_or_else(damaged)?
+        };
+        self.position = 0;
+
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plain.len() {
+            if !self.next_chunk()? {
+                return Ok(0);
+            }
+        }
+
+        let count = out.len().min(self.plain.len() - self.position);
+        out[..count].copy_from_slice(&self.plain[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_objects_round_trip_and_detect_tampering() {
+        let location = std::env::temp_dir().join(format!("checkpoint-crypto-{}", Uuid::new_v4()));
+        let backup_location = location.to_string_lossy().to_string();
+
+        set_up(&backup_location, "hunter2").unwrap();
+        assert!(unlock(&backup_location, "wrong").is_err());
+        unlock(&backup_location, "hunter2").unwrap();
+        let key = key_for(&backup_location).unwrap();
+
+        // several chunks, the last one partial
+        let contents: Vec<u8> = (0..CHUNK_SIZE * 2 + 100).map(|i| i as u8).collect();
+        let mut writer = encrypt_writer(&key, false, Vec::new()).unwrap();
+        writer.write_all(&contents).unwrap();
+        let encrypted = writer.finish().unwrap();
+        assert!(!encrypted
+            .windows(CHUNK_SIZE)
+            .any(|w| w == &contents[..CHUNK_SIZE]));
+
+        let (mut reader, compressed) = decrypt_reader(encrypted.as_slice()).unwrap();
+        let mut decrypted = Vec::new();
+        reader.read_to_end(&mut decrypted).unwrap();
+        assert!(!compressed);
+        assert_eq!(decrypted, contents);
+
+        // dropping the last chunk must not go unnoticed
+        let truncated = &encrypted[..HEADER_LEN + 2 * (CHUNK_SIZE + TAG_SIZE)];
+        let (mut reader, _) = decrypt_reader(truncated).unwrap();
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+        let sealed = seal(&key, b"metadata").unwrap();
+        assert_eq!(unseal(&key, &sealed).unwrap(), b"metadata");
+
+        fs::remove_dir_all(&location).unwrap();
+    }
+}
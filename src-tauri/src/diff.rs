@@ -0,0 +1,307 @@
+use crate::filter::FileFilter;
+use crate::game::Game;
+use crate::snapshot::manifest_path;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// What a diff needs to know about one file.
+#[derive(Debug, Clone)]
+pub(crate) struct FileState {
+    pub hash: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Files of a snapshot or save folder, keyed by `<save root>/<path in root>`.
+pub(crate) type FileIndex = BTreeMap<String, FileState>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub change: ChangeKind,
+    pub old_size: Option<u64>,
+    pub new_size: Option<u64>,
+    pub size_delta: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotDiff {
+    pub changes: Vec<FileChange>,
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    pub unchanged: usize,
+    pub size_delta: i64,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Lists what changed going from `old` to `new`, sorted by path.
+pub(crate) fn compare(old: &FileIndex, new: &FileIndex) -> SnapshotDiff {
+    let mut diff = SnapshotDiff {
+        changes: Vec::new(),
+        added: 0,
+        removed: 0,
+        modified: 0,
+        unchanged: 0,
+        size_delta: 0,
+    };
+
+    for (path, old_file) in old {
+        match new.get(path) {
+            None => {
+                diff.removed += 1;
+                diff.changes.push(FileChange {
+                    path: path.clone(),
+                    change: ChangeKind::Removed,
+                    old_size: Some(old_file.size),
+                    new_size: None,
+                    size_delta: -(old_file.size as i64),
+                });
+            }
+            Some(new_file) if new_file.hash != old_file.hash => {
+                diff.modified += 1;
+                diff.changes.push(FileChange {
+                    path: path.clone(),
+                    change: ChangeKind::Modified,
+                    old_size: Some(old_file.size),
+                    new_size: Some(new_file.size),
+                    size_delta: new_file.size as i64 - old_file.size as i64,
+                });
+            }
+            Some(_) => diff.unchanged += 1,
+        }
+    }
+
+    for (path, new_file) in new {
+        if !old.contains_key(path) {
+            diff.added += 1;
+            diff.changes.push(FileChange {
+                path: path.clone(),
+                change: ChangeKind::Added,
+                old_size: None,
+                new_size: Some(new_file.size),
+                size_delta: new_file.size as i64,
+            });
+        }
+    }
+
+    diff.changes.sort_by(|a, b| a.path.cmp(&b.path));
+    diff.size_delta = diff.changes.iter().map(|c| c.size_delta).sum();
+    diff
+}
+
+/// What changed from snapshot `from_id` to snapshot `to_id`.
+pub fn diff_snapshots(
+    game: &Game,
+    from_id: &str,
+    to_id: &str,
+    backup_location: &str,
+) -> Result<SnapshotDiff, String> {
+    let from = crate::snapshot::snapshot_index(game, from_id, backup_location)?;
+    let to = crate::snapshot::snapshot_index(game, to_id, backup_location)?;
+    Ok(compare(&from, &to))
+}
+
+/// What changed in the save folders since the snapshot was taken. Only files
+/// the game's filters cover are compared. The game's save locations have to be
+/// resolved already.
+pub fn diff_with_live(
+    game: &Game,
+    snapshot_id: &str,
+    backup_location: &str,
+) -> Result<SnapshotDiff, String> {
+    let filter = FileFilter::for_game(game)?;
+    let snapshot = filtered(
+        crate::snapshot::snapshot_index(game, snapshot_id, backup_location)?,
+        &filter,
+    );
+    let live = live_index(game, &filter, &snapshot)?;
+    Ok(compare(&snapshot, &live))
+}
+
+/// How the save folders compare with the snapshots of the game.
+pub enum SaveStatus {
+    /// A snapshot holds exactly the current saves, or there are no saves.
+    Saved,
+    /// No snapshot holds the current saves, with what changed since the newest one.
+    Unsaved(SnapshotDiff),
+    /// No snapshot could be read, e.g. they are all encrypted and locked.
+    Unknown,
+}
+
+/// Compares the save folders with every snapshot of the game that can be read.
+/// Snapshots that can't, like locked ones, are skipped.
+pub fn save_status(game: &Game, backup_location: &str) -> Result<SaveStatus, String> {
+    let snapshots = crate::snapshot::list_snapshots(&game.id, backup_location)?;
+    let filter = FileFilter::for_game(game)?;
+
+    // newest first
+    let indexes: Vec<FileIndex> = snapshots
+        .iter()
+        .filter(|snapshot| !snapshot.locked)
+        .filter_map(|snapshot| {
+            crate::snapshot::snapshot_index(game, &snapshot.id, backup_location).ok()
+        })
+        .map(|index| filtered(index, &filter))
+        .collect();
+    if indexes.is_empty() && !snapshots.is_empty() {
+        return Ok(SaveStatus::Unknown);
+    }
+
+    let empty = FileIndex::new();
+    let newest = indexes.first().unwrap_or(&empty);
+    let live = live_index(game, &filter, newest)?;
+
+    // e.g. an older snapshot that was just restored, the auto-backup taken
+    // before it is newer
+    if live.is_empty() || indexes.iter().any(|index| compare(index, &live).is_empty()) {
+        return Ok(SaveStatus::Saved);
+    }
+
+    Ok(SaveStatus::Unsaved(compare(newest, &live)))
+}
+
+// drops files the game's current filters don't cover, e.g. ones captured before
+// an exclude pattern was added
+fn filtered(index: FileIndex, filter: &FileFilter) -> FileIndex {
+    index
+        .into_iter()
+        .filter(|(path, _)| {
+            let relative = path.split_once('/').map_or(path.as_str(), |(_, rest)| rest);
+            filter.matches(relative)
+        })
+        .collect()
+}
+
+// files whose size and modification time match `reference` are assumed unchanged
+// and not hashed again
+fn live_index(
+    game: &Game,
+    filter: &FileFilter,
+    reference: &FileIndex,
+) -> Result<FileIndex, String> {
+    let mut index = FileIndex::new();
+
+    for root in &game.save_roots {
+        let save_path = Path::new(&root.path);
+        if !save_path.exists() {
+            continue;
+        }
+
+        for entry in WalkDir::new(save_path) {
+            let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+            let path = entry.path();
+
+            if !path.is_file() {
+                continue;
+            }
+
+            let relative_path = path
+                .strip_prefix(save_path)
+                .map_err(|e| format!("Failed to calculate relative path: {}", e))?;
+            let relative_path = manifest_path(relative_path);
+
+            if !filter.matches(&relative_path) {
+                continue;
+            }
+
+            let key = format!("{}/{}", root.name, relative_path);
+            let metadata =
+                fs::metadata(path).map_err(|e| format!("Failed to get metadata: {}", e))?;
+            let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+
+            let state = match reference.get(&key) {
+                Some(known)
+                    if known.size == metadata.len()
+                        && known.modified.is_some()
+                        && known.modified == modified =>
+                {
+                    known.clone()
+                }
+                _ => {
+                    let (hash, size) = crate::store::hash_file(path)?;
+                    FileState {
+                        hash,
+                        size,
+                        modified,
+                    }
+                }
+            };
+            index.insert(key, state);
+        }
+    }
+
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(files: &[(&str, &str, u64)]) -> FileIndex {
+        files
+            .iter()
+            .map(|(path, hash, size)| {
+                (
+                    path.to_string(),
+                    FileState {
+                        hash: hash.to_string(),
+                        size: *size,
+                        modified: None,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compare_reports_added_removed_and_modified_files() {
+        let old = index(&[
+            ("saves/slot1.sav", "a", 100),
+            ("saves/slot2.sav", "b", 50),
+            ("saves/settings.ini", "c", 10),
+        ]);
+        let new = index(&[
+            ("saves/slot1.sav", "a2", 120),
+            ("saves/settings.ini", "c", 10),
+            ("saves/slot3.sav", "d", 70),
+        ]);
+
+        let diff = compare(&old, &new);
+        let changes: Vec<(&str, ChangeKind, i64)> = diff
+            .changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.change, c.size_delta))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("saves/slot1.sav", ChangeKind::Modified, 20),
+                ("saves/slot2.sav", ChangeKind::Removed, -50),
+                ("saves/slot3.sav", ChangeKind::Added, 70),
+            ]
+        );
+        assert_eq!((diff.added, diff.removed, diff.modified), (1, 1, 1));
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.size_delta, 40);
+
+        assert!(compare(&new, &new).is_empty());
+    }
+}
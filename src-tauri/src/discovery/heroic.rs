@@ -0,0 +1,338 @@
+use super::{DiscoveredGame, GameSource};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// where Heroic keeps its config, natively and as a Flatpak, relative to the
+// home directory
+const HEROIC_CONFIGS: &[&str] = &[
+    ".config/heroic",
+    ".var/app/com.heroicgameslauncher.hgl/config/heroic",
+];
+
+// Epic games, installed through legendary
+#[derive(Deserialize)]
+struct LegendaryInstall {
+    app_name: String,
+    title: String,
+    install_path: String,
+    #[serde(default)]
+    executable: String,
+    #[serde(default)]
+    platform: String,
+    #[serde(default)]
+    is_dlc: bool,
+}
+
+#[derive(Deserialize)]
+struct GogInstalled {
+    #[serde(default)]
+    installed: Vec<GogInstall>,
+}
+
+#[derive(Deserialize)]
+struct GogInstall {
+    #[serde(rename = "appName")]
+    app_name: String,
+    install_path: String,
+    #[serde(default)]
+    platform: String,
+    #[serde(default)]
+    is_dlc: bool,
+}
+
+// GOG's installed.json has no titles, the library cache does
+#[derive(Deserialize)]
+struct GogLibrary {
+    #[serde(default)]
+    games: Vec<GogTitle>,
+}
+
+#[derive(Deserialize)]
+struct GogTitle {
+    app_name: String,
+    #[serde(default)]
+    title: String,
+}
+
+struct Install {
+    app_name: String,
+    title: String,
+    install_path: String,
+    // relative to the install folder
+    executable: Option<String>,
+    windows: bool,
+}
+
+/// Epic and GOG games of every Heroic installation of the current user.
+/// Installations that can't be read are skipped, with a warning added to `warnings`.
+pub fn discover(warnings: &mut Vec<String>) -> Vec<DiscoveredGame> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+
+    let mut games = Vec::new();
+    for config_dir in HEROIC_CONFIGS
+        .iter()
+        .map(|path| home.join(path))
+        .filter(|path| path.is_dir())
+    {
+        match scan(&config_dir, &home) {
+            Ok(found) => games.extend(found),
+            Err(e) => warnings.push(format!(
+                "Failed to scan Heroic at {}: {}",
+                config_dir.display(),
+                e
+            )),
+        }
+    }
+    games
+}
+
+/// Games installed through the Heroic whose config folder is `config_dir`.
+/// Native games' saves are looked for under `home`.
+pub fn scan(config_dir: &Path, home: &Path) -> Result<Vec<DiscoveredGame>, String> {
+    let mut installs = Vec::new();
+
+    let legendary = config_dir.join("legendaryConfig/legendary/installed.json");
+    if legendary.is_file() {
+        let games: BTreeMap<String, LegendaryInstall> = read_json(&legendary)?;
+        for game in games.into_values().filter(|game| !game.is_dlc) {
+            installs.push(Install {
+                app_name: game.app_name,
+                title: game.title,
+                install_path: game.install_path,
+                executable: Some(game.executable).filter(|exe| !exe.is_empty()),
+                windows: game.platform.eq_ignore_ascii_case("windows"),
+            });
+        }
+    }
+
+    let gog = config_dir.join("gog_store/installed.json");
+    if gog.is_file() {
+        let installed: GogInstalled = read_json(&gog)?;
+        let titles = gog_titles(config_dir);
+        for game in installed.installed.into_iter().filter(|game| !game.is_dlc) {
+            let title = titles
+                .get(&game.app_name)
+                .cloned()
+                .or_else(|| folder_name(Path::new(&game.install_path)))
+                .unwrap_or_else(|| game.app_name.clone());
+            installs.push(Install {
+                executable: gog_executable(Path::new(&game.install_path), &game.app_name),
+                windows: game.platform.eq_ignore_ascii_case("windows"),
+                app_name: game.app_name,
+                title,
+                install_path: game.install_path,
+            });
+        }
+    }
+
+    Ok(installs
+        .into_iter()
+        .map(|install| install.into_discovered(config_dir, home))
+        .collect())
+}
+
+impl Install {
+    fn into_discovered(self, config_dir: &Path, home: &Path) -> DiscoveredGame {
+        let install_dir = PathBuf::from(&self.install_path);
+        let wine_prefix = self
+            .windows
+            .then(|| wine_prefix(config_dir, home, &self.app_name, &self.title))
+            .flatten();
+
+        let exe_name = self
+            .executable
+            .as_deref()
+            .and_then(|exe| folder_name(Path::new(exe)))
+            .or_else(|| super::steam::find_executable(&install_dir));
+
+        let folder = folder_name(&install_dir).unwrap_or_default();
+        let save_roots = match &wine_prefix {
+            Some(prefix) => super::prefix_candidates(Path::new(prefix), &[&self.title, &folder]),
+            None => super::native_candidates(home, &[&folder, &self.title]),
+        };
+
+        DiscoveredGame {
+            source: GameSource::Heroic,
+            source_id: self.app_name,
+            name: self.title,
+            install_dir: self.install_path,
+            exe_name,
+            wine_prefix,
+            save_roots,
+            include_patterns: Vec::new(),
+            already_added: false,
+        }
+    }
+}
+
+// titles by app name, from whichever library cache this Heroic version writes
+fn gog_titles(config_dir: &Path) -> HashMap<String, String> {
+    ["store_cache/gog_library.json", "gog_store/library.json"]
+        .iter()
+        .map(|path| config_dir.join(path))
+        .find_map(|path| read_json::<GogLibrary>(&path).ok())
+        .map(|library| {
+            library
+                .games
+                .into_iter()
+                .filter(|game| !game.title.is_empty())
+                .map(|game| (game.app_name, game.title))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// GOG installs describe how to start the game in goggame-<id>.info
+fn gog_executable(install_dir: &Path, app_name: &str) -> Option<String> {
+    let info: Value = read_json(&install_dir.join(format!("goggame-{}.info", app_name))).ok()?;
+    let tasks = info.get("playTasks")?.as_array()?;
+    tasks
+        .iter()
+        .find(|task| task.get("isPrimary").and_then(Value::as_bool) == Some(true))
+        .or_else(|| tasks.first())?
+        .get("path")?
+        .as_str()
+        .map(str::to_string)
+}
+
+// the prefix set for the game, else the one Heroic makes for it in the default
+// prefix folder
+fn wine_prefix(config_dir: &Path, home: &Path, app_name: &str, title: &str) -> Option<String> {
+    let game_config = config_dir
+        .join("GamesConfig")
+        .join(format!("{}.json", app_name));
+    let configured = read_json::<Value>(&game_config).ok().and_then(|config| {
+        config
+            .get(app_name)?
+            .get("winePrefix")?
+            .as_str()
+            .map(|prefix| expand_home(prefix, home))
+    });
+    if configured.is_some() {
+        return configured;
+    }
+
+    let config: Value = read_json(&config_dir.join("config.json")).ok()?;
+    let default_dir = config.get("defaultSettings")?.get("winePrefix")?.as_str()?;
+    let prefix = PathBuf::from(expand_home(default_dir, home)).join(title);
+    prefix
+        .is_dir()
+        .then(|| prefix.to_string_lossy().to_string())
+}
+
+fn expand_home(path: &str, home: &Path) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => home.join(rest).to_string_lossy().to_string(),
+        None => path.to_string(),
+    }
+}
+
+fn folder_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let data = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_finds_epic_and_gog_games_with_their_prefixes() {
+        let root = std::env::temp_dir().join(format!("checkpoint-heroic-{}", uuid::Uuid::new_v4()));
+        let config_dir = root.join("heroic");
+        let home = root.join("home");
+        let write = |path: PathBuf, contents: String| {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+
+        let prefix = root.join("Prefixes/Hades");
+        fs::create_dir_all(prefix.join("drive_c/users/steamuser/Saved Games/Hades")).unwrap();
+        write(
+            config_dir.join("legendaryConfig/legendary/installed.json"),
+            serde_json::json!({
+                "Min": {
+                    "app_name": "Min",
+                    "title": "Hades",
+                    "install_path": root.join("Games/Hades"),
+                    "executable": "x64/Hades.exe",
+                    "platform": "Windows",
+                    "is_dlc": false
+                },
+                "MinDlc": {
+                    "app_name": "MinDlc",
+                    "title": "Hades Soundtrack",
+                    "install_path": root.join("Games/Hades"),
+                    "platform": "Windows",
+                    "is_dlc": true
+                }
+            })
+            .to_string(),
+        );
+        write(
+            config_dir.join("GamesConfig/Min.json"),
+            serde_json::json!({ "Min": { "winePrefix": prefix } }).to_string(),
+        );
+
+        let gog_dir = root.join("Games/Gwent");
+        fs::create_dir_all(home.join(".config/Gwent")).unwrap();
+        write(
+            config_dir.join("gog_store/installed.json"),
+            serde_json::json!({
+                "installed": [
+                    { "appName": "1971477531", "install_path": gog_dir, "platform": "linux" }
+                ]
+            })
+            .to_string(),
+        );
+        write(
+            config_dir.join("store_cache/gog_library.json"),
+            serde_json::json!({ "games": [{ "app_name": "1971477531", "title": "Gwent" }] })
+                .to_string(),
+        );
+        write(
+            gog_dir.join("goggame-1971477531.info"),
+            serde_json::json!({ "playTasks": [{ "isPrimary": true, "path": "bin/gwent" }] })
+                .to_string(),
+        );
+
+        let mut games = scan(&config_dir, &home).unwrap();
+        games.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(games.len(), 2);
+
+        let gwent = &games[0];
+        assert_eq!(gwent.name, "Gwent");
+        assert_eq!(gwent.source, GameSource::Heroic);
+        assert_eq!(gwent.exe_name.as_deref(), Some("gwent"));
+        assert_eq!(gwent.wine_prefix, None);
+        assert_eq!(
+            gwent.save_roots[0].path,
+            home.join(".config/Gwent").to_string_lossy()
+        );
+
+        let hades = &games[1];
+        assert_eq!(hades.source_id, "Min");
+        assert_eq!(hades.exe_name.as_deref(), Some("Hades.exe"));
+        assert_eq!(
+            hades.wine_prefix.as_deref(),
+            Some(prefix.to_string_lossy().as_ref())
+        );
+        assert_eq!(hades.save_roots[0].path, "%USERPROFILE%/Saved Games/Hades");
+        let request = hades.to_request().unwrap();
+        assert_eq!(request.wine_prefix, hades.wine_prefix);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
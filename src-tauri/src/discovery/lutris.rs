@@ -0,0 +1,317 @@
+use super::{DiscoveredGame, GameSource};
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// where Lutris keeps its data and config, natively and as a Flatpak, relative
+// to the home directory
+const LUTRIS_DIRS: &[(&str, &str)] = &[
+    (".local/share/lutris", ".config/lutris"),
+    (
+        ".var/app/net.lutris.Lutris/data/lutris",
+        ".var/app/net.lutris.Lutris/config/lutris",
+    ),
+];
+
+// Steam games are found through Steam itself
+const SKIPPED_RUNNERS: &[&str] = &["steam"];
+
+// a row of the games table in pga.db
+struct LutrisGame {
+    name: String,
+    slug: String,
+    runner: String,
+    directory: Option<String>,
+    configpath: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GameConfig {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    game: Option<GameSection>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GameSection {
+    #[serde(default)]
+    exe: Option<String>,
+    #[serde(default)]
+    prefix: Option<String>,
+}
+
+/// Installed games of every Lutris installation of the current user.
+/// Installations that can't be read are skipped, with a warning added to `warnings`.
+pub fn discover(warnings: &mut Vec<String>) -> Vec<DiscoveredGame> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+
+    let mut games = Vec::new();
+    for (data_dir, config_dir) in LUTRIS_DIRS
+        .iter()
+        .map(|(data, config)| (home.join(data), home.join(config)))
+        .filter(|(data, config)| data.is_dir() || config.is_dir())
+    {
+        match scan(&data_dir, &config_dir, &home, warnings) {
+            Ok(found) => games.extend(found),
+            Err(e) => warnings.push(format!(
+                "Failed to scan Lutris at {}: {}",
+                data_dir.display(),
+                e
+            )),
+        }
+    }
+    games
+}
+
+/// Installed games of the Lutris with the given data and config folders, from
+/// its pga.db database, or only from the game configs when there is none.
+/// Native games' saves are looked for under `home`. Game configs that can't be
+/// parsed are ignored, with a warning added to `warnings`.
+pub fn scan(
+    data_dir: &Path,
+    config_dir: &Path,
+    home: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<DiscoveredGame>, String> {
+    // newer versions keep the game configs with the data
+    let config_dirs = [data_dir.join("games"), config_dir.join("games")];
+
+    let database = data_dir.join("pga.db");
+    let games = if database.is_file() {
+        read_database(&database)?
+    } else {
+        config_games(&config_dirs)
+    };
+
+    Ok(games
+        .into_iter()
+        .filter(|game| !SKIPPED_RUNNERS.contains(&game.runner.as_str()))
+        .map(|game| {
+            let config = match game.configpath.as_deref() {
+                Some(configpath) => read_config(&config_dirs, configpath).unwrap_or_else(|e| {
+                    warnings.push(e);
+                    None
+                }),
+                None => None,
+            };
+            let config = config.and_then(|config| config.game).unwrap_or_default();
+            into_discovered(game, config, home)
+        })
+        .collect())
+}
+
+fn read_database(path: &Path) -> Result<Vec<LutrisGame>, String> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut statement = connection
+        .prepare("SELECT name, slug, runner, directory, configpath FROM games WHERE installed = 1")
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let games = statement
+        .query_map([], |row| {
+            Ok(LutrisGame {
+                name: row.get(0)?,
+                slug: row.get(1)?,
+                runner: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                directory: row.get(3)?,
+                configpath: row.get(4)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(games)
+}
+
+// without a database every config is an installed game, named after its slug
+// when the config has no name
+fn config_games(config_dirs: &[PathBuf]) -> Vec<LutrisGame> {
+    let mut games = Vec::new();
+    for dir in config_dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("yml") {
+                continue;
+            }
+            let Some(configpath) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            // configs are named <slug>-<timestamp>
+            let slug = configpath
+                .rsplit_once('-')
+                .map(|(slug, _)| slug.to_string())
+                .unwrap_or_else(|| configpath.clone());
+            // one that can't be parsed is reported when the game is read
+            let name = read_config(config_dirs, &configpath)
+                .ok()
+                .flatten()
+                .and_then(|config| config.name)
+                .unwrap_or_else(|| slug.replace('-', " "));
+
+            games.push(LutrisGame {
+                name,
+                slug,
+                runner: String::new(),
+                directory: None,
+                configpath: Some(configpath),
+            });
+        }
+    }
+    games
+}
+
+// None when there is no config
+fn read_config(config_dirs: &[PathBuf], configpath: &str) -> Result<Option<GameConfig>, String> {
+    let Some((path, yaml)) = config_dirs
+        .iter()
+        .map(|dir| dir.join(format!("{}.yml", configpath)))
+        .find_map(|path| Some((path.clone(), fs::read_to_string(&path).ok()?)))
+    else {
+        return Ok(None);
+    };
+
+    serde_yaml::from_str(&yaml)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn into_discovered(game: LutrisGame, config: GameSection, home: &Path) -> DiscoveredGame {
+    let wine_prefix = config
+        .prefix
+        .filter(|prefix| !prefix.is_empty())
+        .map(|prefix| match prefix.strip_prefix("~/") {
+            Some(rest) => home.join(rest).to_string_lossy().to_string(),
+            None => prefix,
+        });
+
+    let exe = config.exe.filter(|exe| !exe.is_empty());
+    let exe_name = exe.as_deref().and_then(|exe| {
+        Path::new(exe)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    });
+
+    // the game's folder, else the folder holding its executable
+    let install_dir = game
+        .directory
+        .filter(|dir| !dir.is_empty())
+        .or_else(|| {
+            let exe = Path::new(exe.as_deref()?);
+            exe.is_absolute()
+                .then(|| exe.parent())
+                .flatten()
+                .map(|dir| dir.to_string_lossy().to_string())
+        })
+        .unwrap_or_default();
+
+    let save_roots = match &wine_prefix {
+        Some(prefix) => super::prefix_candidates(Path::new(prefix), &[&game.name, &game.slug]),
+        None => super::native_candidates(home, &[&game.name, &game.slug]),
+    };
+
+    DiscoveredGame {
+        source: GameSource::Lutris,
+        source_id: game.slug,
+        name: game.name,
+        install_dir,
+        exe_name,
+        wine_prefix,
+        save_roots,
+        include_patterns: Vec::new(),
+        already_added: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_reads_the_database_and_game_configs() {
+        let root = std::env::temp_dir().join(format!("checkpoint-lutris-{}", uuid::Uuid::new_v4()));
+        let data_dir = root.join("data");
+        let config_dir = root.join("config");
+        let home = root.join("home");
+        fs::create_dir_all(config_dir.join("games")).unwrap();
+        fs::create_dir_all(&data_dir).unwrap();
+
+        let connection = Connection::open(data_dir.join("pga.db")).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE games (id INTEGER PRIMARY KEY, name TEXT, slug TEXT, runner TEXT,
+                    directory TEXT, installed INTEGER, configpath TEXT);
+                 INSERT INTO games (name, slug, runner, directory, installed, configpath) VALUES
+                    ('Diablo II', 'diablo-ii', 'wine', '/games/diablo-ii', 1, 'diablo-ii-1700000000'),
+                    ('SuperTux', 'supertux', 'linux', '', 1, 'supertux-1700000001'),
+                    ('Portal', 'portal', 'steam', '', 1, 'portal-1700000002'),
+                    ('Removed', 'removed', 'linux', '', 0, 'removed-1700000003');",
+            )
+            .unwrap();
+        drop(connection);
+
+        let prefix = root.join("prefixes/diablo-ii");
+        fs::create_dir_all(prefix.join("drive_c/users/steamuser/AppData/Roaming/Diablo II"))
+            .unwrap();
+        fs::write(
+            config_dir.join("games/diablo-ii-1700000000.yml"),
+            format!(
+                "game:\n  exe: drive_c/Diablo II/Game.exe\n  prefix: {}\nwine:\n  version: lutris-7.2\n",
+                prefix.display()
+            ),
+        )
+        .unwrap();
+        fs::write(
+            config_dir.join("games/supertux-1700000001.yml"),
+            "game:\n  exe: /usr/games/supertux2\n",
+        )
+        .unwrap();
+        fs::create_dir_all(home.join(".local/share/supertux")).unwrap();
+
+        let mut warnings = Vec::new();
+        let mut games = scan(&data_dir, &config_dir, &home, &mut warnings).unwrap();
+        games.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(games.len(), 2);
+        assert!(warnings.is_empty());
+
+        let diablo = &games[0];
+        assert_eq!(diablo.source, GameSource::Lutris);
+        assert_eq!(diablo.source_id, "diablo-ii");
+        assert_eq!(diablo.install_dir, "/games/diablo-ii");
+        assert_eq!(diablo.exe_name.as_deref(), Some("Game.exe"));
+        assert_eq!(
+            diablo.wine_prefix.as_deref(),
+            Some(prefix.to_string_lossy().as_ref())
+        );
+        assert_eq!(diablo.save_roots[0].path, "%APPDATA%/Diablo II");
+
+        let supertux = &games[1];
+        assert_eq!(supertux.exe_name.as_deref(), Some("supertux2"));
+        assert_eq!(supertux.install_dir, "/usr/games");
+        assert_eq!(supertux.wine_prefix, None);
+        assert_eq!(
+            supertux.save_roots[0].path,
+            home.join(".local/share/supertux").to_string_lossy()
+        );
+
+        // without the database the configs are the games. One that can't be
+        // parsed is still proposed, and reported once
+        fs::remove_file(data_dir.join("pga.db")).unwrap();
+        fs::write(config_dir.join("games/broken-1700000004.yml"), "game: [").unwrap();
+        let mut names: Vec<String> = scan(&data_dir, &config_dir, &home, &mut warnings)
+            .unwrap()
+            .into_iter()
+            .map(|game| game.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["broken", "diablo ii", "supertux"]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("broken-1700000004.yml"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
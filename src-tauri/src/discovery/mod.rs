@@ -0,0 +1,244 @@
+use crate::game::{AddGameRequest, Game, SaveRoot};
+use crate::ludusavi::{Lookup, Manifest};
+use crate::paths::PathVariables;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub mod heroic;
+pub mod lutris;
+pub mod steam;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameSource {
+    Steam,
+    // Epic and GOG games installed through Heroic
+    Heroic,
+    Lutris,
+}
+
+impl GameSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            GameSource::Steam => "Steam",
+            GameSource::Heroic => "Heroic",
+            GameSource::Lutris => "Lutris",
+        }
+    }
+}
+
+// folders inside a Wine prefix Windows games commonly save to, with the name
+// of the save root they become
+const PREFIX_SAVE_FOLDERS: &[(&str, &str)] = &[
+    ("documents", "%USERPROFILE%/Documents/My Games"),
+    ("documents", "%USERPROFILE%/Documents"),
+    ("saved-games", "%USERPROFILE%/Saved Games"),
+    ("appdata", "%APPDATA%"),
+    ("local-appdata", "%LOCALAPPDATA%"),
+];
+
+/// A game a launcher installed, proposed for adding. Its save roots are only
+/// guesses, the user confirms or edits them before the game is added.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredGame {
+    pub source: GameSource,
+    // the launcher's own id, e.g. the Steam app id
+    pub source_id: String,
+    pub name: String,
+    pub install_dir: String,
+    pub exe_name: Option<String>,
+    // the Wine prefix the launcher runs the game in
+    #[serde(default)]
+    pub wine_prefix: Option<String>,
+    pub save_roots: Vec<SaveRoot>,
+    #[serde(default)]
+    pub include_patterns: Vec<String>,
+    #[serde(default)]
+    pub already_added: bool,
+}
+
+impl DiscoveredGame {
+    /// The request adding the game like a manual add would.
+    pub fn to_request(&self) -> Result<AddGameRequest, String> {
+        let first = self
+            .save_roots
+            .first()
+            .ok_or_else(|| format!("No save folder found for {}", self.name))?;
+
+        Ok(AddGameRequest {
+            name: self.name.clone(),
+            save_location: first.path.clone(),
+            exe_name: self.exe_name.clone(),
+            cover_image: None,
+            save_roots: self.save_roots.clone(),
+            include_patterns: self.include_patterns.clone(),
+            exclude_patterns: Vec::new(),
+            wine_prefix: self.wine_prefix.clone(),
+            steam_app_id: (self.source == GameSource::Steam).then(|| self.source_id.clone()),
+            watch_saves: false,
+            snapshot_on_exit: false,
+            snapshot_on_start: false,
+        })
+    }
+}
+
+/// What a discovery found, and what it had to skip.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Discovery {
+    pub games: Vec<DiscoveredGame>,
+    // launcher installations and game files that couldn't be read
+    pub warnings: Vec<String>,
+}
+
+/// Games installed by every supported launcher, sorted by name. Games already
+/// in `games` are flagged rather than left out. Save locations from the
+/// manifest replace the guessed ones for the games it knows.
+pub fn discover_games(
+    games: &[Game],
+    variables: &PathVariables,
+    manifest: Option<&Manifest>,
+) -> Discovery {
+    let mut warnings = Vec::new();
+    let mut found = steam::discover(&mut warnings);
+    found.extend(heroic::discover(&mut warnings));
+    found.extend(lutris::discover(&mut warnings));
+    if let Some(manifest) = manifest {
+        apply_manifest(&mut found, manifest);
+    }
+    mark_added(&mut found, games, variables);
+    found.sort_by_key(|game| game.name.to_lowercase());
+    Discovery {
+        games: found,
+        warnings,
+    }
+}
+
+fn apply_manifest(found: &mut [DiscoveredGame], manifest: &Manifest) {
+    for discovered in found {
+        let lookup = Lookup {
+            name: &discovered.name,
+            steam_app_id: (discovered.source == GameSource::Steam)
+                .then_some(discovered.source_id.as_str()),
+            install_dir: Some(&discovered.install_dir),
+            windows: discovered.wine_prefix.is_some()
+                || discovered
+                    .exe_name
+                    .as_ref()
+                    .is_some_and(|exe| exe.to_lowercase().ends_with(".exe")),
+        };
+        if let Ok(found) = manifest.lookup(&lookup) {
+            discovered.save_roots = found.save_roots;
+            discovered.include_patterns = found.include_patterns;
+        }
+    }
+}
+
+// the XDG folders native games commonly save to, under the first of `folders`
+// that exists in each
+fn native_candidates(home: &Path, folders: &[&str]) -> Vec<SaveRoot> {
+    let mut candidates = Vec::new();
+    for (name, dir) in [("data", ".local/share"), ("config", ".config")] {
+        let found = folders
+            .iter()
+            .filter(|folder| !folder.is_empty())
+            .map(|folder| home.join(dir).join(folder))
+            .find(|path| path.is_dir());
+        if let Some(path) = found {
+            candidates.push(SaveRoot {
+                name: name.to_string(),
+                path: path.to_string_lossy().to_string(),
+            });
+        }
+    }
+    candidates
+}
+
+// the folders Windows games commonly save to that exist inside `prefix`, kept
+// as placeholders so they follow the game's prefix
+fn prefix_candidates(prefix: &Path, folders: &[&str]) -> Vec<SaveRoot> {
+    let mut candidates: Vec<SaveRoot> = Vec::new();
+    for (name, parent) in PREFIX_SAVE_FOLDERS {
+        if candidates.iter().any(|root| root.name == *name) {
+            continue;
+        }
+        let found = folders
+            .iter()
+            .filter(|folder| !folder.is_empty())
+            .map(|folder| format!("{}/{}", parent, folder))
+            .find(|path| {
+                crate::wine::resolve(path, Some(prefix))
+                    .is_ok_and(|resolved| Path::new(&resolved).is_dir())
+            });
+        if let Some(path) = found {
+            candidates.push(SaveRoot {
+                name: name.to_string(),
+                path,
+            });
+        }
+    }
+    candidates
+}
+
+// the same name or a shared save folder means the game was added before
+fn mark_added(found: &mut [DiscoveredGame], games: &[Game], variables: &PathVariables) {
+    let roots: Vec<String> = games
+        .iter()
+        .flat_map(|game| &game.save_roots)
+        .filter_map(|root| variables.expand(&root.path).ok())
+        .collect();
+
+    for discovered in found {
+        discovered.already_added = games
+            .iter()
+            .any(|game| game.name.eq_ignore_ascii_case(&discovered.name))
+            || discovered.save_roots.iter().any(|candidate| {
+                let candidate = variables
+                    .expand(&candidate.path)
+                    .unwrap_or_else(|_| candidate.path.clone());
+                roots
+                    .iter()
+                    .any(|root| Path::new(&candidate) == Path::new(root))
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discovered_games_become_add_requests() {
+        let mut found = vec![DiscoveredGame {
+            source: GameSource::Steam,
+            source_id: "620".to_string(),
+            name: "Portal 2".to_string(),
+            install_dir: "/games/Portal 2".to_string(),
+            exe_name: Some("portal2_linux".to_string()),
+            wine_prefix: None,
+            save_roots: Vec::new(),
+            include_patterns: Vec::new(),
+            already_added: false,
+        }];
+        assert!(found[0].to_request().is_err());
+
+        found[0].save_roots.push(SaveRoot {
+            name: "steam-cloud".to_string(),
+            path: "/steam/userdata/1/620/remote".to_string(),
+        });
+        let request = found[0].to_request().unwrap();
+        assert_eq!(request.save_location, "/steam/userdata/1/620/remote");
+        assert_eq!(request.exe_name.as_deref(), Some("portal2_linux"));
+
+        let variables = PathVariables::default();
+        mark_added(&mut found, &[], &variables);
+        assert!(!found[0].already_added);
+        let existing = Game::new(
+            "My Portal".to_string(),
+            "/steam/userdata/1/620/remote/".to_string(),
+            None,
+            None,
+        );
+        mark_added(&mut found, &[existing], &variables);
+        assert!(found[0].already_added);
+    }
+}
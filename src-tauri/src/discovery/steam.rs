@@ -0,0 +1,628 @@
+use super::{DiscoveredGame, GameSource};
+use crate::game::SaveRoot;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// where Steam lives on Linux, relative to the home directory. ~/.steam/steam
+// usually links to one of the others
+const STEAM_ROOTS: &[&str] = &[
+    ".steam/steam",
+    ".steam/root",
+    ".local/share/Steam",
+    ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+];
+
+// Steam installs these like games
+const TOOL_PREFIXES: &[&str] = &[
+    "Proton",
+    "Steam Linux Runtime",
+    "Steamworks Common Redistributables",
+];
+
+// StateFlags bit of fully installed apps
+const FULLY_INSTALLED: u64 = 4;
+
+/// Steam installations under `home`, each listed once.
+pub fn steam_roots(home: &Path) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+
+    STEAM_ROOTS
+        .iter()
+        .map(|path| home.join(path))
+        .filter(|path| path.join("steamapps").is_dir())
+        .filter(|path| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())))
+        .collect()
+}
+
+/// Installed games of every Steam installation of the current user. Installations
+/// that can't be read are skipped, with a warning added to `warnings`.
+pub fn discover(warnings: &mut Vec<String>) -> Vec<DiscoveredGame> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+
+    let mut games = Vec::new();
+    for root in steam_roots(&home) {
+        match scan(&root, &home, warnings) {
+            Ok(found) => games.extend(found),
+            Err(e) => warnings.push(format!("Failed to scan Steam at {}: {}", root.display(), e)),
+        }
+    }
+    games
+}
+
+/// Installed games in every library of the Steam installation at `steam_root`.
+/// Native games' saves are looked for under `home`. App manifests that can't be
+/// read are skipped, with a warning added to `warnings`.
+pub fn scan(
+    steam_root: &Path,
+    home: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<DiscoveredGame>, String> {
+    let mut games = Vec::new();
+    let mut seen = HashSet::new();
+
+    for library in library_folders(steam_root)? {
+        // libraries on drives that aren't mounted
+        let Ok(entries) = fs::read_dir(library.join("steamapps")) else {
+            continue;
+        };
+
+        let mut manifests: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("appmanifest_") && name.ends_with(".acf"))
+            })
+            .collect();
+        manifests.sort();
+
+        for path in manifests {
+            let app = match read_app_manifest(&path) {
+                Ok(Some(app)) => app,
+                Ok(None) => continue,
+                Err(e) => {
+                    warnings.push(e);
+                    continue;
+                }
+            };
+
+            if seen.insert(app.app_id.clone()) {
+                games.push(app.into_discovered(&library, steam_root, home));
+            }
+        }
+    }
+
+    Ok(games)
+}
+
+/// The Steam installation's own library followed by the ones listed in its
+/// libraryfolders.vdf.
+pub fn library_folders(steam_root: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut libraries = vec![steam_root.to_path_buf()];
+
+    let path = steam_root.join("steamapps").join("libraryfolders.vdf");
+    if !path.exists() {
+        return Ok(libraries);
+    }
+
+    let vdf = read_vdf(&path)?;
+    let folders = vdf
+        .get("libraryfolders")
+        .ok_or_else(|| format!("{} lists no libraries", path.display()))?;
+
+    for (key, folder) in folders.entries() {
+        let library = match folder {
+            // older Steam versions list the paths directly
+            Vdf::Value(value) if key.parse::<u32>().is_ok() => value.as_str(),
+            Vdf::Table(_) => match folder.get("path").and_then(Vdf::as_str) {
+                Some(value) => value,
+                None => continue,
+            },
+            Vdf::Value(_) => continue,
+        };
+
+        let library = PathBuf::from(library);
+        if !libraries.iter().any(|known| same_dir(known, &library)) {
+            libraries.push(library);
+        }
+    }
+
+    Ok(libraries)
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    a == b
+        || matches!(
+            (fs::canonicalize(a), fs::canonicalize(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+struct AppManifest {
+    app_id: String,
+    name: String,
+    install_dir: String,
+}
+
+// None for apps that aren't fully installed and for Steam's own tools
+fn read_app_manifest(path: &Path) -> Result<Option<AppManifest>, String> {
+    let vdf = read_vdf(path)?;
+    let state = vdf
+        .get("AppState")
+        .ok_or_else(|| format!("{} has no AppState", path.display()))?;
+    let field = |key: &str| {
+        state
+            .get(key)
+            .and_then(Vdf::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| format!("{} has no {}", path.display(), key))
+    };
+
+    let app = AppManifest {
+        app_id: field("appid")?,
+        name: field("name")?,
+        install_dir: field("installdir")?,
+    };
+
+    let installed = state
+        .get("StateFlags")
+        .and_then(Vdf::as_str)
+        .and_then(|flags| flags.parse::<u64>().ok())
+        .is_none_or(|flags| flags & FULLY_INSTALLED != 0);
+    let tool = TOOL_PREFIXES
+        .iter()
+        .any(|prefix| app.name.starts_with(prefix));
+
+    Ok((installed && !tool).then_some(app))
+}
+
+impl AppManifest {
+    fn into_discovered(self, library: &Path, steam_root: &Path, home: &Path) -> DiscoveredGame {
+        let install_dir = library
+            .join("steamapps")
+            .join("common")
+            .join(&self.install_dir);
+        // Proton keeps the prefix with the game's library
+        let prefix = library
+            .join("steamapps/compatdata")
+            .join(&self.app_id)
+            .join("pfx");
+        let prefix = prefix.is_dir().then_some(prefix);
+        let save_roots = self.save_candidates(steam_root, home, prefix.as_deref());
+
+        DiscoveredGame {
+            source: GameSource::Steam,
+            exe_name: find_executable(&install_dir),
+            install_dir: install_dir.to_string_lossy().to_string(),
+            wine_prefix: prefix.map(|prefix| prefix.to_string_lossy().to_string()),
+            source_id: self.app_id,
+            name: self.name,
+            save_roots,
+            include_patterns: Vec::new(),
+            already_added: false,
+        }
+    }
+
+    // Steam Cloud folders of every Steam account, then the folders Windows games
+    // commonly use in the Proton prefix, or the XDG folders native games do
+    // without one. Only folders that exist are proposed
+    fn save_candidates(
+        &self,
+        steam_root: &Path,
+        home: &Path,
+        prefix: Option<&Path>,
+    ) -> Vec<SaveRoot> {
+        let mut candidates = Vec::new();
+
+        let mut accounts: Vec<PathBuf> = fs::read_dir(steam_root.join("userdata"))
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default();
+        accounts.sort();
+        for account in accounts {
+            let remote = account.join(&self.app_id).join("remote");
+            if remote.is_dir() {
+                let name = match candidates.len() {
+                    0 => "steam-cloud".to_string(),
+                    n => format!("steam-cloud-{}", n + 1),
+                };
+                candidates.push(SaveRoot {
+                    name,
+                    path: remote.to_string_lossy().to_string(),
+                });
+            }
+        }
+
+        let folders = [self.install_dir.as_str(), self.name.as_str()];
+        candidates.extend(match prefix {
+            Some(prefix) => super::prefix_candidates(prefix, &folders),
+            None => super::native_candidates(home, &folders),
+        });
+        candidates
+    }
+}
+
+// the largest executable at the top of the install folder, crash reporters and
+// uninstallers aside. Proton games are matched by their .exe
+pub(super) fn find_executable(install_dir: &Path) -> Option<String> {
+    let entries = fs::read_dir(install_dir).ok()?;
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let metadata = entry.metadata().ok()?;
+            let lower = name.to_lowercase();
+
+            if !metadata.is_file()
+                || lower.starts_with("unins")
+                || lower.contains("crash")
+                || !(lower.ends_with(".exe") || is_native_executable(&lower, &metadata))
+            {
+                return None;
+            }
+            Some((metadata.len(), name))
+        })
+        .max()
+        .map(|(_, name)| name)
+}
+
+#[cfg(unix)]
+fn is_native_executable(name: &str, metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    // scripts and libraries can be executable too
+    let extension = Path::new(name).extension().and_then(|e| e.to_str());
+    metadata.permissions().mode() & 0o111 != 0
+        && matches!(extension, None | Some("x86_64" | "x86" | "bin"))
+}
+
+#[cfg(not(unix))]
+fn is_native_executable(_name: &str, _metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// A node of Valve's KeyValues text format, which libraryfolders.vdf and
+/// appmanifest files use.
+#[derive(Debug, PartialEq)]
+enum Vdf {
+    Value(String),
+    Table(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+    // keys are case-insensitive, Steam isn't consistent about them
+    fn get(&self, key: &str) -> Option<&Vdf> {
+        self.entries()
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Vdf::Value(value) => Some(value),
+            Vdf::Table(_) => None,
+        }
+    }
+
+    fn entries(&self) -> &[(String, Vdf)] {
+        match self {
+            Vdf::Table(entries) => entries,
+            Vdf::Value(_) => &[],
+        }
+    }
+}
+
+fn read_vdf(path: &Path) -> Result<Vdf, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_vdf(&contents).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+enum Token {
+    Text(String),
+    Open,
+    Close,
+}
+
+fn parse_vdf(text: &str) -> Result<Vdf, String> {
+    let tokens = tokenize(text)?;
+    let mut tokens = tokens.into_iter();
+    parse_table(&mut tokens, true).map(Vdf::Table)
+}
+
+fn parse_table(
+    tokens: &mut impl Iterator<Item = Token>,
+    top_level: bool,
+) -> Result<Vec<(String, Vdf)>, String> {
+    let mut entries = Vec::new();
+
+    loop {
+        let key = match tokens.next() {
+            Some(Token::Text(key)) => key,
+            Some(Token::Close) if !top_level => return Ok(entries),
+            None if top_level => return Ok(entries),
+            Some(Token::Close) => return Err("unexpected '}'".to_string()),
+            Some(Token::Open) => return Err("unexpected '{'".to_string()),
+            None => return Err("missing '}'".to_string()),
+        };
+
+        let value = match tokens.next() {
+            Some(Token::Text(value)) => Vdf::Value(value),
+            Some(Token::Open) => Vdf::Table(parse_table(tokens, false)?),
+            _ => return Err(format!("'{}' has no value", key)),
+        };
+        entries.push((key, value));
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some(c) => value.push(c),
+                            None => return Err("unterminated string".to_string()),
+                        },
+                        Some(c) => value.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Text(value));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut value = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, '"' | '{' | '}') {
+                        break;
+                    }
+                    value.push(next);
+                    chars.next();
+                }
+                tokens.push(Token::Text(value));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("checkpoint-{}-{}", label, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn app_manifest(app_id: &str, name: &str, install_dir: &str, flags: u64) -> String {
+        format!(
+            "\"AppState\"\n{{\n\t\"appid\"\t\t\"{}\"\n\t\"name\"\t\t\"{}\"\n\t\"StateFlags\"\t\t\"{}\"\n\t\"installdir\"\t\t\"{}\"\n}}\n",
+            app_id, name, flags, install_dir
+        )
+    }
+
+    #[cfg(unix)]
+    fn make_executable(path: &Path) {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(not(unix))]
+    fn make_executable(_path: &Path) {}
+
+    #[test]
+    fn test_parse_vdf() {
+        let vdf = parse_vdf(
+            r#"
+            // written by Steam
+            "libraryfolders"
+            {
+                "0"
+                {
+                    "path"    "C:\\Program Files (x86)\\Steam"
+                    "label"   ""
+                    "apps" { "620" "12345" }
+                }
+                "contentstatsid" 42
+            }
+            "#,
+        )
+        .unwrap();
+
+        let folders = vdf.get("LibraryFolders").unwrap();
+        assert_eq!(
+            folders
+                .get("0")
+                .and_then(|f| f.get("path"))
+                .and_then(Vdf::as_str),
+            Some("C:\\Program Files (x86)\\Steam")
+        );
+        assert_eq!(
+            folders.get("contentstatsid").and_then(Vdf::as_str),
+            Some("42")
+        );
+        assert_eq!(folders.entries().len(), 2);
+
+        assert!(parse_vdf("\"AppState\" { \"appid\" \"620\"").is_err());
+        assert!(parse_vdf("\"AppState\" { \"appid\" }").is_err());
+    }
+
+    #[test]
+    fn test_library_folders_reads_old_and_new_formats() {
+        let steam = temp_dir("steam");
+        let library = temp_dir("library");
+        let vdf = steam.join("steamapps/libraryfolders.vdf");
+
+        write(
+            &vdf,
+            &format!(
+                "\"libraryfolders\" {{ \"0\" {{ \"path\" \"{}\" }} \"1\" {{ \"path\" \"{}\" }} }}",
+                steam.display(),
+                library.display()
+            ),
+        );
+        assert_eq!(
+            library_folders(&steam).unwrap(),
+            vec![steam.clone(), library.clone()]
+        );
+
+        write(
+            &vdf,
+            &format!(
+                "\"LibraryFolders\" {{ \"TimeNextStatsReport\" \"1700000000\" \"1\" \"{}\" }}",
+                library.display()
+            ),
+        );
+        assert_eq!(
+            library_folders(&steam).unwrap(),
+            vec![steam.clone(), library.clone()]
+        );
+
+        fs::remove_dir_all(&steam).unwrap();
+        fs::remove_dir_all(&library).unwrap();
+    }
+
+    #[test]
+    fn test_scan_finds_installed_games_across_libraries() {
+        let home = temp_dir("home");
+        let steam = home.join(".local/share/Steam");
+        let library = temp_dir("library");
+
+        write(
+            &steam.join("steamapps/libraryfolders.vdf"),
+            &format!(
+                "\"libraryfolders\" {{ \"0\" {{ \"path\" \"{}\" }} \"1\" {{ \"path\" \"{}\" }} }}",
+                steam.display(),
+                library.display()
+            ),
+        );
+
+        // a native game with Steam Cloud saves and a config folder
+        write(
+            &steam.join("steamapps/appmanifest_620.acf"),
+            &app_manifest("620", "Portal 2", "Portal 2", 4),
+        );
+        let portal = steam.join("steamapps/common/Portal 2");
+        write(&portal.join("portal2.sh"), "#!/bin/sh");
+        make_executable(&portal.join("portal2.sh"));
+        write(&portal.join("portal2_linux"), "binary");
+        make_executable(&portal.join("portal2_linux"));
+        write(
+            &portal.join("README.txt"),
+            "a large readme that isn't executable",
+        );
+        fs::create_dir_all(steam.join("userdata/12345/620/remote")).unwrap();
+        fs::create_dir_all(home.join(".config/Portal 2")).unwrap();
+
+        // Proton and an unfinished download aren't proposed
+        write(
+            &steam.join("steamapps/appmanifest_2348590.acf"),
+            &app_manifest("2348590", "Proton 8.0", "Proton 8.0", 4),
+        );
+        write(
+            &library.join("steamapps/appmanifest_570.acf"),
+            &app_manifest("570", "Dota 2", "dota 2 beta", 1026),
+        );
+
+        // a Proton game in the second library, saving into its prefix
+        write(
+            &library.join("steamapps/appmanifest_1145360.acf"),
+            &app_manifest("1145360", "Hades", "Hades", 4),
+        );
+        let prefix = library.join("steamapps/compatdata/1145360/pfx");
+        fs::create_dir_all(prefix.join("drive_c/users/steamuser/Documents/Hades")).unwrap();
+        let hades = library.join("steamapps/common/Hades");
+        write(&hades.join("Hades.exe"), "windows binary");
+        write(
+            &hades.join("unins000.exe"),
+            "the largest file in the folder",
+        );
+
+        // a manifest that can't be read is reported, the others still are
+        write(
+            &library.join("steamapps/appmanifest_400.acf"),
+            "\"AppState\"\n{\n",
+        );
+
+        let mut warnings = Vec::new();
+        let games = scan(&steam, &home, &mut warnings).unwrap();
+        let summary: Vec<(&str, &str, Option<&str>)> = games
+            .iter()
+            .map(|g| (g.source_id.as_str(), g.name.as_str(), g.exe_name.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("620", "Portal 2", Some("portal2_linux")),
+                ("1145360", "Hades", Some("Hades.exe")),
+            ]
+        );
+        assert_eq!(games[1].install_dir, hades.to_string_lossy());
+
+        let roots: Vec<(&str, &str)> = games[0]
+            .save_roots
+            .iter()
+            .map(|r| (r.name.as_str(), r.path.as_str()))
+            .collect();
+        assert_eq!(
+            roots,
+            vec![
+                (
+                    "steam-cloud",
+                    &*steam.join("userdata/12345/620/remote").to_string_lossy()
+                ),
+                ("config", &*home.join(".config/Portal 2").to_string_lossy()),
+            ]
+        );
+        assert_eq!(games[0].wine_prefix, None);
+        assert_eq!(
+            games[1].wine_prefix.as_deref(),
+            Some(prefix.to_string_lossy().as_ref())
+        );
+        let roots: Vec<(&str, &str)> = games[1]
+            .save_roots
+            .iter()
+            .map(|r| (r.name.as_str(), r.path.as_str()))
+            .collect();
+        assert_eq!(roots, vec![("documents", "%USERPROFILE%/Documents/Hades")]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("appmanifest_400.acf"));
+
+        assert_eq!(steam_roots(&home), vec![steam.clone()]);
+
+        fs::remove_dir_all(&home).unwrap();
+        fs::remove_dir_all(&library).unwrap();
+    }
+}
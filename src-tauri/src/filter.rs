@@ -0,0 +1,196 @@
+use crate::game::Game;
+use crate::snapshot::manifest_path;
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Decides which files under a save folder belong in a snapshot.
+///
+/// Patterns are matched against paths relative to the save folder, using `/`
+/// as separator. A pattern without a `/` matches a file name at any depth
+/// (`*.log`), a pattern ending in `/` matches a whole directory (`shadercache/`).
+/// With no include patterns every file is included; excludes always win.
+pub struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewFile {
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FilterPreview {
+    pub included: Vec<PreviewFile>,
+    pub excluded: Vec<PreviewFile>,
+    pub included_size: u64,
+    pub excluded_size: u64,
+}
+
+impl FileFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, String> {
+        let include = if include.iter().all(|p| p.trim().is_empty()) {
+            None
+        } else {
+            Some(build_set(include)?)
+        };
+
+        Ok(Self {
+            include,
+            exclude: build_set(exclude)?,
+        })
+    }
+
+    pub fn for_game(game: &Game) -> Result<Self, String> {
+        Self::new(&game.include_patterns, &game.exclude_patterns)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_empty()
+    }
+
+    pub fn matches(&self, relative_path: &str) -> bool {
+        let included = self
+            .include
+            .as_ref()
+            .is_none_or(|set| set.is_match(relative_path));
+
+        included && !self.exclude.is_match(relative_path)
+    }
+}
+
+fn build_set(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        let pattern = pattern.trim().trim_start_matches("./");
+        if pattern.is_empty() {
+            continue;
+        }
+
+        let normalized = if let Some(dir) = pattern.strip_suffix('/') {
+            format!("{}/**", dir)
+        } else if !pattern.contains('/') {
+            format!("**/{}", pattern)
+        } else {
+            pattern.trim_start_matches('/').to_string()
+        };
+
+        builder.add(compile(&normalized, pattern)?);
+    }
+
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build glob patterns: {}", e))
+}
+
+fn compile(normalized: &str, original: &str) -> Result<Glob, String> {
+    GlobBuilder::new(normalized)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| format!("Invalid pattern '{}': {}", original, e))
+}
+
+/// Lists which files under `save_location` the given patterns would capture.
+pub fn preview(
+    save_location: &str,
+    include: &[String],
+    exclude: &[String],
+) -> Result<FilterPreview, String> {
+    let save_path = Path::new(save_location);
+    if !save_path.exists() {
+        return Err(format!("Save location does not exist: {}", save_location));
+    }
+
+    let filter = FileFilter::new(include, exclude)?;
+    let mut preview = FilterPreview {
+        included: Vec::new(),
+        excluded: Vec::new(),
+        included_size: 0,
+        excluded_size: 0,
+    };
+
+    for entry in WalkDir::new(save_path).sort_by_file_name() {
+        let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+        let path = entry.path();
+
+        if !path.is_file() {
+            continue;
+        }
+
+        let relative_path = path
+            .strip_prefix(save_path)
+            .map_err(|e| format!("Failed to calculate relative path: {}", e))?;
+        let relative_path = manifest_path(relative_path);
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+
+        if filter.matches(&relative_path) {
+            preview.included_size += size;
+            preview.included.push(PreviewFile {
+                path: relative_path,
+                size,
+            });
+        } else {
+            preview.excluded_size += size;
+            preview.excluded.push(PreviewFile {
+                path: relative_path,
+                size,
+            });
+        }
+    }
+
+    Ok(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_empty_filter_matches_everything() {
+        let filter = FileFilter::new(&[], &[]).unwrap();
+        assert!(filter.is_empty());
+        assert!(filter.matches("slot1.sav"));
+        assert!(filter.matches("deep/nested/file.bin"));
+    }
+
+    #[test]
+    fn test_name_patterns_match_at_any_depth() {
+        let filter = FileFilter::new(&[], &patterns(&["*.log", "crash_*.dmp"])).unwrap();
+        assert!(!filter.matches("game.log"));
+        assert!(!filter.matches("logs/old/game.log"));
+        assert!(!filter.matches("crash_2024.dmp"));
+        assert!(filter.matches("saves/slot1.sav"));
+    }
+
+    #[test]
+    fn test_directory_and_path_patterns() {
+        let filter = FileFilter::new(
+            &patterns(&["saves/**", "settings.ini"]),
+            &patterns(&["saves/backup/", "shadercache/"]),
+        )
+        .unwrap();
+        assert!(filter.matches("saves/slot1.sav"));
+        assert!(filter.matches("saves/profiles/p1.dat"));
+        assert!(filter.matches("settings.ini"));
+        assert!(!filter.matches("saves/backup/slot1.sav"));
+        assert!(!filter.matches("shadercache/cache.bin"));
+        assert!(!filter.matches("screenshots/shot.png"));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_reported() {
+        let error = FileFilter::new(&patterns(&["saves/[abc"]), &[])
+            .err()
+            .unwrap();
+        assert!(error.contains("saves/[abc"));
+    }
+}
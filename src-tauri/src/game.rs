@@ -1,17 +1,59 @@
+use crate::paths::PathVariables;
+use crate::retention::RetentionPolicy;
+use crate::store::CompressionSettings;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+pub const DEFAULT_SAVE_ROOT: &str = "saves";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveRoot {
+    pub name: String,
+    pub path: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Game {
     pub id: String,
     pub name: String,
+    // path of the first save root, kept so older configs and the single-folder UI keep working
     pub save_location: String,
+    #[serde(default)]
+    pub save_roots: Vec<SaveRoot>,
     pub exe_name: Option<String>,
     pub cover_image: Option<String>,
     pub created_at: DateTime<Utc>,
     pub last_restored_snapshot_id: Option<String>,
     pub last_restored_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub include_patterns: Vec<String>,
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    // overrides the global retention policy when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention: Option<RetentionPolicy>,
+    // overrides the global compression settings when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionSettings>,
+    // overrides the global encryption setting when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<bool>,
+    // what `%APPDATA%` and the like in save locations resolve against. Found
+    // automatically when not set, see `wine::find_prefix`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wine_prefix: Option<String>,
+    // set for games added from a Steam library, finds their Proton prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steam_app_id: Option<String>,
+    // snapshot automatically when the save folder changes, if the watcher is enabled
+    #[serde(default)]
+    pub watch_saves: bool,
+    // snapshot when the process monitor sees `exe_name` exit / start
+    #[serde(default)]
+    pub snapshot_on_exit: bool,
+    #[serde(default)]
+    pub snapshot_on_start: bool,
 }
 
 impl Game {
@@ -24,14 +66,178 @@ impl Game {
         Self {
             id: Uuid::new_v4().to_string(),
             name,
+            save_roots: vec![SaveRoot {
+                name: DEFAULT_SAVE_ROOT.to_string(),
+                path: save_location.clone(),
+            }],
             save_location,
             exe_name,
             cover_image,
             created_at: Utc::now(),
             last_restored_snapshot_id: None,
             last_restored_at: None,
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            retention: None,
+            compression: None,
+            encryption: None,
+            wine_prefix: None,
+            steam_app_id: None,
+            watch_saves: false,
+            snapshot_on_exit: false,
+            snapshot_on_start: false,
+        }
+    }
+
+    /// Builds a game from an add request, checking its save roots and filters.
+    /// The cover image is left to the caller.
+    pub fn from_request(request: AddGameRequest) -> Result<Self, String> {
+        crate::filter::FileFilter::new(&request.include_patterns, &request.exclude_patterns)?;
+
+        let mut game = Game::new(request.name, request.save_location, request.exe_name, None);
+        if !request.save_roots.is_empty() {
+            game.set_save_roots(request.save_roots)?;
         }
+        game.include_patterns = request.include_patterns;
+        game.exclude_patterns = request.exclude_patterns;
+        game.watch_saves = request.watch_saves;
+        game.snapshot_on_exit = request.snapshot_on_exit;
+        game.snapshot_on_start = request.snapshot_on_start;
+        game.wine_prefix = request.wine_prefix.filter(|prefix| !prefix.is_empty());
+        game.steam_app_id = request.steam_app_id;
+        Ok(game)
     }
+
+    /// A copy of the game with path variables and Windows placeholders in its
+    /// save locations resolved, for the actual file work. The config keeps them.
+    pub fn resolved_with(&self, variables: &PathVariables) -> Result<Game, String> {
+        let mut game = self.clone();
+        for root in &mut game.save_roots {
+            root.path = variables.expand(&root.path)?;
+        }
+        if let Some(prefix) = &game.wine_prefix {
+            game.wine_prefix = Some(variables.expand(prefix)?);
+        }
+
+        // placeholders are resolved against the prefix, so after the variables
+        if game
+            .save_roots
+            .iter()
+            .any(|root| crate::wine::has_placeholder(&root.path))
+        {
+            let prefix = crate::wine::find_prefix(&game);
+            for root in &mut game.save_roots {
+                root.path = crate::wine::resolve(&root.path, prefix.as_deref())?;
+            }
+        }
+
+        if let Some(first) = game.save_roots.first() {
+            game.save_location = first.path.clone();
+        }
+        Ok(game)
+    }
+
+    /// Rewrites save locations under a variable's folder to use the variable,
+    /// e.g. `/home/alice/.config/Game` as `$XDG_CONFIG_HOME/Game`, the most
+    /// specific one when several fit. `save_location` becomes the resolved first
+    /// one, a real path the UI can show and open. Returns true when anything changed.
+    pub fn make_portable(&mut self, variables: &PathVariables) -> bool {
+        self.rebase_paths(variables, variables)
+    }
+
+    /// Like `make_portable`, for when the variables change: paths are read
+    /// with the `from` variables and rewritten with the `to` ones. Paths using
+    /// a variable `to` doesn't have any more become absolute again.
+    pub fn rebase_paths(&mut self, from: &PathVariables, to: &PathVariables) -> bool {
+        let mut changed = false;
+        let paths = self
+            .save_roots
+            .iter_mut()
+            .map(|root| &mut root.path)
+            .chain(self.wine_prefix.as_mut());
+
+        for path in paths {
+            // unknown variables are left for resolving to report
+            let Ok(absolute) = from.expand(path) else {
+                continue;
+            };
+            let rebased = to.contract(&absolute).unwrap_or(absolute);
+            if rebased != *path {
+                *path = rebased;
+                changed = true;
+            }
+        }
+
+        if let Ok(resolved) = self.resolved_with(to) {
+            if resolved.save_location != self.save_location {
+                self.save_location = resolved.save_location;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Games saved before multiple save roots existed only have `save_location`.
+    /// Returns true when the game had to be migrated.
+    pub fn migrate_save_roots(&mut self) -> bool {
+        if !self.save_roots.is_empty() || self.save_location.is_empty() {
+            return false;
+        }
+
+        self.save_roots.push(SaveRoot {
+            name: DEFAULT_SAVE_ROOT.to_string(),
+            path: self.save_location.clone(),
+        });
+        true
+    }
+
+    pub fn set_save_roots(&mut self, save_roots: Vec<SaveRoot>) -> Result<(), String> {
+        validate_save_roots(&save_roots)?;
+        self.save_location = save_roots[0].path.clone();
+        self.save_roots = save_roots;
+        Ok(())
+    }
+
+    // editing the single save location from the UI moves the first root
+    pub fn set_save_location(&mut self, save_location: String) {
+        match self.save_roots.first_mut() {
+            Some(root) => root.path = save_location.clone(),
+            None => self.save_roots.push(SaveRoot {
+                name: DEFAULT_SAVE_ROOT.to_string(),
+                path: save_location.clone(),
+            }),
+        }
+        self.save_location = save_location;
+    }
+
+    pub fn save_root(&self, name: &str) -> Option<&SaveRoot> {
+        self.save_roots.iter().find(|root| root.name == name)
+    }
+}
+
+pub fn validate_save_roots(save_roots: &[SaveRoot]) -> Result<(), String> {
+    if save_roots.is_empty() {
+        return Err("A game needs at least one save location".to_string());
+    }
+
+    for (index, root) in save_roots.iter().enumerate() {
+        let name = root.name.trim();
+
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            return Err(format!("Invalid save location name: '{}'", root.name));
+        }
+        if root.path.trim().is_empty() {
+            return Err(format!("Save location '{}' has no path", root.name));
+        }
+        if save_roots[..index]
+            .iter()
+            .any(|other| other.name == root.name)
+        {
+            return Err(format!("Duplicate save location name: '{}'", root.name));
+        }
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Deserialize)]
@@ -40,6 +246,23 @@ pub struct AddGameRequest {
     pub save_location: String,
     pub exe_name: Option<String>,
     pub cover_image: Option<String>,
+    // when given, replaces `save_location` as the full list of save roots
+    #[serde(default)]
+    pub save_roots: Vec<SaveRoot>,
+    #[serde(default)]
+    pub include_patterns: Vec<String>,
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    #[serde(default)]
+    pub wine_prefix: Option<String>,
+    #[serde(default)]
+    pub steam_app_id: Option<String>,
+    #[serde(default)]
+    pub watch_saves: bool,
+    #[serde(default)]
+    pub snapshot_on_exit: bool,
+    #[serde(default)]
+    pub snapshot_on_start: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,6 +270,14 @@ pub struct UpdateGameRequest {
     pub game_id: String,
     pub name: Option<String>,
     pub save_location: Option<String>,
+    pub save_roots: Option<Vec<SaveRoot>>,
     pub exe_name: Option<String>,
     pub cover_image: Option<String>,
+    pub include_patterns: Option<Vec<String>>,
+    pub exclude_patterns: Option<Vec<String>>,
+    // an empty string clears it
+    pub wine_prefix: Option<String>,
+    pub watch_saves: Option<bool>,
+    pub snapshot_on_exit: Option<bool>,
+    pub snapshot_on_start: Option<bool>,
 }
@@ -1,17 +1,40 @@
 pub mod commands;
 pub mod config;
+pub mod crypto;
+pub mod diff;
+pub mod discovery;
+pub mod filter;
 pub mod game;
+pub mod ludusavi;
+pub mod monitor;
 pub mod oauth_server;
+pub mod operation;
+pub mod paths;
 pub mod process;
+pub mod retention;
+pub mod sessions;
 pub mod snapshot;
+pub mod store;
+pub mod watcher;
+pub mod wine;
 
 use commands::*;
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
 
 pub fn run() {
-    let config = config::Config::load().unwrap_or_default();
+    let config = config::Config::load_and_migrate().unwrap_or_default();
+    let session_log = sessions::SessionLog::load().unwrap_or_else(|e| {
+        eprintln!("Failed to load sessions: {}", e);
+        sessions::SessionLog::default()
+    });
+    let sessions = Arc::new(Mutex::new(session_log));
     let state = AppState {
         config: Mutex::new(config),
+        operations: Mutex::new(HashMap::new()),
+        sessions: sessions.clone(),
     };
 
     tauri::Builder::default()
@@ -20,9 +43,41 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_deep_link::init())
         .manage(state)
+        .setup(move |app| {
+            let handle = app.handle().clone();
+            match watcher::SaveWatcher::start(sessions.clone(), move |snapshot| {
+                let _ = handle.emit("auto-snapshot-created", snapshot);
+            }) {
+                Ok(save_watcher) => {
+                    app.manage(save_watcher);
+                }
+                // auto-snapshots are optional, the app works fine without them
+                Err(e) => eprintln!("{}", e),
+            }
+
+            let handle = app.handle().clone();
+            app.manage(monitor::ProcessMonitor::start(sessions, move |event| {
+                let _ = match event {
+                    monitor::MonitorEvent::SessionStarted(session) => {
+                        handle.emit("game-session-started", session)
+                    }
+                    monitor::MonitorEvent::SessionEnded(session) => {
+                        handle.emit("game-session-ended", session)
+                    }
+                    monitor::MonitorEvent::SnapshotCreated(snapshot) => {
+                        handle.emit("auto-snapshot-created", snapshot)
+                    }
+                };
+            }));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_config,
             set_backup_location,
+            set_watcher_settings,
+            get_path_variables,
+            set_path_variables,
+            set_monitor_settings,
             add_game,
             list_games,
             delete_game,
@@ -30,12 +85,32 @@ pub fn run() {
             create_snapshot,
             list_snapshots,
             restore_snapshot,
+            restore_snapshot_files,
+            list_snapshot_files,
             delete_snapshot,
             rename_snapshot,
+            set_snapshot_pinned,
             verify_snapshot,
+            diff_snapshots,
+            diff_with_current_save,
+            preview_snapshot_files,
+            set_retention_policy,
+            set_compression,
+            get_encryption_status,
+            unlock_encryption,
+            set_encryption,
+            preview_retention,
+            get_playtime,
+            list_sessions,
             is_process_running,
             select_folder,
             import_snapshot,
+            export_snapshot_zip,
+            export_snapshot,
+            discover_games,
+            add_discovered_games,
+            lookup_save_manifest,
+            cancel_operation,
             reset_checkpoint,
             open_folder,
             update_last_restored_snapshot,
@@ -50,4 +125,9 @@ pub fn run() {
 #[derive(Debug)]
 pub struct AppState {
     pub config: Mutex<config::Config>,
+    // cancellation flags of running snapshot operations, keyed by operation id
+    pub operations: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    // every recorded play session, shared with the process monitor and the save
+    // watcher so changes from all of them end up in sessions.json
+    pub sessions: Arc<Mutex<sessions::SessionLog>>,
 }
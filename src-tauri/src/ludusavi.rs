@@ -0,0 +1,559 @@
+use crate::game::{AddGameRequest, SaveRoot, DEFAULT_SAVE_ROOT};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// A save location database in Ludusavi's manifest format: game names mapped to
+/// the files holding their saves, written as path templates like
+/// `<winAppData>/Hades/*.sav`.
+#[derive(Debug, Default)]
+pub struct Manifest {
+    games: BTreeMap<String, ManifestGame>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestGame {
+    // another entry this one is just a second name for
+    #[serde(default)]
+    alias: Option<String>,
+    #[serde(default)]
+    files: BTreeMap<String, Option<FileRule>>,
+    // folder names the game installs into, only the keys matter
+    #[serde(default)]
+    install_dir: BTreeMap<String, serde_yaml::Value>,
+    #[serde(default)]
+    steam: Option<SteamInfo>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FileRule {
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    when: Vec<Condition>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Condition {
+    #[serde(default)]
+    os: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SteamInfo {
+    #[serde(default)]
+    id: Option<u64>,
+}
+
+/// What a manifest entry is looked up by.
+#[derive(Debug, Default)]
+pub struct Lookup<'a> {
+    pub name: &'a str,
+    pub steam_app_id: Option<&'a str>,
+    // the game's install folder, needed for saves kept next to the game
+    pub install_dir: Option<&'a str>,
+    // the game runs through Wine or Proton, so its Windows save locations apply
+    pub windows: bool,
+}
+
+/// The save locations a manifest entry gives for a game.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestMatch {
+    // the entry's name in the manifest
+    pub name: String,
+    pub steam_app_id: Option<String>,
+    pub save_roots: Vec<SaveRoot>,
+    pub include_patterns: Vec<String>,
+}
+
+impl ManifestMatch {
+    /// Replaces the request's save roots with the manifest's, adding its patterns.
+    pub fn apply(&self, request: &mut AddGameRequest) {
+        request.save_location = self.save_roots[0].path.clone();
+        request.save_roots = self.save_roots.clone();
+        request
+            .include_patterns
+            .extend(self.include_patterns.iter().cloned());
+        if request.steam_app_id.is_none() {
+            request.steam_app_id = self.steam_app_id.clone();
+        }
+    }
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let yaml = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read manifest {}: {}", path.display(), e))?;
+        Self::parse(&yaml)
+    }
+
+    pub fn parse(yaml: &str) -> Result<Self, String> {
+        let games =
+            serde_yaml::from_str(yaml).map_err(|e| format!("Failed to parse manifest: {}", e))?;
+        Ok(Self { games })
+    }
+
+    /// The save locations the manifest has for a game, found by Steam app id,
+    /// then name, then install folder.
+    pub fn lookup(&self, lookup: &Lookup) -> Result<ManifestMatch, String> {
+        let (name, game) = self
+            .find(lookup)
+            .ok_or_else(|| format!("{} isn't in the manifest", lookup.name))?;
+
+        let (save_roots, include_patterns) = save_rules(game, lookup).ok_or_else(|| {
+            format!(
+                "The manifest has no save locations for {} that apply here",
+                name
+            )
+        })?;
+
+        Ok(ManifestMatch {
+            name: name.to_string(),
+            steam_app_id: game
+                .steam
+                .as_ref()
+                .and_then(|steam| steam.id)
+                .map(|id| id.to_string())
+                .or_else(|| lookup.steam_app_id.map(str::to_string)),
+            save_roots,
+            include_patterns,
+        })
+    }
+
+    fn find(&self, lookup: &Lookup) -> Option<(&str, &ManifestGame)> {
+        let by_steam_id = lookup.steam_app_id.and_then(|app_id| {
+            self.games.iter().find(|(_, game)| {
+                game.steam
+                    .as_ref()
+                    .and_then(|steam| steam.id)
+                    .is_some_and(|id| id.to_string() == app_id)
+            })
+        });
+
+        let wanted = normalize(lookup.name);
+        let by_name = || {
+            self.games.get_key_value(lookup.name).or_else(|| {
+                self.games
+                    .iter()
+                    .find(|(name, _)| normalize(name) == wanted)
+            })
+        };
+
+        let folder = lookup
+            .install_dir
+            .and_then(|dir| Path::new(dir).file_name())
+            .map(|folder| folder.to_string_lossy().to_string());
+        let by_folder = || {
+            let folder = folder.as_ref()?;
+            self.games
+                .iter()
+                .find(|(_, game)| game.install_dir.contains_key(folder))
+        };
+
+        let (name, game) = by_steam_id.or_else(by_name).or_else(by_folder)?;
+        match &game.alias {
+            Some(alias) => self
+                .games
+                .get_key_value(alias)
+                .map(|(name, game)| (name.as_str(), game)),
+            None => Some((name.as_str(), game)),
+        }
+    }
+}
+
+// names compared without case, spacing or punctuation, e.g. "HADES ii" and "Hades II"
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn current_os() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "mac",
+        os => os,
+    }
+}
+
+// the save roots and include patterns for the game's files. Native save
+// locations win, unless the game runs through Wine or has only Windows ones
+fn save_rules(game: &ManifestGame, lookup: &Lookup) -> Option<(Vec<SaveRoot>, Vec<String>)> {
+    let native = current_os();
+    if lookup.windows || native == "windows" {
+        return rules_for(game, lookup, "windows");
+    }
+    rules_for(game, lookup, native).or_else(|| rules_for(game, lookup, "windows"))
+}
+
+fn rules_for(
+    game: &ManifestGame,
+    lookup: &Lookup,
+    os: &str,
+) -> Option<(Vec<SaveRoot>, Vec<String>)> {
+    let mut locations: Vec<(Vec<String>, Option<String>)> = game
+        .files
+        .iter()
+        .filter(|(template, rule)| is_save(rule) && applies_to(template, rule, os))
+        .filter_map(|(template, _)| expand(template, lookup, os))
+        .map(|path| split_glob(&path))
+        .collect();
+    if locations.is_empty() {
+        return None;
+    }
+
+    // outer folders first, so nested locations become patterns inside them
+    locations.sort_by_key(|(folder, _)| folder.len());
+    let mut roots: Vec<Vec<String>> = Vec::new();
+    let mut patterns: Vec<String> = Vec::new();
+    let mut whole_root = false;
+    for (folder, glob) in locations {
+        let outer = roots
+            .iter()
+            .find(|root| folder.starts_with(root))
+            .map(Vec::len);
+        let inside = match outer {
+            Some(len) => folder[len..].join("/"),
+            None => {
+                roots.push(folder);
+                String::new()
+            }
+        };
+
+        match (inside.is_empty(), glob) {
+            (true, None) => whole_root = true,
+            (true, Some(glob)) => patterns.push(glob),
+            (false, None) => patterns.push(format!("{}/", inside)),
+            (false, Some(glob)) => patterns.push(format!("{}/{}", inside, glob)),
+        }
+    }
+
+    // patterns apply to every root, so a root saved whole means saving
+    // the others whole too
+    if whole_root {
+        patterns.clear();
+    }
+    patterns.sort();
+    patterns.dedup();
+
+    let mut save_roots: Vec<SaveRoot> = Vec::new();
+    for folder in roots {
+        let base = folder
+            .last()
+            .map(|last| last.trim_matches(['%', '$']).to_string())
+            .filter(|last| !last.is_empty())
+            .unwrap_or_else(|| DEFAULT_SAVE_ROOT.to_string());
+        let mut name = base.clone();
+        let mut count = 1;
+        while save_roots.iter().any(|root| root.name == name) {
+            count += 1;
+            name = format!("{}-{}", base, count);
+        }
+        save_roots.push(SaveRoot {
+            name,
+            path: folder.join("/"),
+        });
+    }
+    Some((save_roots, patterns))
+}
+
+// saves rather than settings, entries without tags are taken to be saves
+fn is_save(rule: &Option<FileRule>) -> bool {
+    rule.as_ref()
+        .is_none_or(|rule| rule.tags.is_empty() || rule.tags.iter().any(|tag| tag == "save"))
+}
+
+// a rule's own conditions decide, else the placeholders it uses
+fn applies_to(template: &str, rule: &Option<FileRule>, os: &str) -> bool {
+    let conditions: Vec<&str> = rule
+        .iter()
+        .flat_map(|rule| &rule.when)
+        .filter_map(|condition| condition.os.as_deref())
+        .collect();
+    if !conditions.is_empty() {
+        return conditions.contains(&os);
+    }
+
+    if template.contains("<win") || template.contains("<reg") {
+        os == "windows"
+    } else if template.contains("<xdg") {
+        os == "linux"
+    } else {
+        true
+    }
+}
+
+/// Turns a manifest path template into a save location Checkpoint understands,
+/// with Windows folders as `%APPDATA%`-style placeholders and Linux ones as
+/// path variables. None when the template needs something unknown, like the
+/// install folder of a game added by hand.
+fn expand(template: &str, lookup: &Lookup, os: &str) -> Option<String> {
+    let install_dir = lookup.install_dir.map(Path::new);
+    let windows = os == "windows";
+
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('<') {
+        let end = start + rest[start..].find('>')?;
+        expanded.push_str(&rest[..start]);
+
+        let value = match &rest[start + 1..end] {
+            "base" => install_dir?.to_string_lossy().to_string(),
+            "game" => install_dir?.file_name()?.to_string_lossy().to_string(),
+            "root" => library_root(install_dir?)?,
+            "home" if windows => "%USERPROFILE%".to_string(),
+            "home" => "$HOME".to_string(),
+            "storeUserId" | "osUserName" => "*".to_string(),
+            "winAppData" => "%APPDATA%".to_string(),
+            "winLocalAppData" => "%LOCALAPPDATA%".to_string(),
+            "winLocalAppDataLow" => "%USERPROFILE%/AppData/LocalLow".to_string(),
+            "winDocuments" => "%USERPROFILE%/Documents".to_string(),
+            "xdgData" => "$XDG_DATA_HOME".to_string(),
+            "xdgConfig" => "$XDG_CONFIG_HOME".to_string(),
+            // <winPublic>, <winProgramData>, <winDir> and registry keys
+            _ => return None,
+        };
+        expanded.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Some(expanded.replace('\\', "/"))
+}
+
+// the Steam library for games in `steamapps/common`, else the folder holding the game
+fn library_root(install_dir: &Path) -> Option<String> {
+    let parent = install_dir.parent()?;
+    let root = if parent.ends_with("steamapps/common") {
+        parent.parent()?.parent()?
+    } else {
+        parent
+    };
+    Some(root.to_string_lossy().to_string())
+}
+
+// splits a location into the folder before any wildcard and the pattern for
+// the files in it, None when the whole folder holds saves. Literal paths
+// ending in a file name get the file as pattern
+fn split_glob(path: &str) -> (Vec<String>, Option<String>) {
+    let parts: Vec<&str> = path
+        .trim_end_matches('/')
+        .split('/')
+        .enumerate()
+        .filter(|(index, part)| *index == 0 || !part.is_empty())
+        .map(|(_, part)| part)
+        .collect();
+
+    let glob_at = parts
+        .iter()
+        .position(|part| part.contains(['*', '?', '[', '{']))
+        .or_else(|| {
+            let last = parts.len() - 1;
+            (last > 0 && looks_like_file(parts[last])).then_some(last)
+        });
+
+    match glob_at {
+        Some(index) => {
+            let mut glob = parts[index..].join("/");
+            // a wildcard folder in the middle means everything below it
+            if index < parts.len() - 1 && !parts[parts.len() - 1].contains(['*', '?', '.']) {
+                glob.push('/');
+            }
+            (
+                parts[..index].iter().map(|part| part.to_string()).collect(),
+                Some(glob),
+            )
+        }
+        None => (parts.iter().map(|part| part.to_string()).collect(), None),
+    }
+}
+
+// a short extension after a dot, like `settings.ini` or `save.dat`
+fn looks_like_file(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(stem, extension)| {
+        !stem.is_empty()
+            && (1..=4).contains(&extension.len())
+            && extension.chars().all(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../tests/fixtures/ludusavi-manifest.yaml");
+
+    fn lookup(manifest: &Manifest, lookup: Lookup) -> ManifestMatch {
+        manifest.lookup(&lookup).unwrap()
+    }
+
+    fn roots(found: &ManifestMatch) -> Vec<(&str, &str)> {
+        found
+            .save_roots
+            .iter()
+            .map(|root| (root.name.as_str(), root.path.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_entries_are_found_by_steam_id_name_alias_and_install_folder() {
+        let manifest = Manifest::parse(FIXTURE).unwrap();
+
+        let by_id = Lookup {
+            name: "Something else",
+            steam_app_id: Some("1145360"),
+            windows: true,
+            ..Default::default()
+        };
+        assert_eq!(lookup(&manifest, by_id).name, "Hades");
+
+        let by_name = Lookup {
+            name: "hades",
+            windows: true,
+            ..Default::default()
+        };
+        let found = lookup(&manifest, by_name);
+        assert_eq!(found.name, "Hades");
+        assert_eq!(found.steam_app_id.as_deref(), Some("1145360"));
+
+        let by_alias = Lookup {
+            name: "Hades: Battle Out of Hell",
+            windows: true,
+            ..Default::default()
+        };
+        assert_eq!(lookup(&manifest, by_alias).name, "Hades");
+
+        let by_folder = Lookup {
+            name: "Unknown",
+            install_dir: Some("/games/steamapps/common/Celeste"),
+            ..Default::default()
+        };
+        assert_eq!(lookup(&manifest, by_folder).name, "Celeste");
+
+        let missing = Lookup {
+            name: "Not A Game",
+            ..Default::default()
+        };
+        assert!(manifest.lookup(&missing).is_err());
+    }
+
+    #[test]
+    fn test_templates_become_save_roots_and_patterns() {
+        let manifest = Manifest::parse(FIXTURE).unwrap();
+
+        // Windows locations become placeholders resolved in the prefix
+        let hades = lookup(
+            &manifest,
+            Lookup {
+                name: "Hades",
+                windows: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            roots(&hades),
+            [("Hades", "%USERPROFILE%/Documents/Saved Games/Hades")]
+        );
+        assert_eq!(hades.include_patterns, ["*.sav"]);
+
+        // saves next to the game need its install folder
+        let celeste = Lookup {
+            name: "Celeste",
+            install_dir: Some("/games/steamapps/common/Celeste"),
+            windows: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            roots(&lookup(&manifest, celeste)),
+            [("Saves", "/games/steamapps/common/Celeste/Saves")]
+        );
+        let celeste = Lookup {
+            name: "Celeste",
+            windows: true,
+            ..Default::default()
+        };
+        assert!(manifest.lookup(&celeste).is_err());
+
+        // nested and single-file locations become patterns in the outer folder,
+        // config-only files are left out
+        let stardew = lookup(
+            &manifest,
+            Lookup {
+                name: "Stardew Valley",
+                windows: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            roots(&stardew),
+            [("StardewValley", "%APPDATA%/StardewValley")]
+        );
+        assert_eq!(stardew.include_patterns, ["*.ini", "Saves/"]);
+
+        let mut request = AddGameRequest {
+            name: "Stardew Valley".to_string(),
+            save_location: String::new(),
+            exe_name: None,
+            cover_image: None,
+            save_roots: Vec::new(),
+            include_patterns: vec!["*.bak".to_string()],
+            exclude_patterns: Vec::new(),
+            wine_prefix: None,
+            steam_app_id: None,
+            watch_saves: false,
+            snapshot_on_exit: false,
+            snapshot_on_start: false,
+        };
+        stardew.apply(&mut request);
+        assert_eq!(request.save_location, "%APPDATA%/StardewValley");
+        assert_eq!(request.include_patterns, ["*.bak", "*.ini", "Saves/"]);
+        assert_eq!(request.steam_app_id.as_deref(), Some("413150"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_native_locations_win_over_windows_ones() {
+        let manifest = Manifest::parse(FIXTURE).unwrap();
+        let native = Lookup {
+            name: "Stardew Valley",
+            ..Default::default()
+        };
+        let stardew = lookup(&manifest, native);
+        assert_eq!(
+            roots(&stardew),
+            [("StardewValley", "$XDG_CONFIG_HOME/StardewValley")]
+        );
+        assert!(stardew.include_patterns.is_empty());
+    }
+
+    #[test]
+    fn test_locations_split_at_the_first_wildcard() {
+        let split = |path: &str| {
+            let (folder, glob) = split_glob(path);
+            (folder.join("/"), glob)
+        };
+        assert_eq!(
+            split("$HOME/.game/saves/"),
+            ("$HOME/.game/saves".to_string(), None)
+        );
+        assert_eq!(
+            split("%APPDATA%/Game/*.sav"),
+            ("%APPDATA%/Game".to_string(), Some("*.sav".to_string()))
+        );
+        assert_eq!(
+            split("/steam/userdata/*/620/remote"),
+            (
+                "/steam/userdata".to_string(),
+                Some("*/620/remote/".to_string())
+            )
+        );
+        assert_eq!(
+            split("%APPDATA%/Game/settings.ini"),
+            (
+                "%APPDATA%/Game".to_string(),
+                Some("settings.ini".to_string())
+            )
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use crate::config::Config;
+use crate::game::Game;
+use crate::operation::Operation;
+use crate::sessions::{Session, SessionLog};
+use crate::snapshot::{CreateSnapshotRequest, Snapshot, SnapshotKind};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorSettings {
+    /// Track play sessions of every game with an executable name. When off, no
+    /// sessions are recorded and only games that snapshot on start or exit are
+    /// polled for.
+    pub enabled: bool,
+    pub poll_interval_secs: u64,
+}
+
+impl Default for MonitorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            poll_interval_secs: 5,
+        }
+    }
+}
+
+pub enum MonitorEvent {
+    SessionStarted(Session),
+    SessionEnded(Session),
+    SnapshotCreated(Snapshot),
+}
+
+enum Message {
+    Refresh,
+}
+
+type MonitorListener = Box<dyn Fn(MonitorEvent) + Send>;
+
+/// Polls the running processes for each game's `exe_name` and reports when games
+/// start and exit, recording each run as a session and taking snapshots on those
+/// transitions for games that ask for it.
+pub struct ProcessMonitor {
+    sender: Sender<Message>,
+}
+
+impl ProcessMonitor {
+    /// Starts the monitor thread, which records sessions in `sessions`.
+    pub fn start(
+        sessions: Arc<Mutex<SessionLog>>,
+        listener: impl Fn(MonitorEvent) + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || run(receiver, sessions, Box::new(listener)));
+        Self { sender }
+    }
+
+    /// Makes the monitor re-read the config before its next poll.
+    pub fn refresh(&self) {
+        let _ = self.sender.send(Message::Refresh);
+    }
+}
+
+fn run(receiver: Receiver<Message>, sessions: Arc<Mutex<SessionLog>>, listener: MonitorListener) {
+    let mut config = Config::load().ok();
+    // every game currently running, with its session when sessions are tracked
+    let mut running: HashMap<String, Option<Session>> = HashMap::new();
+    let mut first_poll = true;
+
+    loop {
+        let settings = config
+            .as_ref()
+            .map(|config| config.monitor.clone())
+            .unwrap_or_default();
+
+        match receiver.recv_timeout(Duration::from_secs(settings.poll_interval_secs.max(1))) {
+            Ok(Message::Refresh) => {
+                config = Config::load().ok();
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let config = config.as_ref();
+        let running_now = config
+            .map(|config| running_games(config, settings.enabled))
+            .unwrap_or_default();
+        let now = Utc::now();
+
+        let (started, stopped) = transitions(&running, &running_now);
+
+        for game in started {
+            // with tracking off only the snapshot hooks run. A game already
+            // running when Checkpoint starts may continue a session from the
+            // previous run
+            let session = settings
+                .enabled
+                .then(|| record_start(&sessions, &game.id, now, first_poll));
+            running.insert(game.id.clone(), session.clone());
+            if let Some(session) = &session {
+                listener(MonitorEvent::SessionStarted(session.clone()));
+            }
+
+            if let (true, Some(config)) = (game.snapshot_on_start, config) {
+                session_snapshot(
+                    config,
+                    &game.id,
+                    session.as_ref(),
+                    "Before playing",
+                    &listener,
+                );
+            }
+        }
+
+        if first_poll {
+            first_poll = false;
+            discard_stale_sessions(&sessions, &running);
+        }
+
+        for game_id in stopped {
+            let Some(session) = running.remove(&game_id) else {
+                continue;
+            };
+
+            let session = session.map(|session| record_end(&sessions, session, now));
+            if let Some(session) = &session {
+                listener(MonitorEvent::SessionEnded(session.clone()));
+            }
+
+            // a game removed from the config while running has nothing to snapshot
+            let game = config.and_then(|config| {
+                config
+                    .games
+                    .iter()
+                    .find(|g| g.id == game_id)
+                    .map(|game| (config, game))
+            });
+            if let Some((config, game)) = game {
+                if game.snapshot_on_exit {
+                    session_snapshot(
+                        config,
+                        &game.id,
+                        session.as_ref(),
+                        "After playing",
+                        &listener,
+                    );
+                }
+            }
+        }
+    }
+}
+
+// with session tracking off, games that don't snapshot on start or exit count
+// as stopped, and nothing is polled when no game does
+fn running_games(config: &Config, track_sessions: bool) -> Vec<&Game> {
+    let tracked: Vec<&Game> = config
+        .games
+        .iter()
+        .filter(|g| g.exe_name.as_deref().is_some_and(|exe| !exe.is_empty()))
+        .filter(|g| track_sessions || g.snapshot_on_start || g.snapshot_on_exit)
+        .collect();
+
+    if tracked.is_empty() {
+        return Vec::new();
+    }
+
+    let process_names = crate::process::running_process_names();
+
+    tracked
+        .into_iter()
+        .filter(|g| {
+            g.exe_name
+                .as_deref()
+                .is_some_and(|exe| crate::process::is_running_in(&process_names, exe))
+        })
+        .collect()
+}
+
+// the log stays locked while it is changed and saved, so no other change to it
+// gets lost
+fn record_start(
+    sessions: &Mutex<SessionLog>,
+    game_id: &str,
+    now: DateTime<Utc>,
+    resume: bool,
+) -> Session {
+    let Ok(mut log) = sessions.lock() else {
+        eprintln!("Failed to record session: the session log is unavailable");
+        return SessionLog::default().start(game_id, now);
+    };
+
+    if resume {
+        if let Some(session) = log.open_session(game_id) {
+            return session.clone();
+        }
+    }
+
+    let session = log.start(game_id, now);
+    if let Err(e) = log.save() {
+        eprintln!("Failed to save session: {}", e);
+    }
+    session
+}
+
+fn record_end(sessions: &Mutex<SessionLog>, session: Session, now: DateTime<Utc>) -> Session {
+    let Ok(mut log) = sessions.lock() else {
+        eprintln!("Failed to record session: the session log is unavailable");
+        return session;
+    };
+
+    let Some(ended) = log.end(&session.id, now) else {
+        // removed meanwhile, e.g. the game was deleted
+        return session;
+    };
+    if let Err(e) = log.save() {
+        eprintln!("Failed to save session: {}", e);
+    }
+    ended
+}
+
+// sessions left open by an earlier run whose game isn't running anymore
+fn discard_stale_sessions(
+    sessions: &Mutex<SessionLog>,
+    running: &HashMap<String, Option<Session>>,
+) {
+    let Ok(mut log) = sessions.lock() else {
+        return;
+    };
+
+    let keep: Vec<String> = running.values().flatten().map(|s| s.id.clone()).collect();
+    let before = log.sessions.len();
+    log.discard_open_sessions(&keep);
+
+    if log.sessions.len() != before {
+        if let Err(e) = log.save() {
+            eprintln!("Failed to save sessions: {}", e);
+        }
+    }
+}
+
+// games that weren't running at the last poll but are now, and ids of games that stopped
+fn transitions<'a, T>(
+    running: &HashMap<String, T>,
+    running_now: &[&'a Game],
+) -> (Vec<&'a Game>, Vec<String>) {
+    let started = running_now
+        .iter()
+        .copied()
+        .filter(|g| !running.contains_key(&g.id))
+        .collect();
+
+    let stopped = running
+        .keys()
+        .filter(|id| !running_now.iter().any(|g| &g.id == *id))
+        .cloned()
+        .collect();
+
+    (started, stopped)
+}
+
+fn session_snapshot(
+    config: &Config,
+    game_id: &str,
+    session: Option<&Session>,
+    label: &str,
+    listener: &MonitorListener,
+) {
+    let request = CreateSnapshotRequest {
+        game_id: game_id.to_string(),
+        name: Some(format!(
+            "{} {}",
+            label,
+            Utc::now().format("%Y-%m-%d %H:%M:%S")
+        )),
+        kind: SnapshotKind::Auto,
+    };
+
+    match crate::snapshot::create_snapshot(
+        config,
+        &request,
+        session.map(|session| session.id.clone()),
+        &Operation::untracked(),
+    ) {
+        Ok(snapshot) => {
+            crate::retention::apply(
+                game_id,
+                &config.retention_policy(game_id),
+                &config.backup_location,
+            );
+            listener(MonitorEvent::SnapshotCreated(snapshot));
+        }
+        Err(e) => eprintln!("Snapshot of game {} failed: {}", game_id, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(id: &str) -> Game {
+        let mut game = Game::new(
+            id.to_string(),
+            String::new(),
+            Some(format!("{}.exe", id)),
+            None,
+        );
+        game.id = id.to_string();
+        game
+    }
+
+    #[test]
+    fn test_transitions_report_started_and_stopped_games() {
+        let (a, b, c) = (game("a"), game("b"), game("c"));
+        let mut log = SessionLog::default();
+        let running = HashMap::from([
+            ("a".to_string(), log.start("a", Utc::now())),
+            ("b".to_string(), log.start("b", Utc::now())),
+        ]);
+
+        let (started, stopped) = transitions(&running, &[&b, &c]);
+        assert_eq!(
+            started.iter().map(|g| g.id.as_str()).collect::<Vec<_>>(),
+            vec!["c"]
+        );
+        assert_eq!(stopped, vec!["a"]);
+
+        let (started, stopped) = transitions(&running, &[&a, &b]);
+        assert!(started.is_empty());
+        assert!(stopped.is_empty());
+    }
+}
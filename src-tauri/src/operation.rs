@@ -0,0 +1,125 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const CANCELLED_MESSAGE: &str = "Operation cancelled";
+
+// reporting every file of a save folder with thousands of small files would
+// flood the frontend, so updates are throttled
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationProgress {
+    pub operation_id: String,
+    pub stage: String,
+    pub files_processed: usize,
+    pub files_total: usize,
+    pub bytes_processed: u64,
+    pub bytes_total: u64,
+    pub current_file: Option<String>,
+}
+
+type ProgressReporter = Box<dyn Fn(&OperationProgress) + Send + Sync>;
+
+/// Tracks a long-running snapshot operation: reports progress to whoever
+/// started it and lets them cancel it between files.
+pub struct Operation {
+    cancelled: Arc<AtomicBool>,
+    reporter: Option<ProgressReporter>,
+    progress: Mutex<(OperationProgress, Option<Instant>)>,
+}
+
+impl Operation {
+    pub fn new(id: &str) -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            reporter: None,
+            progress: Mutex::new((
+                OperationProgress {
+                    operation_id: id.to_string(),
+                    stage: String::new(),
+                    files_processed: 0,
+                    files_total: 0,
+                    bytes_processed: 0,
+                    bytes_total: 0,
+                    current_file: None,
+                },
+                None,
+            )),
+        }
+    }
+
+    /// An operation nobody listens to and nobody can cancel.
+    pub fn untracked() -> Self {
+        Self::new("")
+    }
+
+    pub fn with_reporter(
+        mut self,
+        reporter: impl Fn(&OperationProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.reporter = Some(Box::new(reporter));
+        self
+    }
+
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED_MESSAGE.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Starts a new stage, resetting the counters.
+    pub fn begin(&self, stage: &str, files_total: usize, bytes_total: u64) {
+        let Ok(mut guard) = self.progress.lock() else {
+            return;
+        };
+        let (progress, last_report) = &mut *guard;
+
+        progress.stage = stage.to_string();
+        progress.files_processed = 0;
+        progress.files_total = files_total;
+        progress.bytes_processed = 0;
+        progress.bytes_total = bytes_total;
+        progress.current_file = None;
+
+        *last_report = Some(Instant::now());
+        self.report(progress);
+    }
+
+    /// Records that `current_file` (of `bytes` bytes) has been processed.
+    pub fn advance(&self, current_file: &str, bytes: u64) {
+        let Ok(mut guard) = self.progress.lock() else {
+            return;
+        };
+        let (progress, last_report) = &mut *guard;
+
+        progress.files_processed += 1;
+        progress.bytes_processed += bytes;
+        progress.current_file = Some(current_file.to_string());
+
+        let due = last_report.is_none_or(|at| at.elapsed() >= REPORT_INTERVAL);
+        let done = progress.files_processed >= progress.files_total;
+
+        if due || done {
+            *last_report = Some(Instant::now());
+            self.report(progress);
+        }
+    }
+
+    fn report(&self, progress: &OperationProgress) {
+        if let Some(reporter) = &self.reporter {
+            reporter(progress);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const BUILT_IN: &[&str] = &["HOME", "XDG_DATA_HOME", "XDG_CONFIG_HOME", "STEAM_ROOT"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathVariable {
+    pub name: String,
+    pub path: String,
+    // defined in the config rather than built in
+    pub custom: bool,
+}
+
+/// Variables save locations can start with, like `$XDG_DATA_HOME/Game`, so a
+/// config keeps working when copied to another user or machine.
+#[derive(Debug, Clone, Default)]
+pub struct PathVariables {
+    values: Vec<PathVariable>,
+}
+
+impl PathVariables {
+    /// The built-in variables for the current user, plus the `custom` ones.
+    pub fn new(custom: &BTreeMap<String, String>) -> Self {
+        let home = dirs::home_dir();
+        let steam_root = home.as_deref().and_then(|home| {
+            crate::discovery::steam::steam_roots(home)
+                .into_iter()
+                .next()
+        });
+        let built_in = [
+            ("HOME", home),
+            ("XDG_DATA_HOME", dirs::data_dir()),
+            ("XDG_CONFIG_HOME", dirs::config_dir()),
+            ("STEAM_ROOT", steam_root),
+        ];
+
+        let mut variables = Self::default();
+        for (name, path) in built_in {
+            if let Some(path) = path {
+                variables.push(name, &path.to_string_lossy(), false);
+            }
+        }
+        for (name, path) in custom {
+            if validate(name, path).is_ok() {
+                variables.push(name, path, true);
+            }
+        }
+        variables
+    }
+
+    fn push(&mut self, name: &str, path: &str, custom: bool) {
+        self.values.push(PathVariable {
+            name: name.to_string(),
+            path: path.to_string(),
+            custom,
+        });
+    }
+
+    pub fn list(&self) -> &[PathVariable] {
+        &self.values
+    }
+
+    /// Replaces a leading `$NAME` or `${NAME}` with the folder it stands for.
+    /// Other paths are returned as they are.
+    pub fn expand(&self, path: &str) -> Result<String, String> {
+        let Some(rest) = path.strip_prefix('$') else {
+            return Ok(path.to_string());
+        };
+
+        let (name, rest) = match rest.strip_prefix('{') {
+            Some(braced) => braced
+                .split_once('}')
+                .ok_or_else(|| format!("Missing '}}' in {}", path))?,
+            None => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                rest.split_at(end)
+            }
+        };
+
+        let variable = self
+            .values
+            .iter()
+            .find(|variable| variable.name == name)
+            .ok_or_else(|| format!("Unknown path variable ${} in {}", name, path))?;
+        Ok(format!("{}{}", variable.path, rest))
+    }
+
+    /// An absolute path under a variable's folder rewritten to use the variable,
+    /// the most specific one when several match. None when no variable fits.
+    pub fn contract(&self, path: &str) -> Option<String> {
+        let path = Path::new(path);
+        if !path.is_absolute() {
+            return None;
+        }
+
+        let mut best: Option<(&PathVariable, &Path, usize)> = None;
+        for variable in &self.values {
+            let root = Path::new(&variable.path);
+            let depth = root.components().count();
+            // a variable for the filesystem root would match everything
+            if depth < 2 {
+                continue;
+            }
+
+            if let Ok(relative) = path.strip_prefix(root) {
+                if best.is_none_or(|(_, _, best_depth)| depth > best_depth) {
+                    best = Some((variable, relative, depth));
+                }
+            }
+        }
+
+        let (variable, relative, _) = best?;
+        let relative = crate::snapshot::manifest_path(relative);
+        if relative.is_empty() {
+            Some(format!("${}", variable.name))
+        } else {
+            Some(format!("${}/{}", variable.name, relative))
+        }
+    }
+}
+
+/// Checks a custom variable, it can't replace a built-in one and has to stand
+/// for an absolute path.
+pub fn validate(name: &str, path: &str) -> Result<(), String> {
+    let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_name {
+        return Err(format!(
+            "Invalid variable name '{}', use letters, digits and '_'",
+            name
+        ));
+    }
+    if BUILT_IN.contains(&name) {
+        return Err(format!("${} is built in and can't be changed", name));
+    }
+    if !Path::new(path).is_absolute() {
+        return Err(format!("${} has to be an absolute path", name));
+    }
+    Ok(())
+}
+
+// the fixtures are unix paths
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn variables() -> PathVariables {
+        let mut variables = PathVariables::default();
+        variables.push("HOME", "/home/alice", false);
+        variables.push("XDG_DATA_HOME", "/home/alice/.local/share", false);
+        variables.push("STEAM_ROOT", "/home/alice/.local/share/Steam", false);
+        variables.push("GAMES", "/mnt/games", true);
+        variables
+    }
+
+    #[test]
+    fn test_paths_contract_to_the_most_specific_variable_and_expand_back() {
+        let variables = variables();
+
+        for (absolute, portable) in [
+            ("/home/alice/Documents/Saves", "$HOME/Documents/Saves"),
+            ("/home/alice/.local/share/Game", "$XDG_DATA_HOME/Game"),
+            (
+                "/home/alice/.local/share/Steam/userdata/1/620/remote",
+                "$STEAM_ROOT/userdata/1/620/remote",
+            ),
+            ("/mnt/games/prefix", "$GAMES/prefix"),
+            ("/home/alice", "$HOME"),
+        ] {
+            assert_eq!(variables.contract(absolute).as_deref(), Some(portable));
+            assert_eq!(variables.expand(portable).unwrap(), absolute);
+        }
+
+        assert_eq!(variables.contract("/home/alicia/saves"), None);
+        assert_eq!(variables.contract("%APPDATA%/Game"), None);
+        assert_eq!(
+            variables.expand("${HOME}/saves").unwrap(),
+            "/home/alice/saves"
+        );
+        assert_eq!(variables.expand("/srv/saves").unwrap(), "/srv/saves");
+        assert!(variables.expand("$NOPE/saves").is_err());
+    }
+
+    #[test]
+    fn test_save_locations_move_onto_new_variables() {
+        let before = variables();
+        let mut after = variables();
+        after.push("SAVES", "/mnt/games/prefix/saves", true);
+
+        let mut game = crate::game::Game::new(
+            "Game".to_string(),
+            "/mnt/games/prefix/saves/slot1".to_string(),
+            None,
+            None,
+        );
+        assert!(game.make_portable(&before));
+        assert_eq!(game.save_roots[0].path, "$GAMES/prefix/saves/slot1");
+        assert_eq!(game.save_location, "/mnt/games/prefix/saves/slot1");
+        assert!(!game.make_portable(&before));
+
+        assert!(game.rebase_paths(&before, &after));
+        assert_eq!(game.save_roots[0].path, "$SAVES/slot1");
+
+        // without the variable the path is spelled out again
+        let mut removed = PathVariables::default();
+        removed.push("HOME", "/home/alice", false);
+        assert!(game.rebase_paths(&after, &removed));
+        assert_eq!(game.save_roots[0].path, "/mnt/games/prefix/saves/slot1");
+    }
+
+    #[test]
+    fn test_custom_variables_are_checked() {
+        assert!(validate("GAMES", "/mnt/games").is_ok());
+        assert!(validate("HOME", "/mnt/games").is_err());
+        assert!(validate("2GAMES", "/mnt/games").is_err());
+        assert!(validate("MY-GAMES", "/mnt/games").is_err());
+        assert!(validate("GAMES", "games").is_err());
+    }
+}
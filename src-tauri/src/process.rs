@@ -1,27 +1,33 @@
+use std::collections::HashSet;
 use sysinfo::{ProcessRefreshKind, RefreshKind, System};
 
 pub fn is_process_running(process_name: &str) -> Result<bool, String> {
+    Ok(is_running_in(&running_process_names(), process_name))
+}
+
+/// Lowercased names of every running process, for checking several games with one scan.
+pub fn running_process_names() -> HashSet<String> {
     let s =
         System::new_with_specifics(RefreshKind::new().with_processes(ProcessRefreshKind::new()));
 
+    s.processes()
+        .values()
+        .map(|process| process.name().to_lowercase())
+        .collect()
+}
+
+pub fn is_running_in(process_names: &HashSet<String>, process_name: &str) -> bool {
     let target_name = process_name.to_lowercase();
 
-    for process in s.processes_by_exact_name(target_name.as_str()) {
-        if process.name().to_lowercase() == target_name {
-            return Ok(true);
-        }
+    if process_names.contains(&target_name) {
+        return true;
     }
 
-    if target_name.ends_with(".exe") {
-        let without_exe = &target_name[..target_name.len() - 4];
-        for process in s.processes_by_exact_name(without_exe) {
-            if process.name().to_lowercase() == without_exe {
-                return Ok(true);
-            }
-        }
+    // Windows executables show up without their extension on some platforms
+    match target_name.strip_suffix(".exe") {
+        Some(without_exe) => process_names.contains(without_exe),
+        None => false,
     }
-
-    Ok(false)
 }
 
 #[cfg(test)]
@@ -33,6 +39,15 @@ mod tests {
         // actually, its better to test with a known process? maybe yes
         let result = is_process_running("nonexistent_process_12345.exe");
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), false);
+        assert!(!result.unwrap());
+    }
+
+    #[test]
+    fn test_is_running_in_ignores_case_and_exe_suffix() {
+        let names = HashSet::from(["game".to_string(), "launcher.exe".to_string()]);
+        assert!(is_running_in(&names, "Game.exe"));
+        assert!(is_running_in(&names, "game"));
+        assert!(is_running_in(&names, "Launcher.EXE"));
+        assert!(!is_running_in(&names, "other.exe"));
     }
 }
@@ -0,0 +1,445 @@
+use crate::snapshot::{Snapshot, SnapshotKind};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Rules deciding which snapshots of a game are kept. Unset rules don't apply;
+/// with none of `keep_last`, `keep_daily_days` and `keep_weekly_weeks` set, every
+/// regular snapshot is kept.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Keep the N most recent snapshots.
+    pub keep_last: Option<usize>,
+    /// Keep the newest snapshot of each day for this many days.
+    pub keep_daily_days: Option<u32>,
+    /// Keep the newest snapshot of each week for this many weeks.
+    pub keep_weekly_weeks: Option<u32>,
+    /// Cap on the disk space a game's snapshots take up, oldest go first. Files
+    /// several snapshots share count once. Pinned snapshots are always kept and
+    /// don't count.
+    pub max_total_size: Option<u64>,
+    /// Keep only this many auto-backups taken before restores. They are not
+    /// counted by the other keep rules.
+    pub max_auto_backups: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PruneReason {
+    // not covered by keep_last / daily / weekly
+    NotRetained,
+    AutoBackupLimit,
+    SizeLimit,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrunedSnapshot {
+    pub id: String,
+    pub name: String,
+    pub timestamp: DateTime<Utc>,
+    pub size: u64,
+    pub reason: PruneReason,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetentionPlan {
+    pub kept: Vec<String>,
+    pub pruned: Vec<PrunedSnapshot>,
+    // sum of the pruned snapshots' sizes. Files shared with kept snapshots stay
+    // in the object store, so less than this may actually be freed
+    pub pruned_size: u64,
+}
+
+/// What the snapshots of a game take up on disk: the objects each references
+/// and their size in the store. Snapshots it doesn't list, e.g. locked ones,
+/// count with their own stored size.
+#[derive(Debug, Default)]
+pub struct DiskUsage {
+    objects: HashMap<String, Vec<(String, u64)>>,
+}
+
+impl DiskUsage {
+    pub fn of(snapshots: &[Snapshot], backup_location: &str) -> Self {
+        let objects = snapshots
+            .iter()
+            .filter(|s| !s.locked)
+            .filter_map(|s| {
+                crate::snapshot::snapshot_objects(&s.game_id, &s.id, backup_location)
+                    .ok()
+                    .flatten()
+                    .map(|objects| (s.id.clone(), objects))
+            })
+            .collect();
+        Self { objects }
+    }
+
+    // what `snapshot` adds to the objects already counted
+    fn add(&self, snapshot: &Snapshot, counted: &mut HashSet<String>) -> u64 {
+        match self.objects.get(&snapshot.id) {
+            Some(objects) => objects
+                .iter()
+                .filter(|(hash, _)| counted.insert(hash.clone()))
+                .map(|(_, size)| size)
+                .sum(),
+            None => snapshot.stored_size,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.keep_last == Some(0) {
+            return Err("Keep last must be at least 1".to_string());
+        }
+        if self.max_total_size == Some(0) {
+            return Err("Size limit must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+
+    fn has_keep_rules(&self) -> bool {
+        self.keep_last.is_some()
+            || self.keep_daily_days.is_some()
+            || self.keep_weekly_weeks.is_some()
+    }
+}
+
+/// Works out which of `snapshots` the policy keeps. The newest snapshot and
+/// pinned snapshots are always kept. `usage` is what the size cap is checked against.
+pub fn plan(
+    snapshots: &[Snapshot],
+    usage: &DiskUsage,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> RetentionPlan {
+    let mut snapshots: Vec<&Snapshot> = snapshots.iter().collect();
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.timestamp));
+
+    let (auto_backups, regular): (Vec<&Snapshot>, Vec<&Snapshot>) = snapshots
+        .iter()
+        .partition(|s| s.kind == SnapshotKind::AutoBackup);
+
+    let mut pruned: Vec<(&Snapshot, PruneReason)> = Vec::new();
+
+    if policy.has_keep_rules() {
+        let mut kept = HashSet::new();
+
+        if let Some(keep_last) = policy.keep_last {
+            kept.extend(regular.iter().take(keep_last).map(|s| s.id.as_str()));
+        }
+        if let Some(days) = policy.keep_daily_days {
+            kept.extend(newest_per_period(
+                &regular,
+                now - Duration::days(days.into()),
+                |date| (date.year(), date.ordinal()),
+            ));
+        }
+        if let Some(weeks) = policy.keep_weekly_weeks {
+            kept.extend(newest_per_period(
+                &regular,
+                now - Duration::weeks(weeks.into()),
+                |date| {
+                    let week = date.iso_week();
+                    (week.year(), week.week())
+                },
+            ));
+        }
+
+        pruned.extend(
+            regular
+                .iter()
+                .filter(|s| !kept.contains(s.id.as_str()))
+                .map(|s| (*s, PruneReason::NotRetained)),
+        );
+    }
+
+    if let Some(max_auto_backups) = policy.max_auto_backups {
+        pruned.extend(
+            auto_backups
+                .iter()
+                .skip(max_auto_backups)
+                .map(|s| (*s, PruneReason::AutoBackupLimit)),
+        );
+    }
+
+    if let Some(max_total_size) = policy.max_total_size {
+        let mut total = 0;
+        let mut counted = HashSet::new();
+
+        for (index, snapshot) in snapshots.iter().enumerate() {
+            if snapshot.pinned || pruned.iter().any(|(s, _)| s.id == snapshot.id) {
+                continue;
+            }
+
+            total += usage.add(snapshot, &mut counted);
+            if total > max_total_size && index > 0 {
+                pruned.push((snapshot, PruneReason::SizeLimit));
+            }
+        }
+    }
+
+    // the newest snapshot survives every rule, there's no point in a policy
+    // that leaves a game without any snapshot
+    if let Some(newest) = snapshots.first() {
+        pruned.retain(|(s, _)| s.id != newest.id);
+    }
+    pruned.retain(|(s, _)| !s.pinned);
+
+    let pruned_ids: HashSet<&str> = pruned.iter().map(|(s, _)| s.id.as_str()).collect();
+    let mut pruned: Vec<PrunedSnapshot> = pruned
+        .into_iter()
+        .map(|(s, reason)| PrunedSnapshot {
+            id: s.id.clone(),
+            name: s.name.clone(),
+            timestamp: s.timestamp,
+            size: s.size,
+            reason,
+        })
+        .collect();
+    pruned.sort_by_key(|s| std::cmp::Reverse(s.timestamp));
+
+    RetentionPlan {
+        kept: snapshots
+            .iter()
+            .filter(|s| !pruned_ids.contains(s.id.as_str()))
+            .map(|s| s.id.clone())
+            .collect(),
+        pruned_size: pruned.iter().map(|s| s.size).sum(),
+        pruned,
+    }
+}
+
+// `snapshots` must be sorted newest first
+fn newest_per_period<'a, K: Eq + std::hash::Hash>(
+    snapshots: &[&'a Snapshot],
+    since: DateTime<Utc>,
+    period: impl Fn(NaiveDate) -> K,
+) -> Vec<&'a str> {
+    let mut seen = HashSet::new();
+
+    snapshots
+        .iter()
+        .filter(|s| s.timestamp > since)
+        .filter(|s| seen.insert(period(s.timestamp.with_timezone(&Local).date_naive())))
+        .map(|s| s.id.as_str())
+        .collect()
+}
+
+/// Deletes the snapshots of a game the policy doesn't keep.
+pub fn enforce(
+    game_id: &str,
+    policy: &RetentionPolicy,
+    backup_location: &str,
+) -> Result<RetentionPlan, String> {
+    let snapshots = crate::snapshot::list_snapshots(game_id, backup_location)?;
+    let usage = DiskUsage::of(&snapshots, backup_location);
+    let plan = plan(&snapshots, &usage, policy, Utc::now());
+
+    if !plan.pruned.is_empty() {
+        let ids: Vec<String> = plan.pruned.iter().map(|s| s.id.clone()).collect();
+        crate::snapshot::delete_snapshots(&ids, game_id, backup_location)?;
+    }
+
+    Ok(plan)
+}
+
+/// Enforces the policy after a snapshot was added. Failures are only logged,
+/// they shouldn't fail the snapshot that triggered them.
+pub fn apply(game_id: &str, policy: &RetentionPolicy, backup_location: &str) {
+    if let Err(e) = enforce(game_id, policy, backup_location) {
+        eprintln!("Failed to apply retention policy: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fixed, off-the-hour "now" so snapshots a minute apart never straddle
+    // midnight in whatever timezone the tests run in
+    fn now() -> DateTime<Utc> {
+        "2024-06-15T10:21:00Z".parse().unwrap()
+    }
+
+    fn snapshot(id: &str, age: Duration, size: u64, kind: SnapshotKind) -> Snapshot {
+        Snapshot {
+            id: id.to_string(),
+            game_id: "game".to_string(),
+            timestamp: now() - age,
+            name: id.to_string(),
+            path: String::new(),
+            size,
+            stored_size: size,
+            file_count: 1,
+            kind,
+            pinned: false,
+            session_id: None,
+            encrypted: false,
+            locked: false,
+        }
+    }
+
+    fn pruned_ids(plan: &RetentionPlan) -> Vec<&str> {
+        plan.pruned.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_empty_policy_keeps_everything() {
+        let snapshots = vec![
+            snapshot("a", Duration::hours(1), 10, SnapshotKind::Manual),
+            snapshot("b", Duration::days(400), 10, SnapshotKind::AutoBackup),
+        ];
+
+        let plan = plan(
+            &snapshots,
+            &DiskUsage::default(),
+            &RetentionPolicy::default(),
+            now(),
+        );
+        assert!(plan.pruned.is_empty());
+        assert_eq!(plan.kept.len(), 2);
+    }
+
+    #[test]
+    fn test_keep_last_and_daily_combine() {
+        let snapshots = vec![
+            snapshot("now", Duration::minutes(5), 10, SnapshotKind::Manual),
+            snapshot("earlier", Duration::minutes(10), 10, SnapshotKind::Manual),
+            snapshot("day-2", Duration::days(2), 10, SnapshotKind::Manual),
+            snapshot(
+                "day-2-older",
+                Duration::days(2) + Duration::minutes(1),
+                10,
+                SnapshotKind::Manual,
+            ),
+            snapshot("day-20", Duration::days(20), 10, SnapshotKind::Manual),
+        ];
+        let policy = RetentionPolicy {
+            keep_last: Some(1),
+            keep_daily_days: Some(7),
+            ..Default::default()
+        };
+
+        let plan = plan(&snapshots, &DiskUsage::default(), &policy, now());
+        // "earlier" is neither the last snapshot nor the newest of its day
+        assert_eq!(pruned_ids(&plan), vec!["earlier", "day-2-older", "day-20"]);
+        assert_eq!(plan.pruned_size, 30);
+        assert!(plan.kept.contains(&"day-2".to_string()));
+        assert!(plan
+            .pruned
+            .iter()
+            .all(|s| s.reason == PruneReason::NotRetained));
+    }
+
+    #[test]
+    fn test_auto_backups_are_capped_separately() {
+        let snapshots = vec![
+            snapshot(
+                "backup-1",
+                Duration::minutes(1),
+                10,
+                SnapshotKind::AutoBackup,
+            ),
+            snapshot("manual", Duration::minutes(2), 10, SnapshotKind::Manual),
+            snapshot(
+                "backup-2",
+                Duration::minutes(3),
+                10,
+                SnapshotKind::AutoBackup,
+            ),
+            snapshot(
+                "backup-3",
+                Duration::minutes(4),
+                10,
+                SnapshotKind::AutoBackup,
+            ),
+        ];
+        let policy = RetentionPolicy {
+            keep_last: Some(1),
+            max_auto_backups: Some(1),
+            ..Default::default()
+        };
+
+        let plan = plan(&snapshots, &DiskUsage::default(), &policy, now());
+        assert_eq!(pruned_ids(&plan), vec!["backup-2", "backup-3"]);
+        assert!(plan
+            .pruned
+            .iter()
+            .all(|s| s.reason == PruneReason::AutoBackupLimit));
+    }
+
+    #[test]
+    fn test_size_limit_prunes_oldest_but_never_the_newest() {
+        let snapshots = vec![
+            snapshot("new", Duration::minutes(1), 60, SnapshotKind::Manual),
+            snapshot("mid", Duration::minutes(2), 30, SnapshotKind::Manual),
+            snapshot("old", Duration::minutes(3), 30, SnapshotKind::Manual),
+        ];
+        let policy = RetentionPolicy {
+            max_total_size: Some(100),
+            ..Default::default()
+        };
+
+        let result = plan(&snapshots, &DiskUsage::default(), &policy, now());
+        assert_eq!(pruned_ids(&result), vec!["old"]);
+        assert_eq!(result.pruned[0].reason, PruneReason::SizeLimit);
+
+        let policy = RetentionPolicy {
+            max_total_size: Some(10),
+            ..Default::default()
+        };
+        let result = plan(&snapshots, &DiskUsage::default(), &policy, now());
+        assert_eq!(result.kept, vec!["new"]);
+    }
+
+    #[test]
+    fn test_size_limit_counts_shared_files_once_and_skips_pinned_snapshots() {
+        let mut pinned = snapshot("pinned", Duration::minutes(4), 100, SnapshotKind::Manual);
+        pinned.pinned = true;
+        let snapshots = vec![
+            snapshot("new", Duration::minutes(1), 60, SnapshotKind::Manual),
+            snapshot("mid", Duration::minutes(2), 60, SnapshotKind::Manual),
+            snapshot("old", Duration::minutes(3), 60, SnapshotKind::Manual),
+            pinned,
+        ];
+        // every snapshot shares a 50 byte file and adds one of 10 bytes
+        let objects = |own: &str| vec![("shared".to_string(), 50), (own.to_string(), 10)];
+        let usage = DiskUsage {
+            objects: HashMap::from([
+                ("new".to_string(), objects("a")),
+                ("mid".to_string(), objects("b")),
+                ("old".to_string(), objects("c")),
+                ("pinned".to_string(), vec![("d".to_string(), 100)]),
+            ]),
+        };
+        let policy = RetentionPolicy {
+            max_total_size: Some(75),
+            ..Default::default()
+        };
+
+        let result = plan(&snapshots, &usage, &policy, now());
+        assert_eq!(pruned_ids(&result), vec!["old"]);
+        assert_eq!(result.kept, vec!["new", "mid", "pinned"]);
+    }
+
+    #[test]
+    fn test_pinned_snapshots_are_never_pruned() {
+        let mut pinned = snapshot("pinned", Duration::days(30), 50, SnapshotKind::Manual);
+        pinned.pinned = true;
+        let snapshots = vec![
+            snapshot("new", Duration::minutes(1), 50, SnapshotKind::Manual),
+            snapshot("old", Duration::days(10), 50, SnapshotKind::Manual),
+            pinned,
+        ];
+        let policy = RetentionPolicy {
+            keep_last: Some(1),
+            max_total_size: Some(60),
+            ..Default::default()
+        };
+
+        let plan = plan(&snapshots, &DiskUsage::default(), &policy, now());
+        assert_eq!(pruned_ids(&plan), vec!["old"]);
+        assert_eq!(plan.kept, vec!["new", "pinned"]);
+    }
+}
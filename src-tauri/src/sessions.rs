@@ -0,0 +1,208 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+const SESSIONS_FILE: &str = "sessions.json";
+
+// snapshots taken this long after a session ended still belong to it,
+// e.g. the one taken when the game exits
+const LINK_WINDOW_MINUTES: i64 = 10;
+
+/// One stretch of playing a game, from the process monitor seeing it start to seeing it exit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub game_id: String,
+    pub started_at: DateTime<Utc>,
+    // None while the game is still running
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_secs: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlaytimeSummary {
+    pub game_id: String,
+    pub total_secs: i64,
+    pub session_count: usize,
+    pub last_played: Option<DateTime<Utc>>,
+}
+
+/// Every recorded session, stored next to the config.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SessionLog {
+    pub sessions: Vec<Session>,
+}
+
+impl SessionLog {
+    pub fn load() -> Result<Self, String> {
+        let path = Self::path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read sessions: {}", e))?;
+        serde_json::from_str(&contents).map_err(|e| format!("Failed to parse sessions: {}", e))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path()?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize sessions: {}", e))?;
+
+        // written next to the real file and renamed, so a crash can't leave half a log
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, contents)
+            .map_err(|e| format!("Failed to write sessions: {}", e))?;
+        std::fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to write sessions: {}", e))
+    }
+
+    fn path() -> Result<PathBuf, String> {
+        Ok(crate::config::Config::config_dir()?.join(SESSIONS_FILE))
+    }
+
+    pub fn start(&mut self, game_id: &str, started_at: DateTime<Utc>) -> Session {
+        let session = Session {
+            id: Uuid::new_v4().to_string(),
+            game_id: game_id.to_string(),
+            started_at,
+            ended_at: None,
+            duration_secs: None,
+        };
+        self.sessions.push(session.clone());
+        session
+    }
+
+    pub fn end(&mut self, session_id: &str, ended_at: DateTime<Utc>) -> Option<Session> {
+        let session = self.sessions.iter_mut().find(|s| s.id == session_id)?;
+        session.ended_at = Some(ended_at);
+        session.duration_secs = Some((ended_at - session.started_at).num_seconds().max(0));
+        Some(session.clone())
+    }
+
+    /// The session of a game still marked as running, if any.
+    pub fn open_session(&self, game_id: &str) -> Option<&Session> {
+        self.sessions
+            .iter()
+            .rev()
+            .find(|s| s.game_id == game_id && s.ended_at.is_none())
+    }
+
+    /// Drops sessions that never got an end because Checkpoint wasn't running
+    /// when the game exited, their length is unknown.
+    pub fn discard_open_sessions(&mut self, except: &[String]) {
+        self.sessions
+            .retain(|s| s.ended_at.is_some() || except.contains(&s.id));
+    }
+
+    pub fn remove_game(&mut self, game_id: &str) {
+        self.sessions.retain(|s| s.game_id != game_id);
+    }
+
+    /// Sessions of a game, most recent first.
+    pub fn history(&self, game_id: &str) -> Vec<Session> {
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .iter()
+            .filter(|s| s.game_id == game_id)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.started_at));
+        sessions
+    }
+
+    pub fn summary(&self, game_id: &str, now: DateTime<Utc>) -> PlaytimeSummary {
+        let sessions: Vec<&Session> = self
+            .sessions
+            .iter()
+            .filter(|s| s.game_id == game_id)
+            .collect();
+
+        PlaytimeSummary {
+            game_id: game_id.to_string(),
+            // a running session counts up to now
+            total_secs: sessions
+                .iter()
+                .map(|s| {
+                    s.duration_secs
+                        .unwrap_or_else(|| (now - s.started_at).num_seconds().max(0))
+                })
+                .sum(),
+            session_count: sessions.len(),
+            last_played: sessions.iter().map(|s| s.ended_at.unwrap_or(now)).max(),
+        }
+    }
+
+    /// The session a snapshot taken at `at` belongs to: one running at that time
+    /// or one that ended shortly before.
+    pub fn session_at(&self, game_id: &str, at: DateTime<Utc>) -> Option<&Session> {
+        self.sessions
+            .iter()
+            .filter(|s| s.game_id == game_id && s.started_at <= at)
+            .filter(|s| {
+                s.ended_at
+                    .is_none_or(|ended_at| at <= ended_at + Duration::minutes(LINK_WINDOW_MINUTES))
+            })
+            .max_by_key(|s| s.started_at)
+    }
+}
+
+/// Id of the session a snapshot of the game taken now belongs to, from the log
+/// shared by the app, the process monitor and the save watcher.
+pub fn current_session(log: &Mutex<SessionLog>, game_id: &str) -> Option<String> {
+    let log = log.lock().ok()?;
+    log.session_at(game_id, Utc::now()).map(|s| s.id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        format!("2024-06-15T{}:00Z", time).parse().unwrap()
+    }
+
+    #[test]
+    fn test_summary_counts_finished_and_running_sessions() {
+        let mut log = SessionLog::default();
+        let first = log.start("game", at("10:00"));
+        log.end(&first.id, at("11:30"));
+        log.start("game", at("12:00"));
+        log.start("other", at("09:00"));
+
+        let summary = log.summary("game", at("12:15"));
+        assert_eq!(summary.total_secs, 90 * 60 + 15 * 60);
+        assert_eq!(summary.session_count, 2);
+        assert_eq!(summary.last_played, Some(at("12:15")));
+
+        assert_eq!(log.history("game")[0].started_at, at("12:00"));
+    }
+
+    #[test]
+    fn test_snapshots_link_to_running_or_just_ended_sessions() {
+        let mut log = SessionLog::default();
+        let session = log.start("game", at("10:00"));
+        log.end(&session.id, at("11:00"));
+
+        assert_eq!(
+            log.session_at("game", at("10:30")).map(|s| s.id.as_str()),
+            Some(session.id.as_str())
+        );
+        assert_eq!(
+            log.session_at("game", at("11:05")).map(|s| s.id.as_str()),
+            Some(session.id.as_str())
+        );
+        assert!(log.session_at("game", at("11:30")).is_none());
+        assert!(log.session_at("game", at("09:59")).is_none());
+        assert!(log.session_at("other", at("10:30")).is_none());
+    }
+}
@@ -1,11 +1,24 @@
-use crate::game::Game;
+use crate::config::Config;
+use crate::diff::{FileIndex, FileState, SaveStatus, SnapshotDiff};
+use crate::filter::{FileFilter, PreviewFile};
+use crate::game::{Game, SaveRoot, DEFAULT_SAVE_ROOT};
+use crate::operation::Operation;
+use crate::store::{ObjectStore, StoreSettings};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 use walkdir::WalkDir;
 
+const METADATA_FILE: &str = ".checkpoint-meta.json";
+const AUTO_BACKUP_NAME: &str = "Auto-backup before restore";
+/// Error of exporting an encrypted snapshot without asking for it decrypted.
+pub const ENCRYPTED_EXPORT: &str =
+    "This snapshot is encrypted, exporting it writes its files decrypted";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Snapshot {
     pub id: String,
@@ -14,13 +27,43 @@ pub struct Snapshot {
     pub name: String,
     pub path: String,
     pub size: u64,
+    // bytes the snapshot's files take up in the object store, less than `size`
+    // when they are compressed
+    #[serde(default)]
+    pub stored_size: u64,
     pub file_count: usize,
+    #[serde(default)]
+    pub kind: SnapshotKind,
+    // pinned snapshots are never pruned and need an explicit override to delete
+    #[serde(default)]
+    pub pinned: bool,
+    // the play session the snapshot was taken in or right after
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub encrypted: bool,
+    // encrypted and the passphrase wasn't entered yet, the name is empty until it is
+    #[serde(default)]
+    pub locked: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotKind {
+    #[default]
+    Manual,
+    // taken by the save folder watcher or the process monitor
+    Auto,
+    // taken by `restore_snapshot` right before it overwrites the save folder
+    AutoBackup,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateSnapshotRequest {
     pub game_id: String,
     pub name: Option<String>,
+    #[serde(default)]
+    pub kind: SnapshotKind,
 }
 
 #[derive(Debug, Serialize)]
@@ -28,453 +71,3030 @@ pub struct RestoreResult {
     pub success: bool,
     pub backed_up_current: bool,
     pub backup_snapshot_id: Option<String>,
+    pub files_restored: usize,
+    // true when the restore failed midway and the previous save folder was put back
+    pub rolled_back: bool,
     pub message: String,
+    // set when the restore was refused because the current saves aren't in any
+    // snapshot yet, lists what changed since the newest one
+    pub unsaved_changes: Option<SnapshotDiff>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RestoreOptions {
+    /// Restore even if the current saves hold progress no snapshot has.
+    /// They are still backed up first.
+    pub overwrite_unsaved: bool,
+    /// Extract the snapshot into this directory instead of the save folders,
+    /// which it can't be or be inside of. Each save root becomes a sub-directory
+    /// named after it.
+    pub target_dir: Option<String>,
+}
+
+impl RestoreResult {
+    fn failure(message: String) -> Self {
+        Self {
+            success: false,
+            backed_up_current: false,
+            backup_snapshot_id: None,
+            files_restored: 0,
+            rolled_back: false,
+            message,
+            unsaved_changes: None,
+        }
+    }
+}
+
+// `hash` is the SHA-256 of the file contents and doubles as its object id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub hash: String,
+    pub size: u64,
+    #[serde(default)]
+    pub modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    Valid,
+    Corrupted,
+    // the snapshot predates per-file manifests, so there is nothing to check against
+    Unverifiable,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerificationReport {
+    pub status: VerificationStatus,
+    pub checked_files: usize,
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+    pub corrupted: Vec<String>,
 }
 
+/// Snapshots the game's save folders. `session_id` is the play session the
+/// snapshot belongs to, if any.
 pub fn create_snapshot(
+    config: &Config,
     request: &CreateSnapshotRequest,
-    backup_location: &str,
+    session_id: Option<String>,
+    operation: &Operation,
 ) -> Result<Snapshot, String> {
-    let config = crate::config::Config::load()?;
     let game = config
         .games
         .iter()
         .find(|g| g.id == request.game_id)
-        .ok_or("Game not found")?;
-
-    let save_path = Path::new(&game.save_location);
-    if !save_path.exists() {
-        return Err(format!(
-            "Save location does not exist: {}",
-            game.save_location
-        ));
-    }
+        .ok_or("Game not found")?
+        .resolved_with(&config.path_variables())?;
+    let settings = config.store_settings(&game.id)?;
 
     let timestamp = Utc::now();
-    let snapshot_id = Uuid::new_v4().to_string();
-    let snapshot_name = request
-        .name
-        .clone()
-        .unwrap_or_else(|| timestamp.format("%Y-%m-%d_%H-%M-%S").to_string());
-
-    let snapshot_dir = Path::new(backup_location)
-        .join(&request.game_id)
-        .join(&snapshot_id);
+    let snapshot = NewSnapshot {
+        name: request
+            .name
+            .clone()
+            .unwrap_or_else(|| timestamp.format("%Y-%m-%d_%H-%M-%S").to_string()),
+        timestamp,
+        kind: request.kind,
+        session_id,
+    };
 
-    fs::create_dir_all(&snapshot_dir)
-        .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
+    store_snapshot(
+        &game,
+        snapshot,
+        &config.backup_location,
+        &settings,
+        operation,
+    )
+}
 
-    let mut total_size: u64 = 0;
-    let mut file_count: usize = 0;
+// what a snapshot about to be written is called, and when, why and in which
+// play session it is taken
+struct NewSnapshot {
+    name: String,
+    timestamp: DateTime<Utc>,
+    kind: SnapshotKind,
+    session_id: Option<String>,
+}
 
-    for entry in WalkDir::new(&save_path) {
-        let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
-        let path = entry.path();
+impl NewSnapshot {
+    fn auto_backup(session_id: Option<String>) -> Self {
+        let timestamp = Utc::now();
+        Self {
+            name: format!(
+                "{} {}",
+                AUTO_BACKUP_NAME,
+                timestamp.format("%Y-%m-%d %H:%M:%S")
+            ),
+            timestamp,
+            kind: SnapshotKind::AutoBackup,
+            session_id,
+        }
+    }
+}
 
-        if path.is_file() {
-            let relative_path = path
-                .strip_prefix(&save_path)
-                .map_err(|e| format!("Failed to calculate relative path: {}", e))?;
+// save roots that don't exist yet (e.g. a config folder the game hasn't created)
+// are skipped, but at least one of them has to be there
+fn store_snapshot(
+    game: &Game,
+    snapshot: NewSnapshot,
+    backup_location: &str,
+    settings: &StoreSettings,
+    operation: &Operation,
+) -> Result<Snapshot, String> {
+    let filter = FileFilter::for_game(game)?;
+    let save_roots = &game.save_roots;
+    let roots: Vec<&SaveRoot> = save_roots
+        .iter()
+        .filter(|root| Path::new(&root.path).exists())
+        .collect();
 
-            let dest_path = snapshot_dir.join(relative_path);
+    if roots.is_empty() {
+        let paths: Vec<&str> = save_roots.iter().map(|root| root.path.as_str()).collect();
+        return Err(format!(
+            "Save location does not exist: {}",
+            paths.join(", ")
+        ));
+    }
 
-            if let Some(parent) = dest_path.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Failed to create directory: {}", e))?;
-            }
+    let capture = collect_root_files(&roots, &filter)?;
 
-            fs::copy(path, &dest_path).map_err(|e| format!("Failed to copy file: {}", e))?;
+    write_snapshot(
+        game,
+        snapshot,
+        capture,
+        backup_location,
+        settings,
+        operation,
+    )
+}
 
-            let metadata =
-                fs::metadata(&dest_path).map_err(|e| format!("Failed to get metadata: {}", e))?;
-            total_size += metadata.len();
-            file_count += 1;
-        }
-    }
+// the files a snapshot is about to be written from, and the save roots they come from
+struct PendingCapture {
+    roots: Vec<String>,
+    files: Vec<PendingFile>,
+}
 
-    let metadata = SnapshotMetadata {
-        id: snapshot_id.clone(),
-        game_id: request.game_id.clone(),
-        timestamp,
-        name: snapshot_name.clone(),
-        size: total_size,
-        file_count,
-    };
+// a file about to be captured, `snapshot_path` is its path inside the snapshot
+struct PendingFile {
+    path: PathBuf,
+    snapshot_path: String,
+    metadata: fs::Metadata,
+}
 
-    let metadata_path = snapshot_dir.join(".checkpoint-meta.json");
-    let metadata_json = serde_json::to_string_pretty(&metadata)
-        .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
-    fs::write(&metadata_path, metadata_json)
-        .map_err(|e| format!("Failed to write metadata: {}", e))?;
+// each root becomes its own sub-tree of the snapshot, named after the root
+fn collect_root_files(roots: &[&SaveRoot], filter: &FileFilter) -> Result<PendingCapture, String> {
+    let mut pending = Vec::new();
 
-    Ok(Snapshot {
-        id: snapshot_id,
-        game_id: request.game_id.clone(),
-        timestamp,
-        name: snapshot_name,
-        path: snapshot_dir.to_string_lossy().to_string(),
-        size: total_size,
-        file_count,
-    })
-}
+    for root in roots {
+        let save_path = Path::new(&root.path);
 
-pub fn list_snapshots(game_id: &str, backup_location: &str) -> Result<Vec<Snapshot>, String> {
-    let game_dir = Path::new(backup_location).join(game_id);
+        for entry in WalkDir::new(save_path) {
+            let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+            let path = entry.path();
 
-    if !game_dir.exists() {
-        return Ok(Vec::new());
-    }
+            if path.is_file() {
+                let relative_path = path
+                    .strip_prefix(save_path)
+                    .map_err(|e| format!("Failed to calculate relative path: {}", e))?;
+                let relative_path = manifest_path(relative_path);
 
-    let mut snapshots = Vec::new();
+                if !filter.matches(&relative_path) {
+                    continue;
+                }
 
-    for entry in
-        fs::read_dir(&game_dir).map_err(|e| format!("Failed to read snapshots directory: {}", e))?
-    {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let path = entry.path();
+                let metadata =
+                    fs::metadata(path).map_err(|e| format!("Failed to get metadata: {}", e))?;
 
-        if path.is_dir() {
-            let metadata_path = path.join(".checkpoint-meta.json");
-            if metadata_path.exists() {
-                let contents = fs::read_to_string(&metadata_path)
-                    .map_err(|e| format!("Failed to read metadata: {}", e))?;
-                let metadata: SnapshotMetadata = serde_json::from_str(&contents)
-                    .map_err(|e| format!("Failed to parse metadata: {}", e))?;
-
-                snapshots.push(Snapshot {
-                    id: metadata.id,
-                    game_id: metadata.game_id,
-                    timestamp: metadata.timestamp,
-                    name: metadata.name,
-                    path: path.to_string_lossy().to_string(),
-                    size: metadata.size,
-                    file_count: metadata.file_count,
+                pending.push(PendingFile {
+                    path: path.to_path_buf(),
+                    snapshot_path: format!("{}/{}", root.name, relative_path),
+                    metadata,
                 });
             }
         }
     }
 
-    snapshots.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-
-    Ok(snapshots)
+    Ok(PendingCapture {
+        roots: roots.iter().map(|root| root.name.clone()).collect(),
+        files: pending,
+    })
 }
 
-pub fn restore_snapshot(
-    snapshot_id: &str,
+fn write_snapshot(
     game: &Game,
+    snapshot: NewSnapshot,
+    capture: PendingCapture,
     backup_location: &str,
-) -> Result<RestoreResult, String> {
-    if let Some(ref exe_name) = game.exe_name {
-        if crate::process::is_process_running(exe_name)? {
-            return Ok(RestoreResult {
-                success: false,
-                backed_up_current: false,
-                backup_snapshot_id: None,
-                message: format!(
-                    "Cannot restore: {} is currently running. Please close the game first.",
-                    exe_name
-                ),
-            });
-        }
-    }
+    settings: &StoreSettings,
+    operation: &Operation,
+) -> Result<Snapshot, String> {
+    let snapshot_id = Uuid::new_v4().to_string();
+    let snapshot_dir = Path::new(backup_location).join(&game.id).join(&snapshot_id);
 
-    let snapshot_path = Path::new(backup_location).join(&game.id).join(snapshot_id);
+    fs::create_dir_all(&snapshot_dir)
+        .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
 
-    if !snapshot_path.exists() {
-        return Err("Snapshot not found".to_string());
-    }
+    let store = ObjectStore::new(backup_location).with_settings(settings);
+    let files = match capture_files(capture.files, &store, operation) {
+        Ok(files) => files,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&snapshot_dir);
+            return Err(e);
+        }
+    };
 
-    if !verify_snapshot_internal(&snapshot_path)? {
-        return Ok(RestoreResult {
-            success: false,
-            backed_up_current: false,
-            backup_snapshot_id: None,
-            message: "Snapshot verification failed. Files may be corrupted.".to_string(),
-        });
+    let metadata = SnapshotMetadata {
+        id: snapshot_id.clone(),
+        game_id: game.id.clone(),
+        timestamp: snapshot.timestamp,
+        name: snapshot.name,
+        size: files.iter().map(|f| f.size).sum(),
+        stored_size: Some(stored_size(&files, &store)),
+        file_count: files.len(),
+        kind: snapshot.kind,
+        pinned: false,
+        session_id: snapshot.session_id,
+        roots: capture.roots,
+        files: Some(files),
+        sealed: store.encryption_key().map(SealedMetadata::new),
+        locked: false,
+    };
+
+    if let Err(e) = write_metadata(&snapshot_dir, &metadata) {
+        let _ = fs::remove_dir_all(&snapshot_dir);
+        return Err(e);
     }
 
-    let save_path = Path::new(&game.save_location);
-    let mut backed_up_current = false;
-    let mut backup_snapshot_id = None;
+    Ok(metadata.into_snapshot(&snapshot_dir))
+}
 
-    if save_path.exists() {
-        let current_backup_id = Uuid::new_v4().to_string();
-        let current_backup_path = Path::new(backup_location)
-            .join(&game.id)
-            .join(&current_backup_id);
+// objects whose size can't be read count with their original size
+fn stored_size(files: &[SnapshotFile], store: &ObjectStore) -> u64 {
+    files
+        .iter()
+        .map(|f| store.stored_size(&f.hash).unwrap_or(f.size))
+        .sum()
+}
 
-        fs::create_dir_all(&current_backup_path)
-            .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+fn capture_files(
+    pending: Vec<PendingFile>,
+    store: &ObjectStore,
+    operation: &Operation,
+) -> Result<Vec<SnapshotFile>, String> {
+    operation.begin(
+        "capturing",
+        pending.len(),
+        pending.iter().map(|file| file.metadata.len()).sum(),
+    );
+
+    let mut files = Vec::new();
+
+    for file in pending {
+        operation.check_cancelled()?;
+
+        let modified = file.metadata.modified().ok().map(DateTime::<Utc>::from);
+        let (hash, size) = store.put_file(&file.path)?;
+        operation.advance(&file.snapshot_path, size);
+
+        files.push(SnapshotFile {
+            path: file.snapshot_path,
+            hash,
+            size,
+            modified,
+        });
+    }
 
-        let mut total_size: u64 = 0;
-        let mut file_count: usize = 0;
+    Ok(files)
+}
 
-        for entry in WalkDir::new(&save_path) {
-            let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
-            let path = entry.path();
+// manifest paths always use '/' so snapshots stay portable between platforms
+pub(crate) fn manifest_path(relative_path: &Path) -> String {
+    relative_path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
 
-            if path.is_file() {
-                let relative_path = path
-                    .strip_prefix(&save_path)
-                    .map_err(|e| format!("Failed to calculate relative path: {}", e))?;
-                let dest_path = current_backup_path.join(relative_path);
+fn relative_from_manifest(path: &str) -> PathBuf {
+    path.split('/').filter(|part| !part.is_empty()).collect()
+}
 
-                if let Some(parent) = dest_path.parent() {
-                    fs::create_dir_all(parent)
-                        .map_err(|e| format!("Failed to create directory: {}", e))?;
-                }
+struct SourceFile {
+    // path inside the snapshot, prefixed with the save root name when the snapshot has roots
+    path: String,
+    // None for snapshots taken before save roots existed, they belong to the game's first root
+    root: Option<String>,
+    relative_path: PathBuf,
+    data: SourceData,
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+}
 
-                fs::copy(path, &dest_path).map_err(|e| format!("Failed to copy file: {}", e))?;
+enum SourceData {
+    // hash of the object in the store
+    Object(String),
+    // file inside the snapshot directory
+    Inline(PathBuf),
+}
 
-                let metadata = fs::metadata(&dest_path)
-                    .map_err(|e| format!("Failed to get metadata: {}", e))?;
-                total_size += metadata.len();
-                file_count += 1;
-            }
+impl SourceFile {
+    fn open(&self, store: &ObjectStore) -> Result<Box<dyn Read>, String> {
+        match &self.data {
+            SourceData::Object(hash) => store.open(hash),
+            SourceData::Inline(path) => fs::File::open(path)
+                .map(|file| Box::new(file) as Box<dyn Read>)
+                .map_err(|e| format!("Failed to open {}: {}", self.path, e)),
         }
+    }
 
-        let timestamp = Utc::now();
-        let metadata = SnapshotMetadata {
-            id: current_backup_id.clone(),
-            game_id: game.id.clone(),
-            timestamp,
-            name: format!(
-                "Auto-backup before restore {}",
-                timestamp.format("%Y-%m-%d %H:%M:%S")
-            ),
-            size: total_size,
-            file_count,
-        };
-
-        let metadata_path = current_backup_path.join(".checkpoint-meta.json");
-        let metadata_json = serde_json::to_string_pretty(&metadata)
-            .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
-        fs::write(&metadata_path, metadata_json)
-            .map_err(|e| format!("Failed to write metadata: {}", e))?;
-
-        backed_up_current = true;
-        backup_snapshot_id = Some(current_backup_id);
-
-        for entry in
-            fs::read_dir(&save_path).map_err(|e| format!("Failed to read save directory: {}", e))?
-        {
-            let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-            let path = entry.path();
-
-            if path.is_dir() {
-                fs::remove_dir_all(&path)
-                    .map_err(|e| format!("Failed to remove directory: {}", e))?;
-            } else {
-                fs::remove_file(&path).map_err(|e| format!("Failed to remove file: {}", e))?;
-            }
+    fn copy_to(&self, store: &ObjectStore, dest: &Path) -> Result<u64, String> {
+        match &self.data {
+            SourceData::Object(hash) => store.copy_to(hash, dest),
+            SourceData::Inline(path) => fs::copy(path, dest).map_err(|e| e.to_string()),
         }
-    } else {
-        fs::create_dir_all(&save_path)
-            .map_err(|e| format!("Failed to create save directory: {}", e))?;
+    }
+}
+
+/// Lists every file of a snapshot and where its data lives.
+/// Snapshots taken before the object store existed keep their files inline.
+fn snapshot_sources(
+    snapshot_dir: &Path,
+    metadata: &SnapshotMetadata,
+) -> Result<Vec<SourceFile>, String> {
+    if let Some(files) = &metadata.files {
+        return Ok(files
+            .iter()
+            .map(|f| {
+                let (root, relative) = if metadata.roots.is_empty() {
+                    (None, f.path.as_str())
+                } else {
+                    match f.path.split_once('/') {
+                        Some((root, relative)) => (Some(root.to_string()), relative),
+                        None => (None, f.path.as_str()),
+                    }
+                };
+
+                SourceFile {
+                    path: f.path.clone(),
+                    root,
+                    relative_path: relative_from_manifest(relative),
+                    data: SourceData::Object(f.hash.clone()),
+                    size: f.size,
+                    modified: f.modified,
+                }
+            })
+            .collect());
     }
 
-    let snapshot_data_path = &snapshot_path;
-    for entry in WalkDir::new(snapshot_data_path) {
+    let mut sources = Vec::new();
+
+    for entry in WalkDir::new(snapshot_dir) {
         let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
         let path = entry.path();
 
-        if path.file_name() == Some(std::ffi::OsStr::new(".checkpoint-meta.json")) {
+        if path.file_name() == Some(std::ffi::OsStr::new(METADATA_FILE)) {
             continue;
         }
 
         if path.is_file() {
             let relative_path = path
-                .strip_prefix(snapshot_data_path)
+                .strip_prefix(snapshot_dir)
                 .map_err(|e| format!("Failed to calculate relative path: {}", e))?;
-            let dest_path = save_path.join(relative_path);
-
-            if let Some(parent) = dest_path.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Failed to create directory: {}", e))?;
-            }
-
-            fs::copy(path, &dest_path).map_err(|e| format!("Failed to copy file: {}", e))?;
+            let metadata = entry
+                .metadata()
+                .map_err(|e| format!("Failed to get metadata: {}", e))?;
+            sources.push(SourceFile {
+                path: manifest_path(relative_path),
+                root: None,
+                relative_path: relative_path.to_path_buf(),
+                data: SourceData::Inline(path.to_path_buf()),
+                size: metadata.len(),
+                modified: metadata.modified().ok().map(DateTime::from),
+            });
         }
     }
 
-    Ok(RestoreResult {
-        success: true,
-        backed_up_current,
-        backup_snapshot_id,
-        message: "Restore completed successfully".to_string(),
-    })
+    Ok(sources)
 }
 
-pub fn delete_snapshot(
+/// Every file of a snapshot with its hash. Files of snapshots taken before save
+/// roots existed are keyed under the game's first root, like a new snapshot would.
+pub(crate) fn snapshot_index(
+    game: &Game,
     snapshot_id: &str,
-    game_id: &str,
     backup_location: &str,
-) -> Result<(), String> {
-    let snapshot_path = Path::new(backup_location).join(game_id).join(snapshot_id);
+) -> Result<FileIndex, String> {
+    let snapshot_path = Path::new(backup_location).join(&game.id).join(snapshot_id);
 
-    if !snapshot_path.exists() {
+    if !snapshot_path.join(METADATA_FILE).exists() {
         return Err("Snapshot not found".to_string());
     }
 
-    fs::remove_dir_all(&snapshot_path).map_err(|e| format!("Failed to delete snapshot: {}", e))?;
+    let metadata = read_metadata(&snapshot_path)?;
+    let legacy_root = legacy_root(game);
+    let key = |path: &str| index_key(&metadata, path, legacy_root);
+
+    if let Some(files) = &metadata.files {
+        return Ok(files
+            .iter()
+            .map(|f| {
+                (
+                    key(&f.path),
+                    FileState {
+                        hash: f.hash.clone(),
+                        size: f.size,
+                        modified: f.modified,
+                    },
+                )
+            })
+            .collect());
+    }
 
-    Ok(())
+    // inline snapshots don't record hashes, their files are hashed here
+    let store = ObjectStore::new(backup_location);
+    snapshot_sources(&snapshot_path, &metadata)?
+        .into_iter()
+        .map(|source| {
+            let (hash, size) = crate::store::hash_reader(&mut source.open(&store)?)?;
+            Ok((
+                key(&source.path),
+                FileState {
+                    hash,
+                    size,
+                    modified: None,
+                },
+            ))
+        })
+        .collect()
 }
 
-pub fn rename_snapshot(
+/// Lists the files of a snapshot as `<save root>/<path>`, the form `restore_files` takes.
+pub fn list_snapshot_files(
+    game: &Game,
     snapshot_id: &str,
-    game_id: &str,
-    new_name: &str,
     backup_location: &str,
-) -> Result<(), String> {
-    let snapshot_path = Path::new(backup_location).join(game_id).join(snapshot_id);
+) -> Result<Vec<PreviewFile>, String> {
+    Ok(snapshot_index(game, snapshot_id, backup_location)?
+        .into_iter()
+        .map(|(path, file)| PreviewFile {
+            path,
+            size: file.size,
+        })
+        .collect())
+}
 
-    let metadata_path = snapshot_path.join(".checkpoint-meta.json");
+// snapshots from before save roots existed hold the files of the game's first root
+fn legacy_root(game: &Game) -> &str {
+    game.save_roots
+        .first()
+        .map_or(DEFAULT_SAVE_ROOT, |root| root.name.as_str())
+}
 
-    if !metadata_path.exists() {
-        return Err("Snapshot metadata not found".to_string());
+fn index_key(metadata: &SnapshotMetadata, path: &str, legacy_root: &str) -> String {
+    if metadata.roots.is_empty() {
+        format!("{}/{}", legacy_root, path)
+    } else {
+        path.to_string()
     }
-
-    let contents = fs::read_to_string(&metadata_path)
-        .map_err(|e| format!("Failed to read metadata: {}", e))?;
-    let mut metadata: SnapshotMetadata =
-        serde_json::from_str(&contents).map_err(|e| format!("Failed to parse metadata: {}", e))?;
-
-    metadata.name = new_name.to_string();
-
-    let metadata_json = serde_json::to_string_pretty(&metadata)
-        .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
-    fs::write(&metadata_path, metadata_json)
-        .map_err(|e| format!("Failed to write metadata: {}", e))?;
-
-    Ok(())
 }
 
-pub fn verify_snapshot(
-    snapshot_id: &str,
+/// The objects a snapshot references, each once with its size on disk. None for
+/// snapshots that hold their files inline.
+pub(crate) fn snapshot_objects(
     game_id: &str,
+    snapshot_id: &str,
     backup_location: &str,
-) -> Result<bool, String> {
-    let snapshot_path = Path::new(backup_location).join(game_id).join(snapshot_id);
+) -> Result<Option<Vec<(String, u64)>>, String> {
+    let metadata = read_metadata(&Path::new(backup_location).join(game_id).join(snapshot_id))?;
+    let Some(files) = metadata.files else {
+        return Ok(None);
+    };
 
-    verify_snapshot_internal(&snapshot_path)
+    let store = ObjectStore::new(backup_location);
+    let mut seen = HashSet::new();
+    Ok(Some(
+        files
+            .into_iter()
+            .filter(|f| seen.insert(f.hash.clone()))
+            .map(|f| {
+                let size = store.stored_size(&f.hash).unwrap_or(f.size);
+                (f.hash, size)
+            })
+            .collect(),
+    ))
 }
 
-fn verify_snapshot_internal(snapshot_path: &Path) -> Result<bool, String> {
-    let metadata_path = snapshot_path.join(".checkpoint-meta.json");
+pub fn list_snapshots(game_id: &str, backup_location: &str) -> Result<Vec<Snapshot>, String> {
+    let game_dir = Path::new(backup_location).join(game_id);
 
-    if !metadata_path.exists() {
-        return Ok(false);
+    if !game_dir.exists() {
+        return Ok(Vec::new());
     }
 
-    let contents = fs::read_to_string(&metadata_path)
-        .map_err(|e| format!("Failed to read metadata: {}", e))?;
-    let metadata: SnapshotMetadata =
-        serde_json::from_str(&contents).map_err(|e| format!("Failed to parse metadata: {}", e))?;
-
-    let mut actual_file_count: usize = 0;
-    let mut actual_size: u64 = 0;
+    let mut snapshots = Vec::new();
 
-    for entry in WalkDir::new(snapshot_path) {
-        let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+    for entry in
+        fs::read_dir(&game_dir).map_err(|e| format!("Failed to read snapshots directory: {}", e))?
+    {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
         let path = entry.path();
 
-        if path.is_file() && path.file_name() != Some(std::ffi::OsStr::new(".checkpoint-meta.json"))
-        {
-            actual_file_count += 1;
-            let metadata =
-                fs::metadata(path).map_err(|e| format!("Failed to get metadata: {}", e))?;
-            actual_size += metadata.len();
+        if path.is_dir() && path.join(METADATA_FILE).exists() {
+            let metadata = read_metadata_or_sealed(&path)?;
+            snapshots.push(metadata.into_snapshot(&path));
         }
     }
 
-    let size_matches = actual_size == metadata.size;
-    let count_matches = actual_file_count == metadata.file_count;
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.timestamp));
 
-    Ok(size_matches && count_matches)
+    Ok(snapshots)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct SnapshotMetadata {
-    id: String,
-    game_id: String,
-    timestamp: DateTime<Utc>,
-    name: String,
-    size: u64,
-    file_count: usize,
+struct RestoreTarget<'a> {
+    save_path: PathBuf,
+    staging_dir: PathBuf,
+    sources: Vec<&'a SourceFile>,
 }
 
-pub fn import_snapshot(
-    game_id: &str,
-    name: &str,
-    file_data: &[u8],
+/// Restores a snapshot into the save folders of `game`, whose save locations
+/// have to be resolved already.
+pub fn restore_snapshot(
+    snapshot_id: &str,
+    game: &Game,
     backup_location: &str,
-) -> Result<Snapshot, String> {
-    use std::io::Cursor;
-    use zip::ZipArchive;
+    options: &RestoreOptions,
+    settings: &StoreSettings,
+    session_id: Option<String>,
+    operation: &Operation,
+) -> Result<RestoreResult, String> {
+    let snapshot_path = Path::new(backup_location).join(&game.id).join(snapshot_id);
 
-    let timestamp = Utc::now();
-    let snapshot_id = Uuid::new_v4().to_string();
+    // extracting somewhere else leaves the live saves alone, so the game may keep
+    // running and nothing needs backing up
+    if let Some(target_dir) = &options.target_dir {
+        return extract_snapshot(
+            game,
+            &snapshot_path,
+            Path::new(target_dir),
+            backup_location,
+            operation,
+        );
+    }
 
-    let snapshot_dir = Path::new(backup_location).join(game_id).join(&snapshot_id);
+    if let Some(refused) = check_restorable(game, &snapshot_path, backup_location)? {
+        return Ok(refused);
+    }
 
-    fs::create_dir_all(&snapshot_dir)
-        .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
+    if !options.overwrite_unsaved {
+        // when no snapshot can be read there is no telling, the backup taken
+        // before restoring keeps the current saves either way
+        if let SaveStatus::Unsaved(changes) = crate::diff::save_status(game, backup_location)? {
+            let mut result = RestoreResult::failure(
+                "Your current save has progress that isn't in any snapshot. Restore anyway to replace it, it will be backed up first.".to_string(),
+            );
+            result.unsaved_changes = Some(changes);
+            return Ok(result);
+        }
+    }
 
-    let cursor = Cursor::new(file_data);
-    let mut archive =
-        ZipArchive::new(cursor).map_err(|e| format!("Failed to read zip archive: {}", e))?;
+    let metadata = read_metadata(&snapshot_path)?;
+    let store = ObjectStore::new(backup_location);
+    let sources = snapshot_sources(&snapshot_path, &metadata)?;
 
-    let mut total_size: u64 = 0;
-    let mut file_count: usize = 0;
+    let mut targets = Vec::new();
+    let snapshot_roots: Vec<Option<&String>> = if metadata.roots.is_empty() {
+        vec![None]
+    } else {
+        metadata.roots.iter().map(Some).collect()
+    };
+
+    for snapshot_root in snapshot_roots {
+        let save_root = match snapshot_root {
+            Some(name) => game.save_root(name),
+            None => game.save_roots.first(),
+        };
 
-    for i in 0..archive.len() {
-        let mut file = archive
-            .by_index(i)
-            .map_err(|e| format!("Failed to access file in archive: {}", e))?;
+        let Some(save_root) = save_root else {
+            return Ok(RestoreResult::failure(format!(
+                "Cannot restore: this game no longer has the save location '{}'.",
+                snapshot_root.map_or("", |name| name.as_str())
+            )));
+        };
 
-        if file.is_file() {
-            let out_path = snapshot_dir.join(file.name());
+        // work on the real folder so a symlinked save location keeps its link
+        let save_path =
+            fs::canonicalize(&save_root.path).unwrap_or_else(|_| PathBuf::from(&save_root.path));
+
+        targets.push(RestoreTarget {
+            staging_dir: sibling_path(&save_path, "restore"),
+            save_path,
+            sources: sources
+                .iter()
+                .filter(|source| source.root.as_ref() == snapshot_root)
+                .collect(),
+        });
+    }
 
-            if let Some(parent) = out_path.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Failed to create directory: {}", e))?;
-            }
+    let filter = FileFilter::for_game(game)?;
+    let mut result = RestoreResult::failure(String::new());
 
-            let mut outfile =
-                fs::File::create(&out_path).map_err(|e| format!("Failed to create file: {}", e))?;
-            std::io::copy(&mut file, &mut outfile)
-                .map_err(|e| format!("Failed to extract file: {}", e))?;
+    if targets.iter().any(|target| target.save_path.exists()) {
+        let backup = store_snapshot(
+            game,
+            NewSnapshot::auto_backup(session_id),
+            backup_location,
+            settings,
+            operation,
+        )?;
 
-            let metadata =
-                fs::metadata(&out_path).map_err(|e| format!("Failed to get metadata: {}", e))?;
-            total_size += metadata.len();
-            file_count += 1;
-        }
+        result.backed_up_current = true;
+        result.backup_snapshot_id = Some(backup.id);
     }
 
-    let metadata = SnapshotMetadata {
-        id: snapshot_id.clone(),
-        game_id: game_id.to_string(),
-        timestamp,
-        name: name.to_string(),
-        size: total_size,
-        file_count,
+    // everything is copied next to the save folders first, the live folders are
+    // only touched by the renames in `swap_in`
+    let bytes_total = sources.iter().map(|source| source.size).sum();
+    operation.begin("restoring", sources.len(), bytes_total);
+
+    let mut files_restored = 0;
+    for target in &targets {
+        match stage_files(&target.sources, &target.staging_dir, &store, operation) {
+            Ok(count) => files_restored += count,
+            Err(e) => {
+                for target in &targets {
+                    let _ = fs::remove_dir_all(&target.staging_dir);
+                }
+                result.message = if operation.is_cancelled() {
+                    "Restore cancelled. Your save folder was not changed.".to_string()
+                } else {
+                    format!(
+                        "Restore failed while preparing files: {}. Your save folder was not changed.",
+                        e
+                    )
+                };
+                return Ok(result);
+            }
+        }
+    }
+
+    let mut previous_dirs = Vec::new();
+    for (index, target) in targets.iter().enumerate() {
+        match swap_in(&target.staging_dir, &target.save_path) {
+            Ok(previous_dir) => previous_dirs.push(previous_dir),
+            Err(failure) => {
+                for target in &targets[index..] {
+                    let _ = fs::remove_dir_all(&target.staging_dir);
+                }
+
+                // put back the save roots that were already swapped
+                let mut rolled_back = failure.rolled_back || index == 0;
+                let mut message = failure.message;
+                for (target, previous_dir) in targets[..index].iter().zip(previous_dirs).rev() {
+                    if let Err(e) = undo_swap(&target.save_path, previous_dir) {
+                        rolled_back = false;
+                        message = format!("{}. {}", message, e);
+                    }
+                }
+
+                result.rolled_back = rolled_back;
+                result.message = message;
+                return Ok(result);
+            }
+        }
+    }
+
+    result.success = true;
+    result.files_restored = files_restored;
+    result.message = "Restore completed successfully".to_string();
+
+    for (target, previous_dir) in targets.iter().zip(previous_dirs) {
+        let Some(previous_dir) = previous_dir else {
+            continue;
+        };
+
+        // files the snapshot filters never covered (logs, caches...) aren't part
+        // of the snapshot, so they are moved over instead of being wiped
+        match carry_over_unfiltered(&previous_dir, &target.save_path, &filter) {
+            Ok(()) => {
+                if let Err(e) = fs::remove_dir_all(&previous_dir) {
+                    eprintln!(
+                        "Failed to remove previous save folder {}: {}",
+                        previous_dir.display(),
+                        e
+                    );
+                }
+            }
+            Err(e) => {
+                result.message = format!(
+                    "Restore completed, but some files outside the snapshot filters could not be kept ({}). They are still in {}",
+                    e,
+                    previous_dir.display()
+                );
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+// the reasons not to touch the save folders at all: the game is running or the
+// snapshot is damaged
+fn check_restorable(
+    game: &Game,
+    snapshot_path: &Path,
+    backup_location: &str,
+) -> Result<Option<RestoreResult>, String> {
+    if let Some(ref exe_name) = game.exe_name {
+        if crate::process::is_process_running(exe_name)? {
+            return Ok(Some(RestoreResult::failure(format!(
+                "Cannot restore: {} is currently running. Please close the game first.",
+                exe_name
+            ))));
+        }
+    }
+
+    check_intact(snapshot_path, backup_location)
+}
+
+fn check_intact(
+    snapshot_path: &Path,
+    backup_location: &str,
+) -> Result<Option<RestoreResult>, String> {
+    if !snapshot_path.exists() {
+        return Err("Snapshot not found".to_string());
+    }
+
+    let verified = match verify_snapshot_internal(snapshot_path, backup_location)?.status {
+        VerificationStatus::Valid => true,
+        VerificationStatus::Corrupted => false,
+        VerificationStatus::Unverifiable => legacy_contents_match(snapshot_path)?,
+    };
+
+    if !verified {
+        return Ok(Some(RestoreResult::failure(
+            "Snapshot verification failed. Files may be corrupted.".to_string(),
+        )));
+    }
+
+    Ok(None)
+}
+
+fn extract_snapshot(
+    game: &Game,
+    snapshot_path: &Path,
+    target_dir: &Path,
+    backup_location: &str,
+    operation: &Operation,
+) -> Result<RestoreResult, String> {
+    if let Some(refused) = check_intact(snapshot_path, backup_location)? {
+        return Ok(refused);
+    }
+
+    let target_dir = fs::canonicalize(target_dir).unwrap_or_else(|_| target_dir.to_path_buf());
+
+    // writing into a save folder would change the live saves after all
+    for root in &game.save_roots {
+        let save_path = fs::canonicalize(&root.path).unwrap_or_else(|_| PathBuf::from(&root.path));
+        if target_dir.starts_with(&save_path) {
+            return Err(format!(
+                "Cannot extract into the save location '{}', restore the snapshot instead",
+                root.name
+            ));
+        }
+    }
+
+    if target_dir.exists() {
+        let is_empty = fs::read_dir(&target_dir)
+            .map_err(|e| format!("Failed to read {}: {}", target_dir.display(), e))?
+            .next()
+            .is_none();
+        if !is_empty {
+            return Err(format!(
+                "Target directory is not empty: {}",
+                target_dir.display()
+            ));
+        }
+    }
+
+    let metadata = read_metadata(snapshot_path)?;
+    let store = ObjectStore::new(backup_location);
+    let sources = snapshot_sources(snapshot_path, &metadata)?;
+    let legacy_root = legacy_root(game);
+
+    let bytes_total = sources.iter().map(|source| source.size).sum();
+    operation.begin("restoring", sources.len(), bytes_total);
+
+    // staged next to the target and renamed, so a failed extraction leaves nothing behind
+    let staging_dir = sibling_path(&target_dir, "extract");
+    let staged = stage_extraction(
+        &sources,
+        &metadata,
+        legacy_root,
+        &staging_dir,
+        &target_dir,
+        &store,
+        operation,
+    );
+
+    let mut result = RestoreResult::failure(String::new());
+
+    if let Err(e) = staged {
+        let _ = fs::remove_dir_all(&staging_dir);
+        result.message = if operation.is_cancelled() {
+            "Extraction cancelled.".to_string()
+        } else {
+            format!("Extraction failed: {}", e)
+        };
+        return Ok(result);
+    }
+
+    result.success = true;
+    result.files_restored = sources.len();
+    result.message = format!(
+        "Extracted {} file(s) to {}",
+        sources.len(),
+        target_dir.display()
+    );
+    Ok(result)
+}
+
+fn stage_extraction(
+    sources: &[SourceFile],
+    metadata: &SnapshotMetadata,
+    legacy_root: &str,
+    staging_dir: &Path,
+    target_dir: &Path,
+    store: &ObjectStore,
+    operation: &Operation,
+) -> Result<(), String> {
+    fs::create_dir_all(staging_dir)
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+
+    for source in sources {
+        operation.check_cancelled()?;
+
+        let key = index_key(metadata, &source.path, legacy_root);
+        let dest_path = staging_dir.join(relative_from_manifest(&key));
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+
+        let copied = source
+            .copy_to(store, &dest_path)
+            .map_err(|e| format!("Failed to copy {}: {}", key, e))?;
+        operation.advance(&key, copied);
+    }
+
+    // an empty target directory is replaced
+    if target_dir.exists() {
+        fs::remove_dir(target_dir)
+            .map_err(|e| format!("Failed to replace {}: {}", target_dir.display(), e))?;
+    }
+    fs::rename(staging_dir, target_dir)
+        .map_err(|e| format!("Failed to move files into {}: {}", target_dir.display(), e))
+}
+
+struct FileTarget<'a> {
+    source: &'a SourceFile,
+    // `<save root>/<path>`, as diffs and selections name the file
+    key: String,
+    target: PathBuf,
+}
+
+/// Restores only the files at `paths` from a snapshot, leaving the rest of the
+/// save folders as they are. A path names a file or a directory as
+/// `<save root>/<path>`, like diffs do. The files about to be overwritten are
+/// backed up first. The game's save locations have to be resolved already.
+pub fn restore_files(
+    snapshot_id: &str,
+    game: &Game,
+    paths: &[String],
+    backup_location: &str,
+    settings: &StoreSettings,
+    session_id: Option<String>,
+    operation: &Operation,
+) -> Result<RestoreResult, String> {
+    if paths.is_empty() {
+        return Err("No files selected to restore".to_string());
+    }
+    let snapshot_path = Path::new(backup_location).join(&game.id).join(snapshot_id);
+
+    if let Some(refused) = check_restorable(game, &snapshot_path, backup_location)? {
+        return Ok(refused);
+    }
+
+    let metadata = read_metadata(&snapshot_path)?;
+    let store = ObjectStore::new(backup_location);
+    let sources = snapshot_sources(&snapshot_path, &metadata)?;
+    let legacy_root = legacy_root(game);
+
+    let mut selected: Vec<FileTarget> = Vec::new();
+    for path in paths {
+        let path = path.trim_matches('/');
+        let dir_prefix = format!("{}/", path);
+        let mut matched = false;
+
+        for source in &sources {
+            let key = index_key(&metadata, &source.path, legacy_root);
+            if key != path && !key.starts_with(&dir_prefix) {
+                continue;
+            }
+            matched = true;
+
+            if selected.iter().any(|file| file.key == key) {
+                continue;
+            }
+
+            let root_name = source.root.as_deref().unwrap_or(legacy_root);
+            let Some(save_root) = game.save_root(root_name) else {
+                return Ok(RestoreResult::failure(format!(
+                    "Cannot restore: this game no longer has the save location '{}'.",
+                    root_name
+                )));
+            };
+            let save_path = fs::canonicalize(&save_root.path)
+                .unwrap_or_else(|_| PathBuf::from(&save_root.path));
+
+            selected.push(FileTarget {
+                source,
+                target: save_path.join(&source.relative_path),
+                key,
+            });
+        }
+
+        if !matched {
+            return Err(format!("'{}' is not in this snapshot", path));
+        }
+    }
+
+    let mut result = RestoreResult::failure(String::new());
+
+    let overwritten: Vec<PendingFile> = selected
+        .iter()
+        .filter_map(|file| {
+            let metadata = fs::metadata(&file.target).ok()?;
+            metadata.is_file().then(|| PendingFile {
+                path: file.target.clone(),
+                snapshot_path: file.key.clone(),
+                metadata,
+            })
+        })
+        .collect();
+
+    if !overwritten.is_empty() {
+        let mut roots: Vec<String> = Vec::new();
+        for file in &overwritten {
+            let root = file.snapshot_path.split('/').next().unwrap_or_default();
+            if !roots.iter().any(|r| r == root) {
+                roots.push(root.to_string());
+            }
+        }
+
+        let backup = write_snapshot(
+            game,
+            NewSnapshot::auto_backup(session_id),
+            PendingCapture {
+                roots,
+                files: overwritten,
+            },
+            backup_location,
+            settings,
+            operation,
+        )?;
+
+        result.backed_up_current = true;
+        result.backup_snapshot_id = Some(backup.id);
+    }
+
+    // every file is copied next to its target first, the live files are only
+    // touched by the renames after that
+    let bytes_total = selected.iter().map(|file| file.source.size).sum();
+    operation.begin("restoring", selected.len(), bytes_total);
+
+    let mut staged = Vec::new();
+    for file in &selected {
+        match stage_file(file, &store, operation) {
+            Ok(staging_path) => staged.push(staging_path),
+            Err(e) => {
+                for staging_path in &staged {
+                    let _ = fs::remove_file(staging_path);
+                }
+                result.message = if operation.is_cancelled() {
+                    "Restore cancelled. Your save folder was not changed.".to_string()
+                } else {
+                    format!(
+                        "Restore failed while preparing files: {}. Your save folder was not changed.",
+                        e
+                    )
+                };
+                return Ok(result);
+            }
+        }
+    }
+
+    // the replaced files are moved aside until every file is in place
+    let mut replaced: Vec<(&Path, Option<PathBuf>)> = Vec::new();
+    for (index, (file, staging_path)) in selected.iter().zip(&staged).enumerate() {
+        match replace_file(staging_path, &file.target) {
+            Ok(previous) => replaced.push((&file.target, previous)),
+            Err(e) => {
+                for staging_path in &staged[index..] {
+                    let _ = fs::remove_file(staging_path);
+                }
+
+                let mut rolled_back = true;
+                for (target, previous) in replaced.into_iter().rev() {
+                    let undone = match previous {
+                        Some(previous) => fs::rename(&previous, target),
+                        None => fs::remove_file(target),
+                    };
+                    rolled_back &= undone.is_ok();
+                }
+
+                result.rolled_back = rolled_back;
+                result.message = if rolled_back {
+                    format!(
+                        "Failed to restore {}: {}. The files already restored were put back.",
+                        file.key, e
+                    )
+                } else {
+                    format!(
+                        "Failed to restore {}: {}. Some files could not be put back, they are in the auto-backup.",
+                        file.key, e
+                    )
+                };
+                return Ok(result);
+            }
+        }
+    }
+
+    for (_, previous) in replaced {
+        if let Some(previous) = previous {
+            let _ = fs::remove_file(previous);
+        }
+    }
+
+    result.success = true;
+    result.files_restored = selected.len();
+    result.message = format!("Restored {} file(s)", selected.len());
+    Ok(result)
+}
+
+fn stage_file(
+    file: &FileTarget,
+    store: &ObjectStore,
+    operation: &Operation,
+) -> Result<PathBuf, String> {
+    operation.check_cancelled()?;
+
+    if let Some(parent) = file.target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let staging_path = sibling_path(&file.target, "restore");
+    let copied = file.source.copy_to(store, &staging_path).map_err(|e| {
+        let _ = fs::remove_file(&staging_path);
+        format!("Failed to copy {}: {}", file.key, e)
+    })?;
+    operation.advance(&file.key, copied);
+
+    Ok(staging_path)
+}
+
+// moves `staging_path` over `target`, returning where the replaced file was moved
+fn replace_file(staging_path: &Path, target: &Path) -> Result<Option<PathBuf>, String> {
+    let previous = if target.exists() {
+        let previous = sibling_path(target, "previous");
+        fs::rename(target, &previous).map_err(|e| e.to_string())?;
+        Some(previous)
+    } else {
+        None
+    };
+
+    if let Err(e) = fs::rename(staging_path, target) {
+        if let Some(previous) = &previous {
+            let _ = fs::rename(previous, target);
+        }
+        return Err(e.to_string());
+    }
+
+    Ok(previous)
+}
+
+fn carry_over_unfiltered(
+    previous_dir: &Path,
+    save_path: &Path,
+    filter: &FileFilter,
+) -> Result<(), String> {
+    if filter.is_empty() {
+        return Ok(());
+    }
+
+    for entry in WalkDir::new(previous_dir) {
+        let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+        let path = entry.path();
+
+        if !path.is_file() {
+            continue;
+        }
+
+        let relative_path = path
+            .strip_prefix(previous_dir)
+            .map_err(|e| format!("Failed to calculate relative path: {}", e))?;
+
+        if filter.matches(&manifest_path(relative_path)) {
+            continue;
+        }
+
+        let dest_path = save_path.join(relative_path);
+        if dest_path.exists() {
+            continue;
+        }
+
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+
+        fs::rename(path, &dest_path).map_err(|e| format!("Failed to move file: {}", e))?;
+    }
+
+    Ok(())
+}
+
+fn sibling_path(path: &Path, label: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let sibling = format!(".{}.checkpoint-{}-{}", name, label, Uuid::new_v4());
+
+    match path.parent() {
+        Some(parent) => parent.join(sibling),
+        None => PathBuf::from(sibling),
+    }
+}
+
+// the caller starts the "restoring" stage, restores over several save roots share it
+fn stage_files(
+    sources: &[&SourceFile],
+    staging_dir: &Path,
+    store: &ObjectStore,
+    operation: &Operation,
+) -> Result<usize, String> {
+    fs::create_dir_all(staging_dir)
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+
+    for file in sources {
+        operation.check_cancelled()?;
+
+        let dest_path = staging_dir.join(&file.relative_path);
+
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+
+        let copied = file
+            .copy_to(store, &dest_path)
+            .map_err(|e| format!("Failed to copy {}: {}", file.path, e))?;
+        operation.advance(&file.path, copied);
+    }
+
+    Ok(sources.len())
+}
+
+struct SwapFailure {
+    message: String,
+    rolled_back: bool,
+}
+
+// replaces `save_path` with `staging_dir`, putting the old folder back if anything fails.
+// On success the old folder is left next to it and returned, for the caller to clean up
+fn swap_in(staging_dir: &Path, save_path: &Path) -> Result<Option<PathBuf>, SwapFailure> {
+    let previous_dir = sibling_path(save_path, "previous");
+    let had_previous = save_path.exists();
+
+    if let Some(parent) = save_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| SwapFailure {
+            message: format!("Failed to create save directory: {}", e),
+            rolled_back: false,
+        })?;
+    }
+
+    if had_previous {
+        fs::rename(save_path, &previous_dir).map_err(|e| SwapFailure {
+            message: format!(
+                "Failed to move the current save folder aside: {}. Your save folder was not changed.",
+                e
+            ),
+            rolled_back: false,
+        })?;
+    }
+
+    if let Err(e) = fs::rename(staging_dir, save_path) {
+        if !had_previous {
+            return Err(SwapFailure {
+                message: format!("Failed to move restored files into place: {}", e),
+                rolled_back: false,
+            });
+        }
+
+        return Err(match fs::rename(&previous_dir, save_path) {
+            Ok(()) => SwapFailure {
+                message: format!(
+                    "Failed to move restored files into place: {}. Your previous save folder was put back.",
+                    e
+                ),
+                rolled_back: true,
+            },
+            Err(rollback_error) => SwapFailure {
+                message: format!(
+                    "Failed to move restored files into place: {}. Rolling back also failed ({}), your previous save folder is at {}",
+                    e,
+                    rollback_error,
+                    previous_dir.display()
+                ),
+                rolled_back: false,
+            },
+        });
+    }
+
+    Ok(had_previous.then_some(previous_dir))
+}
+
+// reverts a successful `swap_in` when a later save root fails
+fn undo_swap(save_path: &Path, previous_dir: Option<PathBuf>) -> Result<(), String> {
+    fs::remove_dir_all(save_path).map_err(|e| {
+        format!(
+            "Failed to remove restored files from {}: {}",
+            save_path.display(),
+            e
+        )
+    })?;
+
+    if let Some(previous_dir) = previous_dir {
+        fs::rename(&previous_dir, save_path).map_err(|e| {
+            format!(
+                "Failed to put back {} ({}), the previous save folder is at {}",
+                save_path.display(),
+                e,
+                previous_dir.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+pub fn delete_snapshot(
+    snapshot_id: &str,
+    game_id: &str,
+    backup_location: &str,
+    force: bool,
+) -> Result<(), String> {
+    let snapshot_path = Path::new(backup_location).join(game_id).join(snapshot_id);
+
+    if !snapshot_path.exists() {
+        return Err("Snapshot not found".to_string());
+    }
+
+    if !force
+        && snapshot_path.join(METADATA_FILE).exists()
+        && read_metadata_or_sealed(&snapshot_path)?.pinned
+    {
+        return Err("Snapshot is pinned. Unpin it before deleting it.".to_string());
+    }
+
+    delete_snapshots(&[snapshot_id.to_string()], game_id, backup_location)
+}
+
+/// Deletes several snapshots of a game, cleaning up the object store once at the end.
+pub fn delete_snapshots(
+    snapshot_ids: &[String],
+    game_id: &str,
+    backup_location: &str,
+) -> Result<(), String> {
+    let game_dir = Path::new(backup_location).join(game_id);
+
+    for snapshot_id in snapshot_ids {
+        let snapshot_path = game_dir.join(snapshot_id);
+        if snapshot_path.exists() {
+            fs::remove_dir_all(&snapshot_path)
+                .map_err(|e| format!("Failed to delete snapshot: {}", e))?;
+        }
+    }
+
+    if let Err(e) = collect_garbage(backup_location) {
+        eprintln!("Failed to clean up object store: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Deletes every snapshot of a game along with its cover image.
+pub fn delete_game_snapshots(game_id: &str, backup_location: &str) -> Result<(), String> {
+    let game_dir = Path::new(backup_location).join(game_id);
+    if game_dir.exists() {
+        fs::remove_dir_all(&game_dir).map_err(|e| format!("Failed to delete snapshots: {}", e))?;
+    }
+
+    if let Err(e) = collect_garbage(backup_location) {
+        eprintln!("Failed to clean up object store: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Removes stored objects that no snapshot of any game references anymore.
+pub fn collect_garbage(backup_location: &str) -> Result<usize, String> {
+    let backup_path = Path::new(backup_location);
+    let mut referenced = HashSet::new();
+
+    if backup_path.exists() {
+        for game_entry in WalkDir::new(backup_path).min_depth(1).max_depth(1) {
+            let game_entry = game_entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+            if !game_entry.file_type().is_dir() {
+                continue;
+            }
+
+            for snapshot_entry in WalkDir::new(game_entry.path()).min_depth(1).max_depth(1) {
+                let snapshot_entry =
+                    snapshot_entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+                let snapshot_dir = snapshot_entry.path();
+
+                if !snapshot_dir.join(METADATA_FILE).exists() {
+                    continue;
+                }
+
+                // a manifest we can't read might reference anything, so bail out
+                // instead of deleting objects that could still be in use
+                let metadata = read_metadata_or_sealed(snapshot_dir)?;
+                if let Some(files) = metadata.files {
+                    referenced.extend(files.into_iter().map(|f| f.hash));
+                }
+                if let Some(sealed) = metadata.sealed {
+                    referenced.extend(sealed.objects);
+                }
+            }
+        }
+    }
+
+    ObjectStore::new(backup_location).collect_garbage(&referenced)
+}
+
+pub fn rename_snapshot(
+    snapshot_id: &str,
+    game_id: &str,
+    new_name: &str,
+    backup_location: &str,
+) -> Result<(), String> {
+    let snapshot_path = Path::new(backup_location).join(game_id).join(snapshot_id);
+
+    if !snapshot_path.join(METADATA_FILE).exists() {
+        return Err("Snapshot metadata not found".to_string());
+    }
+
+    let mut metadata = read_metadata(&snapshot_path)?;
+    metadata.name = new_name.to_string();
+    write_metadata(&snapshot_path, &metadata)
+}
+
+pub fn set_snapshot_pinned(
+    snapshot_id: &str,
+    game_id: &str,
+    pinned: bool,
+    backup_location: &str,
+) -> Result<(), String> {
+    let snapshot_path = Path::new(backup_location).join(game_id).join(snapshot_id);
+
+    if !snapshot_path.join(METADATA_FILE).exists() {
+        return Err("Snapshot metadata not found".to_string());
+    }
+
+    // works on locked snapshots too, pinning doesn't touch their sealed part
+    let mut metadata = read_metadata_or_sealed(&snapshot_path)?;
+    metadata.pinned = pinned;
+    write_metadata(&snapshot_path, &metadata)
+}
+
+/// Deleting pinned snapshots in bulk (a whole game, a reset) needs an explicit
+/// override from the user, this is the check callers run before asking for it.
+pub fn ensure_nothing_pinned(game_id: Option<&str>, backup_location: &str) -> Result<(), String> {
+    let pinned = count_pinned(game_id, backup_location)?;
+
+    if pinned > 0 {
+        return Err(format!(
+            "{} pinned snapshot(s) would be deleted. Unpin them or confirm to delete anyway.",
+            pinned
+        ));
+    }
+
+    Ok(())
+}
+
+/// Counts the pinned snapshots of a game, or of every game when `game_id` is None.
+pub fn count_pinned(game_id: Option<&str>, backup_location: &str) -> Result<usize, String> {
+    let backup_path = Path::new(backup_location);
+
+    let game_ids: Vec<String> = match game_id {
+        Some(game_id) => vec![game_id.to_string()],
+        None if backup_path.exists() => fs::read_dir(backup_path)
+            .map_err(|e| format!("Failed to read backup directory: {}", e))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let mut pinned = 0;
+    for game_id in game_ids {
+        pinned += list_snapshots(&game_id, backup_location)?
+            .iter()
+            .filter(|s| s.pinned)
+            .count();
+    }
+
+    Ok(pinned)
+}
+
+pub fn verify_snapshot(
+    snapshot_id: &str,
+    game_id: &str,
+    backup_location: &str,
+) -> Result<VerificationReport, String> {
+    let snapshot_path = Path::new(backup_location).join(game_id).join(snapshot_id);
+
+    if !snapshot_path.exists() {
+        return Err("Snapshot not found".to_string());
+    }
+
+    verify_snapshot_internal(&snapshot_path, backup_location)
+}
+
+enum ObjectCheck {
+    Missing,
+    // hash and size of the contents read back
+    Read(String, u64),
+    // e.g. a compressed object that was truncated
+    Unreadable,
+}
+
+fn verify_snapshot_internal(
+    snapshot_path: &Path,
+    backup_location: &str,
+) -> Result<VerificationReport, String> {
+    let mut report = VerificationReport {
+        status: VerificationStatus::Unverifiable,
+        checked_files: 0,
+        missing: Vec::new(),
+        extra: Vec::new(),
+        corrupted: Vec::new(),
+    };
+
+    if !snapshot_path.join(METADATA_FILE).exists() {
+        return Ok(report);
+    }
+
+    let metadata = read_metadata(snapshot_path)?;
+    let Some(files) = &metadata.files else {
+        return Ok(report);
+    };
+
+    let store = ObjectStore::new(backup_location);
+    // several files can share an object, only hash each one once
+    let mut checked_objects: HashMap<&str, ObjectCheck> = HashMap::new();
+
+    for file in files {
+        let actual = checked_objects.entry(&file.hash).or_insert_with(|| {
+            if !store.contains(&file.hash) {
+                return ObjectCheck::Missing;
+            }
+            store
+                .open(&file.hash)
+                .and_then(|mut reader| crate::store::hash_reader(&mut reader))
+                .map_or(ObjectCheck::Unreadable, |(hash, size)| {
+                    ObjectCheck::Read(hash, size)
+                })
+        });
+
+        match actual {
+            ObjectCheck::Missing => report.missing.push(file.path.clone()),
+            ObjectCheck::Read(hash, size) if *hash == file.hash && *size == file.size => {}
+            _ => report.corrupted.push(file.path.clone()),
+        }
+        report.checked_files += 1;
+    }
+
+    // the snapshot directory itself should only ever hold the metadata file
+    for entry in WalkDir::new(snapshot_path).min_depth(1) {
+        let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+        let path = entry.path();
+
+        if path.is_file() && path.file_name() != Some(std::ffi::OsStr::new(METADATA_FILE)) {
+            let relative_path = path
+                .strip_prefix(snapshot_path)
+                .map_err(|e| format!("Failed to calculate relative path: {}", e))?;
+            report.extra.push(manifest_path(relative_path));
+        }
+    }
+
+    report.status =
+        if report.missing.is_empty() && report.corrupted.is_empty() && report.extra.is_empty() {
+            VerificationStatus::Valid
+        } else {
+            VerificationStatus::Corrupted
+        };
+
+    Ok(report)
+}
+
+// snapshots without a manifest can only be sanity-checked against their recorded totals
+fn legacy_contents_match(snapshot_path: &Path) -> Result<bool, String> {
+    if !snapshot_path.join(METADATA_FILE).exists() {
+        return Ok(false);
+    }
+
+    let metadata = read_metadata(snapshot_path)?;
+
+    let mut actual_file_count: usize = 0;
+    let mut actual_size: u64 = 0;
+
+    for entry in WalkDir::new(snapshot_path) {
+        let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+        let path = entry.path();
+
+        if path.is_file() && path.file_name() != Some(std::ffi::OsStr::new(METADATA_FILE)) {
+            actual_file_count += 1;
+            let metadata =
+                fs::metadata(path).map_err(|e| format!("Failed to get metadata: {}", e))?;
+            actual_size += metadata.len();
+        }
+    }
+
+    Ok(actual_size == metadata.size && actual_file_count == metadata.file_count)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotMetadata {
+    id: String,
+    game_id: String,
+    timestamp: DateTime<Utc>,
+    name: String,
+    size: u64,
+    // None for snapshots from before compression, their files are stored as is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stored_size: Option<u64>,
+    file_count: usize,
+    #[serde(default)]
+    kind: SnapshotKind,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+    // names of the save roots captured, file paths start with one of them.
+    // Empty for snapshots from before games had several save roots
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    roots: Vec<String>,
+    // None for snapshots that still hold their files inline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    files: Option<Vec<SnapshotFile>>,
+    // set for encrypted snapshots. On disk their name, session, roots and files
+    // are only in here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sealed: Option<SealedMetadata>,
+    // sealed and the key isn't unlocked, only the unencrypted fields are filled in
+    #[serde(skip)]
+    locked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedMetadata {
+    key_id: String,
+    // names of the objects the snapshot references, so the store can be cleaned up
+    // without the key. Keyed hashes, or plain hashes for objects encrypted before
+    // objects were named that way
+    objects: Vec<String>,
+    // `PrivateMetadata`, encrypted
+    data: String,
+}
+
+impl SealedMetadata {
+    fn new(key: &crate::crypto::EncryptionKey) -> Self {
+        Self {
+            key_id: key.id().to_string(),
+            objects: Vec::new(),
+            data: String::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PrivateMetadata {
+    name: String,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    roots: Vec<String>,
+    #[serde(default)]
+    files: Option<Vec<SnapshotFile>>,
+}
+
+impl SnapshotMetadata {
+    fn into_snapshot(self, snapshot_dir: &Path) -> Snapshot {
+        Snapshot {
+            id: self.id,
+            game_id: self.game_id,
+            timestamp: self.timestamp,
+            name: self.name,
+            path: snapshot_dir.to_string_lossy().to_string(),
+            size: self.size,
+            stored_size: self.stored_size.unwrap_or(self.size),
+            file_count: self.file_count,
+            kind: self.kind,
+            pinned: self.pinned,
+            session_id: self.session_id,
+            encrypted: self.sealed.is_some(),
+            locked: self.locked,
+        }
+    }
+}
+
+fn read_metadata(snapshot_dir: &Path) -> Result<SnapshotMetadata, String> {
+    let metadata = read_metadata_or_sealed(snapshot_dir)?;
+    if metadata.locked {
+        return Err(crate::crypto::LOCKED.to_string());
+    }
+    Ok(metadata)
+}
+
+// the metadata of encrypted snapshots stays sealed when their key isn't unlocked,
+// for what only needs the unencrypted fields
+fn read_metadata_or_sealed(snapshot_dir: &Path) -> Result<SnapshotMetadata, String> {
+    let contents = fs::read_to_string(snapshot_dir.join(METADATA_FILE))
+        .map_err(|e| format!("Failed to read metadata: {}", e))?;
+    let mut metadata: SnapshotMetadata =
+        serde_json::from_str(&contents).map_err(|e| format!("Failed to parse metadata: {}", e))?;
+
+    if let Some(sealed) = &metadata.sealed {
+        match crate::crypto::cached_key(&sealed.key_id) {
+            Some(key) => {
+                let private: PrivateMetadata =
+                    serde_json::from_slice(&crate::crypto::unseal(&key, &sealed.data)?)
+                        .map_err(|e| format!("Failed to parse metadata: {}", e))?;
+                metadata.name = private.name;
+                metadata.session_id = private.session_id;
+                metadata.roots = private.roots;
+                metadata.files = private.files;
+            }
+            None => metadata.locked = true,
+        }
+    }
+
+    // auto-backups taken before snapshots recorded their kind are only recognizable by name
+    if metadata.kind == SnapshotKind::Manual && metadata.name.starts_with(AUTO_BACKUP_NAME) {
+        metadata.kind = SnapshotKind::AutoBackup;
+    }
+
+    Ok(metadata)
+}
+
+fn write_metadata(snapshot_dir: &Path, metadata: &SnapshotMetadata) -> Result<(), String> {
+    let metadata_json = match &metadata.sealed {
+        // a locked snapshot's sealed part is written back as it was read
+        Some(sealed) if !metadata.locked => {
+            serde_json::to_string_pretty(&seal_metadata(metadata, sealed)?)
+        }
+        _ => serde_json::to_string_pretty(metadata),
+    }
+    .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+    fs::write(snapshot_dir.join(METADATA_FILE), metadata_json)
+        .map_err(|e| format!("Failed to write metadata: {}", e))
+}
+
+// moves the private fields into the sealed part, encrypted with the snapshot's key
+fn seal_metadata(
+    metadata: &SnapshotMetadata,
+    sealed: &SealedMetadata,
+) -> Result<SnapshotMetadata, String> {
+    let key = crate::crypto::cached_key(&sealed.key_id).ok_or(crate::crypto::LOCKED)?;
+    let private = PrivateMetadata {
+        name: metadata.name.clone(),
+        session_id: metadata.session_id.clone(),
+        roots: metadata.roots.clone(),
+        files: metadata.files.clone(),
+    };
+    let private_json =
+        serde_json::to_vec(&private).map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+
+    // names listed before are kept, they may be of objects stored the old way
+    let objects: std::collections::BTreeSet<String> = metadata
+        .files
+        .iter()
+        .flatten()
+        .map(|f| key.object_id(&f.hash))
+        .chain(sealed.objects.iter().cloned())
+        .collect();
+
+    Ok(SnapshotMetadata {
+        name: String::new(),
+        session_id: None,
+        roots: Vec::new(),
+        files: None,
+        sealed: Some(SealedMetadata {
+            key_id: sealed.key_id.clone(),
+            objects: objects.into_iter().collect(),
+            data: crate::crypto::seal(&key, &private_json)?,
+        }),
+        ..metadata.clone()
+    })
+}
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const ARCHIVE_COMPRESSION_LEVEL: i32 = 3;
+
+// an import may unpack to this many times the archive's size, but always to at
+// least MIN_IMPORT_LIMIT. Anything beyond is taken for a zip bomb
+const MAX_EXPANSION_RATIO: u64 = 1000;
+const MIN_IMPORT_LIMIT: u64 = 1024 * 1024 * 1024;
+const MAX_IMPORT_ENTRIES: usize = 100_000;
+const MAX_METADATA_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// The format the file name of an export asks for.
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if name.ends_with(".zip") {
+            Ok(Self::Zip)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Ok(Self::TarZst)
+        } else {
+            Err(format!(
+                "Can't export to {}, use a .zip or .tar.zst file",
+                path.display()
+            ))
+        }
+    }
+
+    // imports go by the contents, the file name may have been lost on the way
+    fn detect(data: &[u8]) -> Self {
+        if data.starts_with(&ZSTD_MAGIC) {
+            Self::TarZst
+        } else {
+            Self::Zip
+        }
+    }
+}
+
+// the part of an exported snapshot's metadata an import cares about. Archives
+// from older versions only carry the roots, or no metadata at all
+#[derive(Default, Deserialize)]
+struct ArchiveMetadata {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    timestamp: Option<DateTime<Utc>>,
+    #[serde(default)]
+    roots: Vec<String>,
+    #[serde(default)]
+    files: Option<Vec<SnapshotFile>>,
+}
+
+struct ArchiveEntry<'a> {
+    path: String,
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+    reader: &'a mut dyn Read,
+}
+
+// calls `visit` with every regular file of a zip or tar.zst archive, in order
+fn read_archive(
+    data: &[u8],
+    visit: &mut dyn FnMut(ArchiveEntry) -> Result<(), String>,
+) -> Result<(), String> {
+    match ArchiveFormat::detect(data) {
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))
+                .map_err(|e| format!("Failed to read zip archive: {}", e))?;
+
+            for i in 0..archive.len() {
+                let mut file = archive
+                    .by_index(i)
+                    .map_err(|e| format!("Failed to access file in archive: {}", e))?;
+                if file.is_dir() {
+                    continue;
+                }
+                if file
+                    .unix_mode()
+                    .is_some_and(|mode| mode & 0o170000 == 0o120000)
+                {
+                    return Err(format!(
+                        "Archive entry {} is a symbolic link, which imports don't accept",
+                        file.name()
+                    ));
+                }
+
+                visit(ArchiveEntry {
+                    path: archive_entry_path(file.name())?,
+                    size: file.size(),
+                    modified: zip_modified_time(file.last_modified()),
+                    reader: &mut file,
+                })?;
+            }
+        }
+        ArchiveFormat::TarZst => {
+            let tar_error = |e: std::io::Error| format!("Failed to read tar.zst archive: {}", e);
+            let decoder = zstd::stream::read::Decoder::new(data).map_err(tar_error)?;
+            let mut archive = tar::Archive::new(decoder);
+
+            for entry in archive.entries().map_err(tar_error)? {
+                let mut entry = entry.map_err(tar_error)?;
+                let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+                match entry.header().entry_type() {
+                    kind if kind.is_file() => {}
+                    tar::EntryType::Directory | tar::EntryType::XGlobalHeader => continue,
+                    kind if kind.is_symlink() || kind.is_hard_link() => {
+                        return Err(format!(
+                            "Archive entry {} is a link, which imports don't accept",
+                            name
+                        ))
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Archive entry {} isn't a regular file, which imports don't accept",
+                            name
+                        ))
+                    }
+                }
+
+                let path = archive_entry_path(&name)?;
+                let modified = entry
+                    .header()
+                    .mtime()
+                    .ok()
+                    .and_then(|secs| DateTime::from_timestamp(secs as i64, 0));
+                visit(ArchiveEntry {
+                    path,
+                    size: entry.size(),
+                    modified,
+                    reader: &mut entry,
+                })?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// entry names are untrusted, restores turn them into paths inside the save
+// folders. Returns the name as a manifest path
+fn archive_entry_path(name: &str) -> Result<String, String> {
+    // some Windows tools write '\' separators
+    let normalized = name.replace('\\', "/");
+    let bytes = normalized.as_bytes();
+    let has_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    if normalized.starts_with('/') || has_drive {
+        return Err(format!("Archive entry {} has an absolute path", name));
+    }
+
+    let mut parts = Vec::new();
+    for part in normalized.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                return Err(format!(
+                    "Archive entry {} points outside the snapshot",
+                    name
+                ))
+            }
+            part => parts.push(part),
+        }
+    }
+
+    if parts.is_empty() {
+        return Err(format!("Archive entry {} has no file name", name));
+    }
+    Ok(parts.join("/"))
+}
+
+/// Imports a .zip or .tar.zst archive as a new snapshot. Archives exported by
+/// Checkpoint keep their original name, timestamp and save roots, and their files
+/// are checked against the manifest they carry. `name` overrides the archive's name.
+pub fn import_snapshot(
+    game_id: &str,
+    name: Option<&str>,
+    file_data: &[u8],
+    backup_location: &str,
+    settings: &StoreSettings,
+    operation: &Operation,
+) -> Result<Snapshot, String> {
+    let limits = ImportLimits {
+        max_entries: MAX_IMPORT_ENTRIES,
+        max_bytes: (file_data.len() as u64)
+            .saturating_mul(MAX_EXPANSION_RATIO)
+            .max(MIN_IMPORT_LIMIT),
     };
+    import_archive(
+        game_id,
+        name,
+        file_data,
+        backup_location,
+        settings,
+        &limits,
+        operation,
+    )
+}
+
+struct ImportLimits {
+    max_entries: usize,
+    // unpacked size of all files together
+    max_bytes: u64,
+}
+
+impl ImportLimits {
+    fn too_large(&self) -> String {
+        format!(
+            "The archive unpacks to more than {} bytes, it may be a zip bomb",
+            self.max_bytes
+        )
+    }
+}
+
+fn import_archive(
+    game_id: &str,
+    name: Option<&str>,
+    file_data: &[u8],
+    backup_location: &str,
+    settings: &StoreSettings,
+    limits: &ImportLimits,
+    operation: &Operation,
+) -> Result<Snapshot, String> {
+    let snapshot_id = Uuid::new_v4().to_string();
+
+    // a first pass reads the metadata, checks the entries and sizes the progress.
+    // Without metadata every file goes to the game's first save root
+    let mut archive_metadata = None;
+    let mut paths = HashSet::new();
+    let mut bytes_total: u64 = 0;
+    read_archive(file_data, &mut |entry| {
+        if !paths.insert(entry.path.clone()) {
+            return Err(format!(
+                "Archive entry {} appears more than once",
+                entry.path
+            ));
+        }
+        if paths.len() > limits.max_entries {
+            return Err(format!(
+                "The archive has more than {} files, it may be a zip bomb",
+                limits.max_entries
+            ));
+        }
+
+        if entry.path == METADATA_FILE {
+            archive_metadata = Some(
+                serde_json::from_reader::<_, ArchiveMetadata>(entry.reader.take(MAX_METADATA_SIZE))
+                    .map_err(|e| format!("Failed to parse snapshot metadata in archive: {}", e))?,
+            );
+        } else {
+            bytes_total = bytes_total.saturating_add(entry.size);
+            if bytes_total > limits.max_bytes {
+                return Err(limits.too_large());
+            }
+        }
+        Ok(())
+    })?;
+    let files_total = paths.len() - usize::from(archive_metadata.is_some());
+    let archive_metadata = archive_metadata.unwrap_or_default();
+    operation.begin("importing", files_total, bytes_total);
+
+    let store = ObjectStore::new(backup_location).with_settings(settings);
+    let mut files = Vec::new();
+    let mut unpacked = 0;
+
+    read_archive(file_data, &mut |entry| {
+        if entry.path == METADATA_FILE {
+            return Ok(());
+        }
+        operation.check_cancelled()?;
+
+        // sizes in zip headers can lie, what is actually unpacked is counted too
+        let remaining = limits.max_bytes - unpacked;
+        let (hash, size) = store.put_reader(&mut entry.reader.take(remaining + 1))?;
+        if size > remaining {
+            return Err(limits.too_large());
+        }
+        unpacked += size;
+        operation.advance(&entry.path, size);
+        files.push(SnapshotFile {
+            path: entry.path,
+            hash,
+            size,
+            modified: entry.modified,
+        });
+        Ok(())
+    })?;
+
+    if let Some(manifest) = &archive_metadata.files {
+        check_manifest(manifest, &mut files)?;
+    }
 
-    let metadata_path = snapshot_dir.join(".checkpoint-meta.json");
-    let metadata_json = serde_json::to_string_pretty(&metadata)
-        .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
-    fs::write(&metadata_path, metadata_json)
-        .map_err(|e| format!("Failed to write metadata: {}", e))?;
+    let snapshot_dir = Path::new(backup_location).join(game_id).join(&snapshot_id);
+
+    fs::create_dir_all(&snapshot_dir)
+        .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
 
-    Ok(Snapshot {
+    let metadata = SnapshotMetadata {
         id: snapshot_id,
         game_id: game_id.to_string(),
-        timestamp,
-        name: name.to_string(),
-        path: snapshot_dir.to_string_lossy().to_string(),
-        size: total_size,
-        file_count,
+        timestamp: archive_metadata.timestamp.unwrap_or_else(Utc::now),
+        name: name
+            .map(str::to_string)
+            .or(archive_metadata.name)
+            .unwrap_or_else(|| "Imported snapshot".to_string()),
+        size: files.iter().map(|f| f.size).sum(),
+        stored_size: Some(stored_size(&files, &store)),
+        file_count: files.len(),
+        kind: SnapshotKind::Manual,
+        pinned: false,
+        session_id: None,
+        roots: archive_metadata.roots,
+        files: Some(files),
+        sealed: store.encryption_key().map(SealedMetadata::new),
+        locked: false,
+    };
+
+    if let Err(e) = write_metadata(&snapshot_dir, &metadata) {
+        let _ = fs::remove_dir_all(&snapshot_dir);
+        return Err(e);
+    }
+
+    Ok(metadata.into_snapshot(&snapshot_dir))
+}
+
+// an archive has to hold exactly the files its manifest lists. They get the
+// manifest's modification times, archives store them less precisely
+fn check_manifest(manifest: &[SnapshotFile], files: &mut [SnapshotFile]) -> Result<(), String> {
+    let expected: HashMap<&str, &SnapshotFile> =
+        manifest.iter().map(|f| (f.path.as_str(), f)).collect();
+
+    for file in files.iter_mut() {
+        match expected.get(file.path.as_str()) {
+            Some(listed) if listed.hash == file.hash && listed.size == file.size => {
+                file.modified = listed.modified;
+            }
+            Some(_) => {
+                return Err(format!(
+                    "{} in the archive doesn't match its manifest",
+                    file.path
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "{} in the archive isn't in its manifest",
+                    file.path
+                ))
+            }
+        }
+    }
+
+    let present: HashSet<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    let missing = manifest
+        .iter()
+        .filter(|f| !present.contains(f.path.as_str()))
+        .count();
+    if missing > 0 {
+        return Err(format!(
+            "The archive is missing {} file(s) listed in its manifest",
+            missing
+        ));
+    }
+
+    Ok(())
+}
+
+fn zip_modified_time(time: zip::DateTime) -> Option<DateTime<Utc>> {
+    chrono::NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)?
+        .and_hms_opt(
+            time.hour() as u32,
+            time.minute() as u32,
+            time.second() as u32,
+        )
+        .map(|naive| naive.and_utc())
+}
+
+// zip times can't go before 1980, those files are written without one
+fn zip_time(time: DateTime<Utc>) -> Option<zip::DateTime> {
+    use chrono::{Datelike, Timelike};
+
+    zip::DateTime::from_date_and_time(
+        u16::try_from(time.year()).ok()?,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
+
+// archives aren't encrypted, so encrypted snapshots are only exported when the
+// caller explicitly asks for them decrypted
+fn export_sources(
+    snapshot_id: &str,
+    game_id: &str,
+    backup_location: &str,
+    decrypt: bool,
+) -> Result<(SnapshotMetadata, Vec<SourceFile>), String> {
+    let snapshot_path = Path::new(backup_location).join(game_id).join(snapshot_id);
+
+    if !snapshot_path.join(METADATA_FILE).exists() {
+        return Err("Snapshot not found".to_string());
+    }
+
+    let metadata = read_metadata_or_sealed(&snapshot_path)?;
+    if metadata.sealed.is_some() && !decrypt {
+        return Err(ENCRYPTED_EXPORT.to_string());
+    }
+    if metadata.locked {
+        return Err(crate::crypto::LOCKED.to_string());
+    }
+    let sources = snapshot_sources(&snapshot_path, &metadata)?;
+    Ok((metadata, sources))
+}
+
+/// Writes a snapshot to a .zip or .tar.zst archive at `path`, as its extension
+/// says. The archive carries the snapshot's metadata, so importing it keeps the
+/// original name, timestamp and manifest. Archives aren't encrypted: encrypted
+/// snapshots are refused unless `decrypt` is set, and written decrypted when it is.
+pub fn export_snapshot(
+    snapshot_id: &str,
+    game_id: &str,
+    path: &Path,
+    backup_location: &str,
+    decrypt: bool,
+    operation: &Operation,
+) -> Result<(), String> {
+    let format = ArchiveFormat::from_path(path)?;
+    let (metadata, sources) = export_sources(snapshot_id, game_id, backup_location, decrypt)?;
+    let store = ObjectStore::new(backup_location);
+
+    operation.begin(
+        "exporting",
+        sources.len(),
+        sources.iter().map(|s| s.size).sum(),
+    );
+
+    // written next to the destination first, a failed export doesn't leave a
+    // truncated archive behind
+    let staging_path = sibling_path(path, "export");
+    let write_error = |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+    let result = fs::File::create(&staging_path)
+        .map_err(write_error)
+        .and_then(|file| match format {
+            ArchiveFormat::Zip => write_zip(file, &metadata, &sources, &store, operation),
+            ArchiveFormat::TarZst => write_tar_zst(file, &metadata, &sources, &store, operation),
+        })
+        .and_then(|file| file.sync_all().map_err(write_error))
+        .and_then(|()| fs::rename(&staging_path, path).map_err(write_error));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&staging_path);
+    }
+    result
+}
+
+/// Packs the files of a snapshot into an in-memory zip, e.g. for cloud uploads.
+/// Like `export_snapshot`, encrypted snapshots need `decrypt`.
+pub fn export_snapshot_zip(
+    snapshot_id: &str,
+    game_id: &str,
+    backup_location: &str,
+    decrypt: bool,
+) -> Result<Vec<u8>, String> {
+    let (metadata, sources) = export_sources(snapshot_id, game_id, backup_location, decrypt)?;
+    let store = ObjectStore::new(backup_location);
+
+    let cursor = write_zip(
+        std::io::Cursor::new(Vec::new()),
+        &metadata,
+        &sources,
+        &store,
+        &Operation::untracked(),
+    )?;
+    Ok(cursor.into_inner())
+}
+
+// written unsealed, the archive itself isn't encrypted
+fn archive_metadata_json(metadata: &SnapshotMetadata) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(&SnapshotMetadata {
+        sealed: None,
+        ..metadata.clone()
     })
+    .map_err(|e| format!("Failed to serialize metadata: {}", e))
+}
+
+fn write_zip<W: std::io::Write + std::io::Seek>(
+    writer: W,
+    metadata: &SnapshotMetadata,
+    sources: &[SourceFile],
+    store: &ObjectStore,
+    operation: &Operation,
+) -> Result<W, String> {
+    use std::io::Write;
+    use zip::write::FileOptions;
+
+    let mut zip = zip::ZipWriter::new(writer);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let zip_error = |e: zip::result::ZipError| format!("Failed to write zip entry: {}", e);
+
+    zip.start_file(METADATA_FILE, options).map_err(zip_error)?;
+    zip.write_all(&archive_metadata_json(metadata)?)
+        .map_err(|e| format!("Failed to write zip entry: {}", e))?;
+
+    for source in sources {
+        operation.check_cancelled()?;
+
+        let mut file_options = options.large_file(source.size > u32::MAX as u64);
+        if let Some(time) = source.modified.and_then(zip_time) {
+            file_options = file_options.last_modified_time(time);
+        }
+
+        zip.start_file(source.path.as_str(), file_options)
+            .map_err(zip_error)?;
+        let written = std::io::copy(&mut source.open(store)?, &mut zip)
+            .map_err(|e| format!("Failed to write {}: {}", source.path, e))?;
+        operation.advance(&source.path, written);
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish zip archive: {}", e))
+}
+
+fn write_tar_zst<W: std::io::Write>(
+    writer: W,
+    metadata: &SnapshotMetadata,
+    sources: &[SourceFile],
+    store: &ObjectStore,
+    operation: &Operation,
+) -> Result<W, String> {
+    let tar_error = |e: std::io::Error| format!("Failed to write tar.zst archive: {}", e);
+    let encoder =
+        zstd::stream::write::Encoder::new(writer, ARCHIVE_COMPRESSION_LEVEL).map_err(tar_error)?;
+    let mut tar = tar::Builder::new(encoder);
+
+    let metadata_json = archive_metadata_json(metadata)?;
+    tar.append_data(
+        &mut tar_header(metadata_json.len() as u64, Some(metadata.timestamp)),
+        METADATA_FILE,
+        metadata_json.as_slice(),
+    )
+    .map_err(tar_error)?;
+
+    for source in sources {
+        operation.check_cancelled()?;
+
+        tar.append_data(
+            &mut tar_header(source.size, source.modified),
+            &source.path,
+            source.open(store)?,
+        )
+        .map_err(|e| format!("Failed to write {}: {}", source.path, e))?;
+        operation.advance(&source.path, source.size);
+    }
+
+    tar.into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(tar_error)
+}
+
+fn tar_header(size: u64, modified: Option<DateTime<Utc>>) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(modified.map_or(0, |time| time.timestamp().max(0) as u64));
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("checkpoint-{}-{}", label, Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // a game whose only save root is `save_dir`
+    fn test_game(save_dir: &Path) -> Game {
+        let mut game = Game::new(
+            "Game".to_string(),
+            save_dir.to_string_lossy().to_string(),
+            None,
+            None,
+        );
+        game.id = "game".to_string();
+        game
+    }
+
+    fn store_test_snapshot(game: &Game, backup_location: &str) -> Snapshot {
+        store_snapshot(
+            game,
+            NewSnapshot {
+                name: "test".to_string(),
+                timestamp: Utc::now(),
+                kind: SnapshotKind::Manual,
+                session_id: None,
+            },
+            backup_location,
+            &StoreSettings::default(),
+            &Operation::untracked(),
+        )
+        .unwrap()
+    }
+
+    fn snapshot_of(files: &[(&str, &[u8])]) -> (PathBuf, String, Snapshot) {
+        let save_dir = temp_dir("save");
+        for (path, contents) in files {
+            let path = save_dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        let backup_location = temp_dir("backup").to_string_lossy().to_string();
+        let game = test_game(&save_dir);
+
+        let snapshot = store_test_snapshot(&game, &backup_location);
+        fs::remove_dir_all(&save_dir).unwrap();
+
+        (PathBuf::from(&backup_location), backup_location, snapshot)
+    }
+
+    #[test]
+    fn test_verify_reports_corrupted_and_missing_files() {
+        let (backup_dir, backup_location, snapshot) = snapshot_of(&[
+            ("slot1.sav", b"first slot"),
+            ("slots/slot2.sav", b"second slot"),
+        ]);
+
+        let report = verify_snapshot(&snapshot.id, "game", &backup_location).unwrap();
+        assert_eq!(report.status, VerificationStatus::Valid);
+        assert_eq!(report.checked_files, 2);
+
+        let metadata = read_metadata(Path::new(&snapshot.path)).unwrap();
+        let files = metadata.files.unwrap();
+        let store = ObjectStore::new(&backup_location);
+        let slot1 = files.iter().find(|f| f.path == "saves/slot1.sav").unwrap();
+        let slot2 = files
+            .iter()
+            .find(|f| f.path == "saves/slots/slot2.sav")
+            .unwrap();
+
+        // same size, different bytes
+        fs::write(store.object_path(&slot1.hash), b"FIRST SLOT").unwrap();
+        fs::remove_file(store.object_path(&slot2.hash)).unwrap();
+        fs::write(Path::new(&snapshot.path).join("stray.txt"), b"?").unwrap();
+
+        let report = verify_snapshot(&snapshot.id, "game", &backup_location).unwrap();
+        assert_eq!(report.status, VerificationStatus::Corrupted);
+        assert_eq!(report.corrupted, vec!["saves/slot1.sav"]);
+        assert_eq!(report.missing, vec!["saves/slots/slot2.sav"]);
+        assert_eq!(report.extra, vec!["stray.txt"]);
+
+        fs::remove_dir_all(&backup_dir).unwrap();
+    }
+
+    #[test]
+    fn test_snapshots_without_manifest_are_unverifiable() {
+        let backup_dir = temp_dir("backup");
+        let backup_location = backup_dir.to_string_lossy().to_string();
+        let snapshot_dir = backup_dir.join("game").join("legacy");
+        fs::create_dir_all(&snapshot_dir).unwrap();
+        fs::write(snapshot_dir.join("save.dat"), b"inline").unwrap();
+        fs::write(
+            snapshot_dir.join(METADATA_FILE),
+            r#"{"id":"legacy","game_id":"game","timestamp":"2024-01-01T00:00:00Z","name":"old","size":6,"file_count":1}"#,
+        )
+        .unwrap();
+
+        let report = verify_snapshot("legacy", "game", &backup_location).unwrap();
+        assert_eq!(report.status, VerificationStatus::Unverifiable);
+        assert!(legacy_contents_match(&snapshot_dir).unwrap());
+
+        fs::remove_dir_all(&backup_dir).unwrap();
+    }
+
+    fn overwrite_unsaved() -> RestoreOptions {
+        RestoreOptions {
+            overwrite_unsaved: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_restore_swaps_in_snapshot_and_leaves_no_temp_folders() {
+        let root = temp_dir("restore");
+        let save_dir = root.join("Saves");
+        fs::create_dir_all(save_dir.join("slots")).unwrap();
+        fs::write(save_dir.join("slots/slot1.sav"), b"checkpoint").unwrap();
+
+        let backup_location = root.join("backup").to_string_lossy().to_string();
+        let game = test_game(&save_dir);
+
+        let snapshot = store_test_snapshot(&game, &backup_location);
+
+        fs::write(save_dir.join("slots/slot1.sav"), b"later progress").unwrap();
+        fs::write(save_dir.join("new.sav"), b"new file").unwrap();
+
+        let result = restore_snapshot(
+            &snapshot.id,
+            &game,
+            &backup_location,
+            &RestoreOptions::default(),
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .unwrap();
+        assert!(!result.success);
+        let changes = result.unsaved_changes.unwrap();
+        assert_eq!((changes.added, changes.modified), (1, 1));
+        assert_eq!(
+            fs::read(save_dir.join("slots/slot1.sav")).unwrap(),
+            b"later progress"
+        );
+
+        let result = restore_snapshot(
+            &snapshot.id,
+            &game,
+            &backup_location,
+            &overwrite_unsaved(),
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .unwrap();
+        assert!(result.success, "{}", result.message);
+        assert!(result.backed_up_current);
+        assert_eq!(result.files_restored, 1);
+
+        assert_eq!(
+            fs::read(save_dir.join("slots/slot1.sav")).unwrap(),
+            b"checkpoint"
+        );
+        assert!(!save_dir.join("new.sav").exists());
+
+        // the saves are in the restored snapshot, though the backup taken before
+        // restoring it is newer
+        let result = restore_snapshot(
+            &snapshot.id,
+            &game,
+            &backup_location,
+            &RestoreOptions::default(),
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .unwrap();
+        assert!(result.success, "{}", result.message);
+        assert!(result.unsaved_changes.is_none());
+
+        let leftovers: Vec<_> = fs::read_dir(&root)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|name| name.contains(".checkpoint-"))
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_restore_keeps_files_outside_the_filters() {
+        let root = temp_dir("restore-filtered");
+        let save_dir = root.join("Saves");
+        fs::create_dir_all(save_dir.join("shadercache")).unwrap();
+        fs::write(save_dir.join("slot1.sav"), b"checkpoint").unwrap();
+        fs::write(save_dir.join("shadercache/cache.bin"), b"cache").unwrap();
+
+        let backup_location = root.join("backup").to_string_lossy().to_string();
+        let mut game = test_game(&save_dir);
+        game.exclude_patterns = vec!["shadercache/".to_string(), "*.log".to_string()];
+
+        let snapshot = store_test_snapshot(&game, &backup_location);
+        assert_eq!(snapshot.file_count, 1);
+
+        fs::write(save_dir.join("slot1.sav"), b"later progress").unwrap();
+        fs::write(save_dir.join("game.log"), b"log").unwrap();
+
+        let result = restore_snapshot(
+            &snapshot.id,
+            &game,
+            &backup_location,
+            &overwrite_unsaved(),
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .unwrap();
+        assert!(result.success, "{}", result.message);
+
+        assert_eq!(fs::read(save_dir.join("slot1.sav")).unwrap(), b"checkpoint");
+        assert_eq!(
+            fs::read(save_dir.join("shadercache/cache.bin")).unwrap(),
+            b"cache"
+        );
+        assert_eq!(fs::read(save_dir.join("game.log")).unwrap(), b"log");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_restore_covers_every_save_root() {
+        let root = temp_dir("restore-roots");
+        let save_dir = root.join("Saves");
+        let config_dir = root.join("Config");
+        fs::create_dir_all(&save_dir).unwrap();
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(save_dir.join("slot1.sav"), b"checkpoint").unwrap();
+        fs::write(config_dir.join("settings.ini"), b"fov=90").unwrap();
+
+        let backup_location = root.join("backup").to_string_lossy().to_string();
+        let mut game = test_game(&save_dir);
+        game.set_save_roots(vec![
+            SaveRoot {
+                name: "saves".to_string(),
+                path: save_dir.to_string_lossy().to_string(),
+            },
+            SaveRoot {
+                name: "config".to_string(),
+                path: config_dir.to_string_lossy().to_string(),
+            },
+        ])
+        .unwrap();
+
+        let snapshot = store_test_snapshot(&game, &backup_location);
+        assert_eq!(snapshot.file_count, 2);
+
+        fs::write(save_dir.join("slot1.sav"), b"later progress").unwrap();
+        fs::remove_dir_all(&config_dir).unwrap();
+
+        let result = restore_snapshot(
+            &snapshot.id,
+            &game,
+            &backup_location,
+            &overwrite_unsaved(),
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!(result.files_restored, 2);
+
+        assert_eq!(fs::read(save_dir.join("slot1.sav")).unwrap(), b"checkpoint");
+        assert_eq!(
+            fs::read(config_dir.join("settings.ini")).unwrap(),
+            b"fov=90"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_restore_files_only_touches_the_selected_files() {
+        let root = temp_dir("restore-files");
+        let save_dir = root.join("Saves");
+        fs::create_dir_all(save_dir.join("slots")).unwrap();
+        fs::write(save_dir.join("slots/slot1.sav"), b"slot 1").unwrap();
+        fs::write(save_dir.join("slots/slot2.sav"), b"slot 2").unwrap();
+        fs::write(save_dir.join("settings.ini"), b"fov=90").unwrap();
+
+        let backup_location = root.join("backup").to_string_lossy().to_string();
+        let game = test_game(&save_dir);
+
+        let snapshot = store_test_snapshot(&game, &backup_location);
+
+        fs::write(save_dir.join("slots/slot1.sav"), b"slot 1 later").unwrap();
+        fs::write(save_dir.join("slots/slot2.sav"), b"slot 2 later").unwrap();
+        fs::write(save_dir.join("settings.ini"), b"fov=110").unwrap();
+
+        let result = restore_files(
+            &snapshot.id,
+            &game,
+            &["saves/slots/slot1.sav".to_string()],
+            &backup_location,
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .unwrap();
+        assert!(result.success, "{}", result.message);
+        assert_eq!(result.files_restored, 1);
+
+        assert_eq!(
+            fs::read(save_dir.join("slots/slot1.sav")).unwrap(),
+            b"slot 1"
+        );
+        assert_eq!(
+            fs::read(save_dir.join("slots/slot2.sav")).unwrap(),
+            b"slot 2 later"
+        );
+        assert_eq!(fs::read(save_dir.join("settings.ini")).unwrap(), b"fov=110");
+
+        // only the overwritten file was backed up
+        let backup_id = result.backup_snapshot_id.unwrap();
+        let backup = snapshot_index(&game, &backup_id, &backup_location).unwrap();
+        assert_eq!(
+            backup.keys().collect::<Vec<_>>(),
+            vec!["saves/slots/slot1.sav"]
+        );
+
+        // a directory selects everything below it
+        fs::remove_file(save_dir.join("slots/slot1.sav")).unwrap();
+        let result = restore_files(
+            &snapshot.id,
+            &game,
+            &["saves/slots/".to_string()],
+            &backup_location,
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .unwrap();
+        assert_eq!(result.files_restored, 2);
+        assert_eq!(
+            fs::read(save_dir.join("slots/slot2.sav")).unwrap(),
+            b"slot 2"
+        );
+        assert!(fs::read_dir(save_dir.join("slots")).unwrap().all(|e| !e
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .contains(".checkpoint-")));
+
+        assert!(restore_files(
+            &snapshot.id,
+            &game,
+            &["saves/missing.sav".to_string()],
+            &backup_location,
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_restore_to_another_directory_leaves_the_save_alone() {
+        let root = temp_dir("extract");
+        let save_dir = root.join("Saves");
+        fs::create_dir_all(save_dir.join("slots")).unwrap();
+        fs::write(save_dir.join("slots/slot1.sav"), b"checkpoint").unwrap();
+
+        let backup_location = root.join("backup").to_string_lossy().to_string();
+        let game = test_game(&save_dir);
+
+        let snapshot = store_test_snapshot(&game, &backup_location);
+        fs::write(save_dir.join("slots/slot1.sav"), b"later progress").unwrap();
+
+        let target_dir = root.join("inspect");
+        let options = RestoreOptions {
+            target_dir: Some(target_dir.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let result = restore_snapshot(
+            &snapshot.id,
+            &game,
+            &backup_location,
+            &options,
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .unwrap();
+        assert!(result.success, "{}", result.message);
+        assert!(!result.backed_up_current);
+
+        assert_eq!(
+            fs::read(target_dir.join("saves/slots/slot1.sav")).unwrap(),
+            b"checkpoint"
+        );
+        assert_eq!(
+            fs::read(save_dir.join("slots/slot1.sav")).unwrap(),
+            b"later progress"
+        );
+        assert_eq!(list_snapshots("game", &backup_location).unwrap().len(), 1);
+
+        // naming a save folder doesn't turn it into a restore of every save folder
+        let into_save_dir = RestoreOptions {
+            target_dir: Some(save_dir.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        assert!(restore_snapshot(
+            &snapshot.id,
+            &game,
+            &backup_location,
+            &into_save_dir,
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .unwrap_err()
+        .contains("restore the snapshot instead"));
+        assert_eq!(
+            fs::read(save_dir.join("slots/slot1.sav")).unwrap(),
+            b"later progress"
+        );
+
+        // a second extraction would mix two snapshots
+        assert!(restore_snapshot(
+            &snapshot.id,
+            &game,
+            &backup_location,
+            &options,
+            &StoreSettings::default(),
+            None,
+            &Operation::untracked(),
+        )
+        .is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_exported_archives_import_with_their_name_timestamp_and_manifest() {
+        let (backup_dir, backup_location, snapshot) =
+            snapshot_of(&[("slot1.sav", b"first"), ("nested/slot2.sav", b"second")]);
+        let manifest = |snapshot: &Snapshot| {
+            read_metadata(Path::new(&snapshot.path))
+                .unwrap()
+                .files
+                .unwrap()
+                .into_iter()
+                .map(|f| (f.path, f.hash, f.size, f.modified))
+                .collect::<Vec<_>>()
+        };
+
+        for archive in ["export.zip", "export.tar.zst"] {
+            let path = backup_dir.join(archive);
+            export_snapshot(
+                &snapshot.id,
+                "game",
+                &path,
+                &backup_location,
+                false,
+                &Operation::untracked(),
+            )
+            .unwrap();
+
+            let data = fs::read(&path).unwrap();
+            let imported = import_snapshot(
+                "game",
+                None,
+                &data,
+                &backup_location,
+                &StoreSettings::default(),
+                &Operation::untracked(),
+            )
+            .unwrap();
+            assert_eq!(imported.name, "test");
+            assert_eq!(imported.timestamp, snapshot.timestamp);
+            assert_eq!(imported.file_count, 2);
+
+            assert_eq!(manifest(&imported), manifest(&snapshot));
+        }
+
+        assert!(ArchiveFormat::from_path(&backup_dir.join("export.rar")).is_err());
+        fs::remove_dir_all(&backup_dir).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_snapshots_are_only_exported_when_asked_for_decrypted() {
+        let save_dir = temp_dir("save");
+        fs::write(save_dir.join("slot1.sav"), b"secret").unwrap();
+        let backup_dir = temp_dir("backup");
+        let backup_location = backup_dir.to_string_lossy().to_string();
+        crate::crypto::set_up(&backup_location, "hunter2").unwrap();
+        crate::crypto::unlock(&backup_location, "hunter2").unwrap();
+
+        let settings = StoreSettings {
+            key: Some(crate::crypto::key_for(&backup_location).unwrap()),
+            ..StoreSettings::default()
+        };
+        let snapshot = store_snapshot(
+            &test_game(&save_dir),
+            NewSnapshot {
+                name: "test".to_string(),
+                timestamp: Utc::now(),
+                kind: SnapshotKind::Manual,
+                session_id: None,
+            },
+            &backup_location,
+            &settings,
+            &Operation::untracked(),
+        )
+        .unwrap();
+
+        let path = save_dir.join("export.zip");
+        let export = |decrypt| {
+            export_snapshot(
+                &snapshot.id,
+                "game",
+                &path,
+                &backup_location,
+                decrypt,
+                &Operation::untracked(),
+            )
+        };
+        assert_eq!(export(false).unwrap_err(), ENCRYPTED_EXPORT);
+        assert!(!path.exists());
+        assert_eq!(
+            export_snapshot_zip(&snapshot.id, "game", &backup_location, false).unwrap_err(),
+            ENCRYPTED_EXPORT
+        );
+
+        export(true).unwrap();
+        let mut zip = zip::ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+        let name = zip
+            .file_names()
+            .find(|name| name.ends_with("slot1.sav"))
+            .unwrap()
+            .to_string();
+        let mut contents = Vec::new();
+        zip.by_name(&name)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, b"secret");
+
+        fs::remove_dir_all(&save_dir).unwrap();
+        fs::remove_dir_all(&backup_dir).unwrap();
+    }
+
+    fn zip_archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    // the raw name is written as is, `tar::Header::set_path` refuses '..'
+    fn tar_zst_archive(entries: &[(&str, tar::EntryType, &[u8])]) -> Vec<u8> {
+        let mut tar = tar::Builder::new(Vec::new());
+        for (name, kind, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_entry_type(*kind);
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append(&header, *contents).unwrap();
+        }
+        zstd::encode_all(tar.into_inner().unwrap().as_slice(), 3).unwrap()
+    }
+
+    fn import_error(data: &[u8], limits: &ImportLimits) -> String {
+        let backup_dir = temp_dir("import");
+        let backup_location = backup_dir.to_string_lossy().to_string();
+
+        let error = import_archive(
+            "game",
+            None,
+            data,
+            &backup_location,
+            &StoreSettings::default(),
+            limits,
+            &Operation::untracked(),
+        )
+        .unwrap_err();
+        assert!(list_snapshots("game", &backup_location).unwrap().is_empty());
+
+        fs::remove_dir_all(&backup_dir).unwrap();
+        error
+    }
+
+    fn default_limits() -> ImportLimits {
+        ImportLimits {
+            max_entries: MAX_IMPORT_ENTRIES,
+            max_bytes: MIN_IMPORT_LIMIT,
+        }
+    }
+
+    #[test]
+    fn test_imports_reject_entries_outside_the_snapshot() {
+        for name in ["../../.bashrc", "saves/../../escape.sav", "..\\escape.sav"] {
+            let error = import_error(&zip_archive(&[(name, b"x")]), &default_limits());
+            assert!(
+                error.contains("outside the snapshot"),
+                "{}: {}",
+                name,
+                error
+            );
+        }
+        for name in ["/etc/passwd", "C:\\Windows\\evil.dll", "\\evil.sav"] {
+            let error = import_error(&zip_archive(&[(name, b"x")]), &default_limits());
+            assert!(error.contains("absolute path"), "{}: {}", name, error);
+        }
+
+        let error = import_error(
+            &tar_zst_archive(&[("../escape.sav", tar::EntryType::Regular, b"x")]),
+            &default_limits(),
+        );
+        assert!(error.contains("outside the snapshot"), "{}", error);
+        let error = import_error(
+            &tar_zst_archive(&[("/etc/passwd", tar::EntryType::Regular, b"x")]),
+            &default_limits(),
+        );
+        assert!(error.contains("absolute path"), "{}", error);
+
+        let error = import_error(
+            &zip_archive(&[("slot.sav", b"a"), ("./slot.sav", b"b")]),
+            &default_limits(),
+        );
+        assert!(error.contains("more than once"), "{}", error);
+
+        assert_eq!(
+            archive_entry_path("saves\\./slot1.sav").unwrap(),
+            "saves/slot1.sav"
+        );
+    }
+
+    #[test]
+    fn test_imports_reject_links() {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip.add_symlink(
+            "slot.sav",
+            "/etc/passwd",
+            zip::write::FileOptions::default(),
+        )
+        .unwrap();
+        let error = import_error(&zip.finish().unwrap().into_inner(), &default_limits());
+        assert!(error.contains("symbolic link"), "{}", error);
+
+        for kind in [tar::EntryType::Symlink, tar::EntryType::Link] {
+            let error = import_error(
+                &tar_zst_archive(&[("slot.sav", kind, b"")]),
+                &default_limits(),
+            );
+            assert!(error.contains("is a link"), "{}", error);
+        }
+
+        let error = import_error(
+            &tar_zst_archive(&[("fifo", tar::EntryType::Fifo, b"")]),
+            &default_limits(),
+        );
+        assert!(error.contains("isn't a regular file"), "{}", error);
+    }
+
+    #[test]
+    fn test_imports_reject_zip_bombs() {
+        let limits = ImportLimits {
+            max_entries: 2,
+            max_bytes: 1000,
+        };
+        let zeros = vec![0u8; 10_000];
+
+        let bomb = zip_archive(&[("slot.sav", &zeros)]);
+        assert!(import_error(&bomb, &limits).contains("zip bomb"));
+
+        // the same archive, claiming in its headers to unpack to 10 bytes
+        let mut lying = bomb.clone();
+        for (signature, offset) in [(b"PK\x03\x04", 22), (b"PK\x01\x02", 24)] {
+            let start = lying
+                .windows(4)
+                .position(|window| window == signature)
+                .unwrap();
+            lying[start + offset..start + offset + 4].copy_from_slice(&10u32.to_le_bytes());
+        }
+        assert!(import_error(&lying, &limits).contains("zip bomb"));
+
+        let bomb = tar_zst_archive(&[("slot.sav", tar::EntryType::Regular, &zeros)]);
+        assert!(import_error(&bomb, &limits).contains("zip bomb"));
+
+        let many = zip_archive(&[("a.sav", b"a"), ("b.sav", b"b"), ("c.sav", b"c")]);
+        assert!(import_error(&many, &limits).contains("more than 2 files"));
+    }
 }
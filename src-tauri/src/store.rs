@@ -0,0 +1,558 @@
+use crate::crypto::EncryptionKey;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+const OBJECTS_DIR: &str = ".objects";
+const TMP_DIR: &str = "tmp";
+const BUFFER_SIZE: usize = 64 * 1024;
+// appended to the path of objects stored zstd-compressed
+const COMPRESSED_SUFFIX: &str = ".zst";
+// appended to the path of encrypted objects, which may be compressed inside
+const ENCRYPTED_SUFFIX: &str = ".enc";
+
+// objects touched this recently are never collected, so a snapshot that is
+// still being written (its manifest isn't on disk yet) can't lose its files
+const GC_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionSettings {
+    pub enabled: bool,
+    /// zstd level, 1 (fastest) to 22 (smallest).
+    pub level: i32,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: 3,
+        }
+    }
+}
+
+impl CompressionSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=22).contains(&self.level) {
+            return Err("Compression level must be between 1 and 22".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// How a store writes new objects, from the settings of the game they belong to.
+#[derive(Clone, Default)]
+pub struct StoreSettings {
+    pub compression: CompressionSettings,
+    // new objects are encrypted with it when set
+    pub key: Option<EncryptionKey>,
+}
+
+/// Content-addressed storage shared by every snapshot under a backup location.
+/// Each file is stored once under its SHA-256 hash, snapshots only reference it.
+/// Objects are stored as is, zstd-compressed or encrypted. Encrypted objects are
+/// named by a keyed hash instead (see `EncryptionKey::object_id`), so the store
+/// doesn't tell which files it holds. Callers always use the hash of the original
+/// contents and don't need to know which form an object is in.
+pub struct ObjectStore {
+    backup_location: String,
+    root: PathBuf,
+    // zstd level new objects are compressed with, None stores them as is
+    compression_level: Option<i32>,
+    // new objects are encrypted with it when set
+    key: Option<EncryptionKey>,
+    // the location's unlocked key, for finding encrypted objects in a store that
+    // doesn't write them
+    unlocked_key: OnceCell<Option<EncryptionKey>>,
+}
+
+impl ObjectStore {
+    pub fn new(backup_location: &str) -> Self {
+        Self {
+            backup_location: backup_location.to_string(),
+            root: Path::new(backup_location).join(OBJECTS_DIR),
+            compression_level: None,
+            key: None,
+            unlocked_key: OnceCell::new(),
+        }
+    }
+
+    pub fn with_compression(mut self, settings: &CompressionSettings) -> Self {
+        self.compression_level = settings.enabled.then_some(settings.level);
+        self
+    }
+
+    pub fn with_encryption(mut self, key: EncryptionKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    pub fn with_settings(self, settings: &StoreSettings) -> Self {
+        let store = self.with_compression(&settings.compression);
+        match &settings.key {
+            Some(key) => store.with_encryption(key.clone()),
+            None => store,
+        }
+    }
+
+    pub fn encryption_key(&self) -> Option<&EncryptionKey> {
+        self.key.as_ref()
+    }
+
+    pub fn object_path(&self, hash: &str) -> PathBuf {
+        let (prefix, rest) = hash.split_at(2.min(hash.len()));
+        self.root.join(prefix).join(rest)
+    }
+
+    fn suffixed_path(&self, hash: &str, suffix: &str) -> PathBuf {
+        let mut path = self.object_path(hash).into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+
+    fn any_key(&self) -> Option<&EncryptionKey> {
+        self.key.as_ref().or_else(|| {
+            self.unlocked_key
+                .get_or_init(|| crate::crypto::key_for(&self.backup_location).ok())
+                .as_ref()
+        })
+    }
+
+    // where the object is stored encrypted, None when no key is unlocked
+    fn encrypted_path(&self, hash: &str) -> Option<PathBuf> {
+        let key = self.any_key()?;
+        Some(self.suffixed_path(&key.object_id(hash), ENCRYPTED_SUFFIX))
+    }
+
+    // the stored forms of an object, the ones readable without a key first.
+    // Objects encrypted before they were named by keyed hashes are last
+    fn candidate_paths(&self, hash: &str) -> Vec<PathBuf> {
+        let mut paths = vec![
+            self.object_path(hash),
+            self.suffixed_path(hash, COMPRESSED_SUFFIX),
+        ];
+        paths.extend(self.encrypted_path(hash));
+        paths.push(self.suffixed_path(hash, ENCRYPTED_SUFFIX));
+        paths
+    }
+
+    fn find_path(&self, hash: &str) -> Option<PathBuf> {
+        self.candidate_paths(hash)
+            .into_iter()
+            .find(|path| path.is_file())
+    }
+
+    // the object in a form this store reuses instead of writing it again. An
+    // encrypting store only reuses objects encrypted under their keyed name, so
+    // encrypted snapshots never depend on a copy that isn't
+    fn reusable_path(&self, hash: &str) -> Option<PathBuf> {
+        if self.key.is_some() {
+            self.encrypted_path(hash).filter(|path| path.is_file())
+        } else {
+            [
+                self.object_path(hash),
+                self.suffixed_path(hash, COMPRESSED_SUFFIX),
+            ]
+            .into_iter()
+            .find(|path| path.is_file())
+        }
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.find_path(hash).is_some()
+    }
+
+    /// Size of the object on disk, which is less than the file's own size when
+    /// it is compressed. An encrypting store gives the size of its encrypted copy.
+    pub fn stored_size(&self, hash: &str) -> Option<u64> {
+        let path = self.reusable_path(hash).or_else(|| self.find_path(hash))?;
+        fs::metadata(path).ok().map(|m| m.len())
+    }
+
+    /// Opens an object for reading its original contents. Encrypted objects need
+    /// their key to be unlocked.
+    pub fn open(&self, hash: &str) -> Result<Box<dyn Read>, String> {
+        let path = match self.find_path(hash) {
+            Some(path) => path,
+            // it may be stored under a keyed name that can't be worked out yet
+            None if crate::crypto::key_for(&self.backup_location)
+                .is_err_and(|e| e == crate::crypto::LOCKED) =>
+            {
+                return Err(crate::crypto::LOCKED.to_string())
+            }
+            None => return Err(format!("Object {} is missing", hash)),
+        };
+        let file =
+            fs::File::open(&path).map_err(|e| format!("Failed to open object {}: {}", hash, e))?;
+
+        let (reader, compressed): (Box<dyn Read>, bool) = if has_suffix(&path, ENCRYPTED_SUFFIX) {
+            let (reader, compressed) = crate::crypto::decrypt_reader(file)?;
+            (Box::new(reader), compressed)
+        } else {
+            (Box::new(file), has_suffix(&path, COMPRESSED_SUFFIX))
+        };
+
+        if compressed {
+            let decoder = zstd::stream::read::Decoder::new(reader)
+                .map_err(|e| format!("Failed to open object {}: {}", hash, e))?;
+            Ok(Box::new(decoder))
+        } else {
+            Ok(reader)
+        }
+    }
+
+    /// Writes the original contents of an object to `dest`, returning how many bytes were written.
+    pub fn copy_to(&self, hash: &str, dest: &Path) -> Result<u64, String> {
+        let mut reader = self.open(hash)?;
+        let mut file = fs::File::create(dest)
+            .map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+        std::io::copy(&mut reader, &mut file)
+            .map_err(|e| format!("Failed to read object {}: {}", hash, e))
+    }
+
+    /// Stores the file at `path`, returning its hash and size. Files that are
+    /// already in the store are not copied again.
+    pub fn put_file(&self, path: &Path) -> Result<(String, u64), String> {
+        let (hash, size) = hash_file(path)?;
+
+        if let Some(stored_path) = self.reusable_path(&hash) {
+            touch(&stored_path);
+            return Ok((hash, size));
+        }
+
+        let mut file = fs::File::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        self.put_reader(&mut file)
+    }
+
+    /// Streams `reader` into the store, returning the hash and size of what was read.
+    pub fn put_reader(&self, reader: &mut dyn Read) -> Result<(String, u64), String> {
+        let tmp_dir = self.root.join(TMP_DIR);
+        fs::create_dir_all(&tmp_dir)
+            .map_err(|e| format!("Failed to create object directory: {}", e))?;
+
+        let tmp_path = tmp_dir.join(Uuid::new_v4().to_string());
+        let result = self.write_object(reader, &tmp_path);
+
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        result
+    }
+
+    fn write_object(
+        &self,
+        reader: &mut dyn Read,
+        tmp_path: &Path,
+    ) -> Result<(String, u64), String> {
+        let tmp_file =
+            fs::File::create(tmp_path).map_err(|e| format!("Failed to create object: {}", e))?;
+
+        // compressed before it is encrypted, encrypted data doesn't compress
+        let mut writer: Box<dyn ObjectWriter> = Box::new(tmp_file);
+        if let Some(key) = &self.key {
+            writer = Box::new(
+                crate::crypto::encrypt_writer(key, self.compression_level.is_some(), writer)
+                    .map_err(|e| format!("Failed to create object: {}", e))?,
+            );
+        }
+        if let Some(level) = self.compression_level {
+            writer = Box::new(
+                zstd::stream::write::Encoder::new(writer, level)
+                    .map_err(|e| format!("Failed to create object: {}", e))?,
+            );
+        }
+
+        let (hash, size) = copy_hashing(reader, &mut writer)?;
+        writer
+            .finish()
+            .map_err(|e| format!("Failed to write object: {}", e))?;
+
+        let object_path = if let Some(key) = &self.key {
+            self.suffixed_path(&key.object_id(&hash), ENCRYPTED_SUFFIX)
+        } else if self.compression_level.is_some() {
+            self.suffixed_path(&hash, COMPRESSED_SUFFIX)
+        } else {
+            self.object_path(&hash)
+        };
+
+        if let Some(stored_path) = self.reusable_path(&hash) {
+            fs::remove_file(tmp_path).map_err(|e| format!("Failed to remove temp file: {}", e))?;
+            touch(&stored_path);
+        } else {
+            if let Some(parent) = object_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create object directory: {}", e))?;
+            }
+            fs::rename(tmp_path, &object_path)
+                .map_err(|e| format!("Failed to store object: {}", e))?;
+        }
+
+        Ok((hash, size))
+    }
+
+    /// Removes every object that isn't in `referenced`, which lists encrypted
+    /// objects by their keyed hash. Returns how many were removed.
+    pub fn collect_garbage(&self, referenced: &HashSet<String>) -> Result<usize, String> {
+        if !self.root.exists() {
+            return Ok(0);
+        }
+
+        let now = SystemTime::now();
+        let mut removed = 0;
+
+        for entry in WalkDir::new(&self.root).min_depth(2).max_depth(2) {
+            let entry = entry.map_err(|e| format!("Failed to read object store: {}", e))?;
+            let path = entry.path();
+
+            if !path.is_file() || path.parent() == Some(&self.root.join(TMP_DIR)) {
+                continue;
+            }
+
+            let hash = match (path.parent().and_then(|p| p.file_name()), path.file_name()) {
+                (Some(prefix), Some(rest)) => {
+                    let rest = rest.to_string_lossy();
+                    let rest = [COMPRESSED_SUFFIX, ENCRYPTED_SUFFIX]
+                        .iter()
+                        .find_map(|suffix| rest.strip_suffix(suffix))
+                        .unwrap_or(&rest);
+                    format!("{}{}", prefix.to_string_lossy(), rest)
+                }
+                _ => continue,
+            };
+
+            if referenced.contains(&hash) {
+                continue;
+            }
+
+            let recently_used = fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .map(|age| age < GC_GRACE_PERIOD)
+                .unwrap_or(true);
+
+            if recently_used {
+                continue;
+            }
+
+            fs::remove_file(path).map_err(|e| format!("Failed to remove object: {}", e))?;
+            removed += 1;
+
+            if let Some(parent) = path.parent() {
+                // only succeeds once the prefix directory is empty
+                let _ = fs::remove_dir(parent);
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+// the layers an object is written through, finishing one finishes the ones below it
+trait ObjectWriter: Write {
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+impl ObjectWriter for fs::File {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.sync_all()
+    }
+}
+
+impl ObjectWriter for zstd::stream::write::Encoder<'static, Box<dyn ObjectWriter>> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        zstd::stream::write::Encoder::finish(*self)?.finish()
+    }
+}
+
+impl ObjectWriter for crate::crypto::EncryptWriter<Box<dyn ObjectWriter>> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        crate::crypto::EncryptWriter::finish(*self)?.finish()
+    }
+}
+
+fn has_suffix(path: &Path, suffix: &str) -> bool {
+    path.to_string_lossy().ends_with(suffix)
+}
+
+// copies `reader` into `writer`, returning the hash and size of what was copied
+fn copy_hashing(reader: &mut dyn Read, writer: &mut dyn Write) -> Result<(String, u64), String> {
+    let mut hasher = Sha256::new();
+    let mut size: u64 = 0;
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer
+            .write_all(&buffer[..read])
+            .map_err(|e| format!("Failed to write object: {}", e))?;
+        size += read as u64;
+    }
+
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+pub fn hash_file(path: &Path) -> Result<(String, u64), String> {
+    let mut file =
+        fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    hash_reader(&mut file)
+}
+
+pub fn hash_reader(reader: &mut dyn Read) -> Result<(String, u64), String> {
+    copy_hashing(reader, &mut std::io::sink())
+}
+
+fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_location() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("checkpoint-store-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_identical_files_are_stored_once() {
+        let location = temp_location();
+        let store = ObjectStore::new(&location.to_string_lossy());
+
+        let a = location.join("a.sav");
+        let b = location.join("b.sav");
+        fs::write(&a, b"same contents").unwrap();
+        fs::write(&b, b"same contents").unwrap();
+
+        let (hash_a, size_a) = store.put_file(&a).unwrap();
+        let (hash_b, _) = store.put_file(&b).unwrap();
+
+        assert_eq!(hash_a, hash_b);
+        assert_eq!(size_a, 13);
+        assert_eq!(
+            fs::read(store.object_path(&hash_a)).unwrap(),
+            b"same contents"
+        );
+
+        let objects = WalkDir::new(location.join(OBJECTS_DIR))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .count();
+        assert_eq!(objects, 1);
+
+        fs::remove_dir_all(&location).unwrap();
+    }
+
+    #[test]
+    fn test_compressed_objects_read_back_transparently() {
+        let location = temp_location();
+        let plain = ObjectStore::new(&location.to_string_lossy());
+        let compressed =
+            ObjectStore::new(&location.to_string_lossy()).with_compression(&CompressionSettings {
+                enabled: true,
+                level: 19,
+            });
+
+        let contents = b"save data ".repeat(1000);
+        let (hash, size) = compressed.put_reader(&mut &contents[..]).unwrap();
+        assert_eq!(size, contents.len() as u64);
+        assert!(!plain.object_path(&hash).exists());
+        assert!(compressed.stored_size(&hash).unwrap() < size);
+
+        let mut read_back = Vec::new();
+        plain
+            .open(&hash)
+            .unwrap()
+            .read_to_end(&mut read_back)
+            .unwrap();
+        assert_eq!(read_back, contents);
+
+        // the same contents aren't stored again uncompressed
+        let (plain_hash, _) = plain.put_reader(&mut &contents[..]).unwrap();
+        assert_eq!(plain_hash, hash);
+        assert!(!plain.object_path(&hash).exists());
+
+        fs::remove_dir_all(&location).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_objects_are_not_named_by_their_hash() {
+        let location = temp_location();
+        let backup_location = location.to_string_lossy().to_string();
+        crate::crypto::set_up(&backup_location, "hunter2").unwrap();
+        crate::crypto::unlock(&backup_location, "hunter2").unwrap();
+        let key = crate::crypto::key_for(&backup_location).unwrap();
+        let store = ObjectStore::new(&backup_location).with_encryption(key.clone());
+
+        let (hash, _) = store.put_reader(&mut &b"secret save"[..]).unwrap();
+        let stored: Vec<String> = WalkDir::new(location.join(OBJECTS_DIR))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .map(|e| e.path().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(stored.len(), 1);
+        assert!(!stored[0].contains(&hash[2..]));
+        assert!(stored[0].contains(&key.object_id(&hash)[2..]));
+
+        // found again by stores that don't encrypt
+        let mut read_back = Vec::new();
+        ObjectStore::new(&backup_location)
+            .open(&hash)
+            .unwrap()
+            .read_to_end(&mut read_back)
+            .unwrap();
+        assert_eq!(read_back, b"secret save");
+
+        fs::remove_dir_all(&location).unwrap();
+    }
+
+    #[test]
+    fn test_garbage_collection_keeps_referenced_and_recent_objects() {
+        let location = temp_location();
+        let store = ObjectStore::new(&location.to_string_lossy());
+
+        let (kept, _) = store.put_reader(&mut &b"kept"[..]).unwrap();
+        let (stale, _) = store.put_reader(&mut &b"stale"[..]).unwrap();
+        let (recent, _) = store.put_reader(&mut &b"recent"[..]).unwrap();
+
+        let old = SystemTime::now() - GC_GRACE_PERIOD * 2;
+        for hash in [&kept, &stale] {
+            let file = fs::File::options()
+                .write(true)
+                .open(store.object_path(hash))
+                .unwrap();
+            file.set_modified(old).unwrap();
+        }
+
+        let referenced = HashSet::from([kept.clone()]);
+        assert_eq!(store.collect_garbage(&referenced).unwrap(), 1);
+
+        assert!(store.contains(&kept));
+        assert!(!store.contains(&stale));
+        assert!(store.contains(&recent));
+
+        fs::remove_dir_all(&location).unwrap();
+    }
+}
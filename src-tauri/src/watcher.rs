@@ -0,0 +1,434 @@
+use crate::config::Config;
+use crate::operation::Operation;
+use crate::sessions::SessionLog;
+use crate::snapshot::{CreateSnapshotRequest, Snapshot, SnapshotKind};
+use chrono::Utc;
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// how often pending changes are checked when no events come in
+const TICK: Duration = Duration::from_secs(1);
+// events of a paused game still coming in this long after it was resumed are
+// Checkpoint's own writes too
+const RESUME_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatcherSettings {
+    pub enabled: bool,
+    /// How long a save folder has to stay unchanged before it is snapshotted.
+    pub quiet_period_secs: u64,
+}
+
+impl Default for WatcherSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            quiet_period_secs: 30,
+        }
+    }
+}
+
+enum Message {
+    Event(notify::Result<Event>),
+    Refresh,
+    Pause(String),
+    Resume(String),
+}
+
+type SnapshotListener = Box<dyn Fn(&Snapshot) + Send>;
+
+/// Watches the save folders of games with auto-snapshots enabled and snapshots
+/// them once a burst of writes has settled down.
+pub struct SaveWatcher {
+    sender: Sender<Message>,
+}
+
+impl SaveWatcher {
+    /// Starts the watcher thread. `on_snapshot` is called for every snapshot it takes,
+    /// `sessions` links them to the play session they were taken in.
+    pub fn start(
+        sessions: Arc<Mutex<SessionLog>>,
+        on_snapshot: impl Fn(&Snapshot) + Send + 'static,
+    ) -> Result<Self, String> {
+        let (sender, receiver) = mpsc::channel();
+
+        let event_sender = sender.clone();
+        let watcher = notify::recommended_watcher(move |event| {
+            let _ = event_sender.send(Message::Event(event));
+        })
+        .map_err(|e| format!("Failed to start file watcher: {}", e))?;
+
+        std::thread::spawn(move || run(watcher, receiver, sessions, Box::new(on_snapshot)));
+
+        let save_watcher = Self { sender };
+        save_watcher.refresh();
+        Ok(save_watcher)
+    }
+
+    /// Re-reads the config and watches the save roots it lists. Pending changes
+    /// are dropped.
+    pub fn refresh(&self) {
+        let _ = self.sender.send(Message::Refresh);
+    }
+
+    /// Ignores changes to the game's save folders until the returned guard is
+    /// dropped, so Checkpoint's own writes to them (restores) don't trigger a snapshot.
+    pub fn pause(&self, game_id: &str) -> WatchPause {
+        let _ = self.sender.send(Message::Pause(game_id.to_string()));
+        WatchPause {
+            sender: self.sender.clone(),
+            game_id: game_id.to_string(),
+        }
+    }
+}
+
+/// Keeps a game's save folders paused in the watcher while it lives.
+pub struct WatchPause {
+    sender: Sender<Message>,
+    game_id: String,
+}
+
+impl Drop for WatchPause {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Message::Resume(self.game_id.clone()));
+    }
+}
+
+struct WatchState {
+    watcher: RecommendedWatcher,
+    // every watched save root and the game it belongs to
+    watched: Vec<(PathBuf, String)>,
+    // save roots that didn't exist yet, watched as soon as they appear
+    missing: Vec<PathBuf>,
+    quiet_period: Duration,
+    debouncer: Debouncer,
+    // number of pauses held per game
+    paused: HashMap<String, usize>,
+    // when each game was last resumed
+    resumed: HashMap<String, Instant>,
+}
+
+impl WatchState {
+    fn new(watcher: RecommendedWatcher) -> Self {
+        Self {
+            watcher,
+            watched: Vec::new(),
+            missing: Vec::new(),
+            quiet_period: Duration::from_secs(WatcherSettings::default().quiet_period_secs),
+            debouncer: Debouncer::default(),
+            paused: HashMap::new(),
+            resumed: HashMap::new(),
+        }
+    }
+
+    fn refresh(&mut self) {
+        for (path, _) in self.watched.drain(..) {
+            let _ = self.watcher.unwatch(&path);
+        }
+        self.missing.clear();
+        self.debouncer = Debouncer::default();
+
+        let config = match Config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("File watcher could not load the config: {}", e);
+                return;
+            }
+        };
+
+        self.quiet_period = Duration::from_secs(config.watcher.quiet_period_secs);
+        if !config.watcher.enabled {
+            return;
+        }
+
+        let variables = config.path_variables();
+        for game in config.games.iter().filter(|g| g.watch_saves) {
+            let game = match game.resolved_with(&variables) {
+                Ok(game) => game,
+                Err(e) => {
+                    eprintln!("Failed to watch {}: {}", game.name, e);
+                    continue;
+                }
+            };
+
+            for root in &game.save_roots {
+                self.watch_root(PathBuf::from(&root.path), &game.id);
+            }
+        }
+    }
+
+    fn watch_root(&mut self, path: PathBuf, game_id: &str) {
+        if !path.exists() {
+            self.missing.push(path);
+            return;
+        }
+
+        match self.watcher.watch(&path, RecursiveMode::Recursive) {
+            Ok(()) => self.watched.push((path, game_id.to_string())),
+            Err(e) => eprintln!("Failed to watch {}: {}", path.display(), e),
+        }
+    }
+
+    // a restore swaps a new folder in at the save root, the watch stayed on the
+    // one moved aside
+    fn rewatch(&mut self, game_id: &str) {
+        let roots: Vec<PathBuf> = self
+            .watched
+            .iter()
+            .filter(|(_, id)| id == game_id)
+            .map(|(path, _)| path.clone())
+            .collect();
+        self.watched.retain(|(_, id)| id != game_id);
+
+        for path in roots {
+            let _ = self.watcher.unwatch(&path);
+            self.watch_root(path, game_id);
+        }
+    }
+
+    fn game_for(&self, path: &Path) -> Option<&str> {
+        self.watched
+            .iter()
+            .find(|(root, _)| path.starts_with(root))
+            .map(|(_, game_id)| game_id.as_str())
+    }
+
+    fn pause(&mut self, game_id: String) {
+        self.debouncer.forget(&game_id);
+        *self.paused.entry(game_id).or_default() += 1;
+    }
+
+    fn resume(&mut self, game_id: String, now: Instant) {
+        self.debouncer.forget(&game_id);
+        if let Some(count) = self.paused.get_mut(&game_id) {
+            *count -= 1;
+            if *count == 0 {
+                self.paused.remove(&game_id);
+                self.rewatch(&game_id);
+            }
+        }
+        self.resumed.insert(game_id, now);
+    }
+
+    fn is_paused(&self, game_id: &str, now: Instant) -> bool {
+        self.paused.contains_key(game_id)
+            || self
+                .resumed
+                .get(game_id)
+                .is_some_and(|at| now.duration_since(*at) < RESUME_GRACE)
+    }
+}
+
+fn run(
+    watcher: RecommendedWatcher,
+    receiver: Receiver<Message>,
+    sessions: Arc<Mutex<SessionLog>>,
+    on_snapshot: SnapshotListener,
+) {
+    let mut state = WatchState::new(watcher);
+
+    loop {
+        match receiver.recv_timeout(TICK) {
+            Ok(Message::Event(Ok(event))) if is_change(&event.kind) => {
+                let now = Instant::now();
+                for path in &event.paths {
+                    if let Some(game_id) = state.game_for(path).map(str::to_string) {
+                        if !state.is_paused(&game_id, now) {
+                            state.debouncer.record(game_id, now);
+                        }
+                    }
+                }
+            }
+            Ok(Message::Event(Ok(_))) => {}
+            Ok(Message::Event(Err(e))) => eprintln!("File watcher error: {}", e),
+            Ok(Message::Refresh) => state.refresh(),
+            Ok(Message::Pause(game_id)) => state.pause(game_id),
+            Ok(Message::Resume(game_id)) => state.resume(game_id, Instant::now()),
+            Err(RecvTimeoutError::Timeout) => {
+                if state.missing.iter().any(|path| path.exists()) {
+                    state.refresh();
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        for game_id in state.debouncer.take_due(Instant::now(), state.quiet_period) {
+            auto_snapshot(&game_id, &sessions, &on_snapshot);
+        }
+    }
+}
+
+// reads and metadata changes (access times, permissions) don't change a save
+fn is_change(kind: &EventKind) -> bool {
+    match kind {
+        EventKind::Create(_) | EventKind::Remove(_) => true,
+        EventKind::Modify(ModifyKind::Metadata(_)) => false,
+        EventKind::Modify(_) => true,
+        _ => false,
+    }
+}
+
+fn auto_snapshot(game_id: &str, sessions: &Mutex<SessionLog>, on_snapshot: &SnapshotListener) {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Auto-snapshot skipped, could not load the config: {}", e);
+            return;
+        }
+    };
+
+    let request = CreateSnapshotRequest {
+        game_id: game_id.to_string(),
+        name: Some(format!(
+            "Auto-snapshot {}",
+            Utc::now().format("%Y-%m-%d %H:%M:%S")
+        )),
+        kind: SnapshotKind::Auto,
+    };
+
+    let session_id = crate::sessions::current_session(sessions, game_id);
+    match crate::snapshot::create_snapshot(&config, &request, session_id, &Operation::untracked()) {
+        Ok(snapshot) => {
+            crate::retention::apply(
+                game_id,
+                &config.retention_policy(game_id),
+                &config.backup_location,
+            );
+            on_snapshot(&snapshot);
+        }
+        Err(e) => eprintln!("Auto-snapshot of game {} failed: {}", game_id, e),
+    }
+}
+
+/// Remembers the last change per game until no change came in for the quiet period.
+#[derive(Default)]
+struct Debouncer {
+    last_change: HashMap<String, Instant>,
+}
+
+impl Debouncer {
+    fn record(&mut self, game_id: String, at: Instant) {
+        self.last_change.insert(game_id, at);
+    }
+
+    fn forget(&mut self, game_id: &str) {
+        self.last_change.remove(game_id);
+    }
+
+    fn take_due(&mut self, now: Instant, quiet_period: Duration) -> Vec<String> {
+        let due: Vec<String> = self
+            .last_change
+            .iter()
+            .filter(|(_, at)| now.duration_since(**at) >= quiet_period)
+            .map(|(game_id, _)| game_id.clone())
+            .collect();
+
+        for game_id in &due {
+            self.last_change.remove(game_id);
+        }
+
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_debouncer_waits_for_quiet_period() {
+        let quiet = Duration::from_secs(30);
+        let start = Instant::now();
+        let mut debouncer = Debouncer::default();
+
+        debouncer.record("game".to_string(), start);
+        debouncer.record("game".to_string(), start + Duration::from_secs(20));
+        debouncer.record("other".to_string(), start + Duration::from_secs(5));
+
+        assert!(debouncer
+            .take_due(start + Duration::from_secs(30), quiet)
+            .is_empty());
+        assert_eq!(
+            debouncer.take_due(start + Duration::from_secs(40), quiet),
+            vec!["other"]
+        );
+        assert_eq!(
+            debouncer.take_due(start + Duration::from_secs(50), quiet),
+            vec!["game"]
+        );
+        assert!(debouncer
+            .take_due(start + Duration::from_secs(100), quiet)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_paused_games_ignore_changes_until_shortly_after_resuming() {
+        let watcher = notify::recommended_watcher(|_| {}).unwrap();
+        let mut state = WatchState::new(watcher);
+        let start = Instant::now();
+
+        state.debouncer.record("game".to_string(), start);
+        state.pause("game".to_string());
+        state.pause("game".to_string());
+        assert!(state.is_paused("game", start));
+        assert!(!state.is_paused("other", start));
+        // changes from before the pause are dropped too
+        assert!(state
+            .debouncer
+            .take_due(start + Duration::from_secs(60), state.quiet_period)
+            .is_empty());
+
+        // paused until every pause is released
+        state.resume("game".to_string(), start);
+        assert!(state.is_paused("game", start + RESUME_GRACE * 2));
+        state.resume("game".to_string(), start);
+        assert!(state.is_paused("game", start + RESUME_GRACE / 2));
+        assert!(!state.is_paused("game", start + RESUME_GRACE));
+    }
+
+    #[test]
+    fn test_save_roots_swapped_in_by_a_restore_are_watched_after_resuming() {
+        let root = std::env::temp_dir().join(format!("checkpoint-watch-{}", uuid::Uuid::new_v4()));
+        let save_dir = root.join("Saves");
+        fs::create_dir_all(&save_dir).unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                let _ = sender.send(event);
+            }
+        })
+        .unwrap();
+        let mut state = WatchState::new(watcher);
+        state.watch_root(save_dir.clone(), "game");
+
+        // what a restore does to the save folder
+        state.pause("game".to_string());
+        fs::rename(&save_dir, root.join("old")).unwrap();
+        fs::create_dir_all(&save_dir).unwrap();
+        state.resume("game".to_string(), Instant::now());
+        while receiver.try_recv().is_ok() {}
+
+        let save = save_dir.join("slot1.sav");
+        fs::write(&save, b"progress").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let seen = std::iter::from_fn(|| {
+            receiver
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .ok()
+        })
+        .any(|event| event.paths.contains(&save));
+        assert!(seen);
+        assert_eq!(state.game_for(&save), Some("game"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+use crate::game::Game;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Windows folders save locations may start with, and where they are relative to
+// the user's folder inside a prefix
+const PLACEHOLDERS: &[(&str, &str)] = &[
+    ("%USERPROFILE%", ""),
+    ("%APPDATA%", "AppData/Roaming"),
+    ("%LOCALAPPDATA%", "AppData/Local"),
+];
+
+// the Windows user Proton creates in every prefix
+const PROTON_USER: &str = "steamuser";
+
+/// Whether a save location starts with a Windows placeholder like `%APPDATA%`.
+pub fn has_placeholder(path: &str) -> bool {
+    split_placeholder(path).is_some()
+}
+
+// the placeholder's folder inside the user's folder, and the rest of the path
+fn split_placeholder(path: &str) -> Option<(&'static str, &str)> {
+    PLACEHOLDERS.iter().find_map(|(placeholder, folder)| {
+        let head = path.get(..placeholder.len())?;
+        head.eq_ignore_ascii_case(placeholder)
+            .then(|| (*folder, &path[placeholder.len()..]))
+    })
+}
+
+/// Resolves a leading Windows placeholder against the Wine or Proton prefix,
+/// e.g. `%APPDATA%/Game` to `<prefix>/drive_c/users/steamuser/AppData/Roaming/Game`.
+/// Without a prefix, Windows resolves them natively. Paths without a placeholder
+/// are returned as they are.
+pub fn resolve(path: &str, prefix: Option<&Path>) -> Result<String, String> {
+    let Some((folder, rest)) = split_placeholder(path) else {
+        return Ok(path.to_string());
+    };
+
+    let user_dir = match prefix {
+        Some(prefix) => user_dir(prefix),
+        None => native_user_dir().ok_or_else(|| {
+            format!(
+                "No Wine or Proton prefix was set or found to resolve {}",
+                path
+            )
+        })?,
+    };
+
+    let mut resolved = user_dir;
+    for part in folder.split('/').chain(rest.split(['/', '\\'])) {
+        if !part.is_empty() {
+            resolved.push(part);
+        }
+    }
+    Ok(resolved.to_string_lossy().to_string())
+}
+
+#[cfg(windows)]
+fn native_user_dir() -> Option<PathBuf> {
+    std::env::var_os("USERPROFILE").map(PathBuf::from)
+}
+
+#[cfg(not(windows))]
+fn native_user_dir() -> Option<PathBuf> {
+    None
+}
+
+// Proton's user, then the local user Wine names its user after, then whoever
+// else has a folder in the prefix
+fn user_dir(prefix: &Path) -> PathBuf {
+    let users = prefix.join("drive_c").join("users");
+    let local_user = std::env::var("USER").ok();
+
+    let known = [Some(PROTON_USER.to_string()), local_user]
+        .into_iter()
+        .flatten()
+        .map(|user| users.join(user))
+        .find(|dir| dir.is_dir());
+    if let Some(dir) = known {
+        return dir;
+    }
+
+    let mut others: Vec<PathBuf> = fs::read_dir(&users)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir() && !path.ends_with("Public"))
+                .collect()
+        })
+        .unwrap_or_default();
+    others.sort();
+    others
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| users.join(PROTON_USER))
+}
+
+/// The prefix a game's placeholders resolve against: the one set for the game,
+/// else its Proton prefix when it's a Steam game, else `$WINEPREFIX` or `~/.wine`.
+pub fn find_prefix(game: &Game) -> Option<PathBuf> {
+    if let Some(prefix) = &game.wine_prefix {
+        return Some(prefix_dir(Path::new(prefix)));
+    }
+
+    let home = dirs::home_dir();
+
+    if let (Some(app_id), Some(home)) = (&game.steam_app_id, &home) {
+        let prefix = crate::discovery::steam::steam_roots(home)
+            .iter()
+            .flat_map(|root| crate::discovery::steam::library_folders(root).unwrap_or_default())
+            .map(|library| {
+                library
+                    .join("steamapps")
+                    .join("compatdata")
+                    .join(app_id)
+                    .join("pfx")
+            })
+            .find(|prefix| prefix.is_dir());
+        if prefix.is_some() {
+            return prefix;
+        }
+    }
+
+    std::env::var_os("WINEPREFIX")
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| home.join(".wine")))
+        .filter(|prefix| prefix.join("drive_c").is_dir())
+}
+
+// Proton's compatdata folders hold the actual prefix in `pfx`
+fn prefix_dir(path: &Path) -> PathBuf {
+    let pfx = path.join("pfx");
+    if !path.join("drive_c").is_dir() && pfx.join("drive_c").is_dir() {
+        pfx
+    } else {
+        path.to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholders_resolve_inside_the_prefix() {
+        let prefix = std::env::temp_dir().join(format!("checkpoint-pfx-{}", uuid::Uuid::new_v4()));
+        let users = prefix.join("drive_c/users");
+        fs::create_dir_all(users.join("Public")).unwrap();
+        fs::create_dir_all(users.join(PROTON_USER)).unwrap();
+        let user = users.join(PROTON_USER);
+
+        let resolve = |path: &str| resolve(path, Some(&prefix)).unwrap();
+        assert_eq!(
+            resolve("%APPDATA%/Hades"),
+            user.join("AppData/Roaming/Hades").to_string_lossy()
+        );
+        assert_eq!(
+            resolve("%localappdata%\\Game\\Saved"),
+            user.join("AppData/Local/Game/Saved").to_string_lossy()
+        );
+        assert_eq!(
+            resolve("%USERPROFILE%/Documents/My Games"),
+            user.join("Documents/My Games").to_string_lossy()
+        );
+        assert_eq!(resolve("/home/me/saves"), "/home/me/saves");
+        assert!(!has_placeholder("/home/me/%APPDATA%"));
+        #[cfg(not(windows))]
+        assert!(super::resolve("%APPDATA%/Hades", None).is_err());
+
+        // a compatdata folder stands for the prefix inside it
+        let compatdata = prefix
+            .parent()
+            .unwrap()
+            .join(format!("checkpoint-compatdata-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&compatdata).unwrap();
+        fs::rename(&prefix, compatdata.join("pfx")).unwrap();
+        assert_eq!(prefix_dir(&compatdata), compatdata.join("pfx"));
+
+        fs::remove_dir_all(&compatdata).unwrap();
+    }
+}
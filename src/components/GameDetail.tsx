@@ -8,6 +8,7 @@ import {
   deleteGame,
   isProcessRunning,
   importSnapshot,
+  exportSnapshotZip,
   openFolder,
   updateLastRestoredSnapshot
 } from '../lib/api';
@@ -158,9 +159,12 @@ export function GameDetail({ game, onBack, onGameDeleted, onGameUpdated, setLoad
       const snapshot = await createSnapshot({
         game_id: game.id,
         name: newSnapshotName || undefined,
-      });
+      }, crypto.randomUUID());
 
-      if ((backupDestination === 'cloud' || backupDestination === 'both') && isAuthenticated) {
+      if ((backupDestination === 'cloud' || backupDestination === 'both') && isAuthenticated && snapshot.encrypted) {
+        // it would be uploaded decrypted, that has to be confirmed by hand
+        addToast(t('cloud.encryptedNotUploaded'), 'warning');
+      } else if ((backupDestination === 'cloud' || backupDestination === 'both') && isAuthenticated) {
         setLoading(true, t('loading.uploadingCloud'));
         try {
           await handleUploadToCloud(snapshot);
@@ -194,53 +198,71 @@ export function GameDetail({ game, onBack, onGameDeleted, onGameUpdated, setLoad
     }
   };
 
+  const runRestore = async (snapshotId: string, overwriteUnsaved: boolean) => {
+    const snapshotToRestore = snapshots.find(s => s.id === snapshotId);
+    setLoading(true, t('loading.restoring'));
+
+    try {
+      const result = await restoreSnapshot(snapshotId, game.id, crypto.randomUUID(), {
+        overwrite_unsaved: overwriteUnsaved,
+      });
+      if (result.success) {
+        addToast(t('success.restoreComplete'), 'success');
+        addNotification(
+          t('success.restoreComplete'),
+          `"${snapshotToRestore?.name || snapshotId}" - ${game.name}`,
+          'success'
+        );
+        await updateLastRestoredSnapshot(game.id, snapshotId);
+        onGameUpdated({ ...game, last_restored_snapshot_id: snapshotId, last_restored_at: new Date().toISOString() });
+        loadSnapshots();
+      } else if (result.unsaved_changes) {
+        const changes = result.unsaved_changes;
+        setConfirmModal({
+          isOpen: true,
+          title: t('gameDetail.restore'),
+          message: t('gameDetail.confirmRestoreUnsaved')
+            .replace('{count}', String(changes.added + changes.removed + changes.modified)),
+          danger: true,
+          onConfirm: () => {
+            setConfirmModal(prev => ({ ...prev, isOpen: false }));
+            runRestore(snapshotId, true);
+          }
+        });
+      } else {
+        addToast(result.message, 'error');
+        addNotification(
+          t('errors.failedRestore'),
+          result.message,
+          'error'
+        );
+      }
+    } catch (err) {
+      const errorMsg = err instanceof Error ? err.message : t('errors.failedRestore');
+      addToast(errorMsg, 'error');
+      addNotification(
+        t('errors.failedRestore'),
+        errorMsg,
+        'error'
+      );
+    } finally {
+      setLoading(false, '');
+    }
+  };
+
   const handleRestore = async (snapshotId: string) => {
     if (isProcessRunningState) {
       addToast(`${game.exe_name} ${t('gameDetail.gameRunning')}`, 'warning');
       return;
     }
 
-    const snapshotToRestore = snapshots.find(s => s.id === snapshotId);
-
     setConfirmModal({
       isOpen: true,
       title: t('gameDetail.restore'),
       message: t('gameDetail.confirmRestore'),
-      onConfirm: async () => {
+      onConfirm: () => {
         setConfirmModal(prev => ({ ...prev, isOpen: false }));
-        setLoading(true, t('loading.restoring'));
-
-        try {
-          const result = await restoreSnapshot(snapshotId, game.id);
-          if (result.success) {
-            addToast(t('success.restoreComplete'), 'success');
-            addNotification(
-              t('success.restoreComplete'),
-              `"${snapshotToRestore?.name || snapshotId}" - ${game.name}`,
-              'success'
-            );
-            await updateLastRestoredSnapshot(game.id, snapshotId);
-            onGameUpdated({ ...game, last_restored_snapshot_id: snapshotId, last_restored_at: new Date().toISOString() });
-            loadSnapshots();
-          } else {
-            addToast(result.message, 'error');
-            addNotification(
-              t('errors.failedRestore'),
-              result.message,
-              'error'
-            );
-          }
-        } catch (err) {
-          const errorMsg = err instanceof Error ? err.message : t('errors.failedRestore');
-          addToast(errorMsg, 'error');
-          addNotification(
-            t('errors.failedRestore'),
-            errorMsg,
-            'error'
-          );
-        } finally {
-          setLoading(false, '');
-        }
+        runRestore(snapshotId, false);
       }
     });
   };
@@ -438,7 +460,25 @@ export function GameDetail({ game, onBack, onGameDeleted, onGameUpdated, setLoad
     }
   };
 
-  const handleUploadToCloud = async (snapshot: Snapshot) => {
+  const confirmUploadToCloud = (snapshot: Snapshot) => {
+    if (!snapshot.encrypted) {
+      handleUploadToCloud(snapshot);
+      return;
+    }
+
+    setConfirmModal({
+      isOpen: true,
+      title: t('cloud.uploadToCloud'),
+      message: t('cloud.confirmUploadEncrypted'),
+      danger: true,
+      onConfirm: () => {
+        setConfirmModal(prev => ({ ...prev, isOpen: false }));
+        handleUploadToCloud(snapshot, true);
+      }
+    });
+  };
+
+  const handleUploadToCloud = async (snapshot: Snapshot, decrypt = false) => {
     if (!isAuthenticated) {
       addToast(t('cloud.notAuthenticated'), 'warning');
       return;
@@ -454,43 +494,9 @@ export function GameDetail({ game, onBack, onGameDeleted, onGameUpdated, setLoad
         throw new Error('Failed to get valid access token');
       }
 
-      setLoading(true, t('loading.readingFiles'));
-      const { readDir, readFile } = await import('@tauri-apps/plugin-fs');
-      const { join } = await import('@tauri-apps/api/path');
-
-      const snapshotPath = snapshot.path;
-
-      const files: { path: string; content: Uint8Array }[] = [];
-
-      async function readDirectoryRecursive(dirPath: string, relativePath: string = '') {
-        const entries = await readDir(dirPath);
-
-        for (const entry of entries) {
-          const entryRelativePath = relativePath ? `${relativePath}/${entry.name}` : entry.name;
-          const entryFullPath = await join(dirPath, entry.name);
-
-          if (entry.isDirectory) {
-            await readDirectoryRecursive(entryFullPath, entryRelativePath);
-          } else if (entry.isFile && entry.name !== '.checkpoint-meta.json') {
-            const content = await readFile(entryFullPath);
-            files.push({ path: entryRelativePath, content });
-          }
-        }
-      }
-
-      await readDirectoryRecursive(snapshotPath);
-
       setLoading(true, t('loading.creatingZip'));
-      const JSZip = (await import('jszip')).default;
-      const zip = new JSZip();
-
-      for (const file of files) {
-        zip.file(file.path, file.content);
-      }
-
-      const zipBlob = await zip.generateAsync({ type: 'blob' }, (metadata: { percent: number }) => {
-        setLoading(true, `Creating zip... ${Math.round(metadata.percent)}%`);
-      });
+      const zipData = await exportSnapshotZip(snapshot.id, game.id, decrypt);
+      const zipBlob = new Blob([new Uint8Array(zipData)], { type: 'application/zip' });
 
       setLoading(true, t('loading.uploadingCloud'));
       const fileId = await uploadSnapshot(
@@ -882,7 +888,7 @@ export function GameDetail({ game, onBack, onGameDeleted, onGameUpdated, setLoad
                           }}
                         />
                       ) : (
-                        <h4>{snapshot.name}</h4>
+                        <h4>{snapshot.locked ? t('gameDetail.encryptedSnapshot') : snapshot.name}</h4>
                       )}
                       <p>
                         {formatDate(snapshot.timestamp)} • {formatSize(snapshot.size)} • {snapshot.file_count} {snapshot.file_count === 1 ? t('cloud.file') : t('cloud.files')}
@@ -897,7 +903,7 @@ export function GameDetail({ game, onBack, onGameDeleted, onGameUpdated, setLoad
                       {isAuthenticated && !cloudSnapshots.has(snapshot.id) && backupDestination !== 'local' && (
                         <button
                           className="btn btn-secondary btn-small"
-                          onClick={() => handleUploadToCloud(snapshot)}
+                          onClick={() => confirmUploadToCloud(snapshot)}
                           disabled={isUploading}
                           title={t('cloud.uploadToCloud')}
                         >
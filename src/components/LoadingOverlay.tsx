@@ -1,4 +1,8 @@
+import { useEffect, useState } from 'react';
 import { Loader2 } from 'lucide-react';
+import { cancelOperation, onOperationProgress } from '../lib/api';
+import type { OperationProgress } from '../lib/types';
+import { useI18n } from '../lib/i18n';
 
 interface LoadingOverlayProps {
   isLoading: boolean;
@@ -6,13 +10,51 @@ interface LoadingOverlayProps {
 }
 
 export function LoadingOverlay({ isLoading, message }: LoadingOverlayProps) {
+  const { t } = useI18n();
+  const [progress, setProgress] = useState<OperationProgress | null>(null);
+
+  useEffect(() => {
+    if (!isLoading) {
+      setProgress(null);
+      return;
+    }
+
+    const unlisten = onOperationProgress(setProgress);
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, [isLoading]);
+
   if (!isLoading) return null;
 
+  const percent = progress && progress.bytes_total > 0
+    ? Math.min(100, Math.round((progress.bytes_processed / progress.bytes_total) * 100))
+    : null;
+
   return (
     <div className="loading-overlay">
       <div className="loading-overlay-content">
         <Loader2 size={40} className="loading-spinner-icon" />
         {message && <p className="loading-message">{message}</p>}
+        {progress && (
+          <div className="loading-progress">
+            {percent !== null && (
+              <div className="loading-progress-bar">
+                <div className="loading-progress-fill" style={{ width: `${percent}%` }} />
+              </div>
+            )}
+            <p className="loading-progress-detail">
+              {progress.files_processed}/{progress.files_total}
+              {progress.current_file && ` - ${progress.current_file}`}
+            </p>
+            <button
+              className="btn btn-secondary btn-small"
+              onClick={() => cancelOperation(progress.operation_id)}
+            >
+              {t('common.cancel')}
+            </button>
+          </div>
+        )}
       </div>
     </div>
   );
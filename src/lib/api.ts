@@ -1,12 +1,31 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import type {
   Game,
   Snapshot,
   Config,
+  PathVariable,
   CreateSnapshotRequest,
   AddGameRequest,
+  DiscoveredGame,
+  Discovery,
+  ManifestMatch,
   UpdateGameRequest,
-  RestoreResult
+  RestoreResult,
+  VerificationReport,
+  OperationProgress,
+  FilterPreview,
+  PreviewFile,
+  RetentionPolicy,
+  CompressionSettings,
+  EncryptionStatus,
+  RetentionPlan,
+  WatcherSettings,
+  MonitorSettings,
+  Session,
+  PlaytimeSummary,
+  RestoreOptions,
+  SnapshotDiff
 } from './types';
 
 export const getConfig = (): Promise<Config> =>
@@ -15,35 +34,140 @@ export const getConfig = (): Promise<Config> =>
 export const setBackupLocation = (path: string): Promise<void> =>
   invoke('set_backup_location', { path });
 
+export const setWatcherSettings = (settings: WatcherSettings): Promise<void> =>
+  invoke('set_watcher_settings', { settings });
+
+export const setMonitorSettings = (settings: MonitorSettings): Promise<void> =>
+  invoke('set_monitor_settings', { settings });
+
+export const getPathVariables = (): Promise<PathVariable[]> =>
+  invoke('get_path_variables');
+
+export const setPathVariables = (variables: Record<string, string>): Promise<PathVariable[]> =>
+  invoke('set_path_variables', { variables });
+
 export const addGame = (request: AddGameRequest): Promise<Game> =>
   invoke('add_game', { request });
 
+export const discoverGames = (manifestPath: string | null = null): Promise<Discovery> =>
+  invoke('discover_games', { manifestPath });
+
+export const lookupSaveManifest = (
+  manifestPath: string,
+  name: string,
+  steamAppId: string | null,
+  windows: boolean
+): Promise<ManifestMatch> =>
+  invoke('lookup_save_manifest', { manifestPath, name, steamAppId, windows });
+
+export const addDiscoveredGames = (games: DiscoveredGame[]): Promise<Game[]> =>
+  invoke('add_discovered_games', { games });
+
 export const listGames = (): Promise<Game[]> =>
   invoke('list_games');
 
-export const deleteGame = (gameId: string): Promise<void> =>
-  invoke('delete_game', { gameId });
+export const deleteGame = (gameId: string, force?: boolean): Promise<void> =>
+  invoke('delete_game', { gameId, force });
 
 export const updateGame = (request: UpdateGameRequest): Promise<Game> =>
   invoke('update_game', { request });
 
-export const createSnapshot = (request: CreateSnapshotRequest): Promise<Snapshot> =>
-  invoke('create_snapshot', { request });
+export const createSnapshot = (
+  request: CreateSnapshotRequest,
+  operationId?: string,
+  passphrase?: string
+): Promise<Snapshot> =>
+  invoke('create_snapshot', { request, operationId, passphrase });
 
 export const listSnapshots = (gameId: string): Promise<Snapshot[]> =>
   invoke('list_snapshots', { gameId });
 
-export const restoreSnapshot = (snapshotId: string, gameId: string): Promise<RestoreResult> =>
-  invoke('restore_snapshot', { snapshotId, gameId });
-
-export const deleteSnapshot = (snapshotId: string, gameId: string): Promise<void> =>
-  invoke('delete_snapshot', { snapshotId, gameId });
+export const restoreSnapshot = (
+  snapshotId: string,
+  gameId: string,
+  operationId?: string,
+  options?: RestoreOptions,
+  passphrase?: string
+): Promise<RestoreResult> =>
+  invoke('restore_snapshot', { snapshotId, gameId, operationId, options, passphrase });
+
+export const restoreSnapshotFiles = (
+  snapshotId: string,
+  gameId: string,
+  paths: string[],
+  operationId?: string,
+  passphrase?: string
+): Promise<RestoreResult> =>
+  invoke('restore_snapshot_files', { snapshotId, gameId, paths, operationId, passphrase });
+
+export const listSnapshotFiles = (snapshotId: string, gameId: string): Promise<PreviewFile[]> =>
+  invoke('list_snapshot_files', { snapshotId, gameId });
+
+export const deleteSnapshot = (snapshotId: string, gameId: string, force?: boolean): Promise<void> =>
+  invoke('delete_snapshot', { snapshotId, gameId, force });
 
 export const renameSnapshot = (snapshotId: string, gameId: string, newName: string): Promise<void> =>
   invoke('rename_snapshot', { snapshotId, gameId, newName });
 
-export const verifySnapshot = (snapshotId: string, gameId: string): Promise<boolean> =>
-  invoke('verify_snapshot', { snapshotId, gameId });
+export const setSnapshotPinned = (snapshotId: string, gameId: string, pinned: boolean): Promise<void> =>
+  invoke('set_snapshot_pinned', { snapshotId, gameId, pinned });
+
+export const verifySnapshot = (
+  snapshotId: string,
+  gameId: string,
+  passphrase?: string
+): Promise<VerificationReport> =>
+  invoke('verify_snapshot', { snapshotId, gameId, passphrase });
+
+export const diffSnapshots = (gameId: string, fromSnapshotId: string, toSnapshotId: string): Promise<SnapshotDiff> =>
+  invoke('diff_snapshots', { gameId, fromSnapshotId, toSnapshotId });
+
+export const diffWithCurrentSave = (gameId: string, snapshotId: string): Promise<SnapshotDiff> =>
+  invoke('diff_with_current_save', { gameId, snapshotId });
+
+export const previewSnapshotFiles = (
+  saveLocation: string,
+  includePatterns: string[],
+  excludePatterns: string[]
+): Promise<FilterPreview> =>
+  invoke('preview_snapshot_files', { saveLocation, includePatterns, excludePatterns });
+
+export const setRetentionPolicy = (
+  gameId: string | null,
+  policy: RetentionPolicy | null
+): Promise<void> =>
+  invoke('set_retention_policy', { gameId, policy });
+
+export const setCompression = (
+  gameId: string | null,
+  settings: CompressionSettings | null
+): Promise<void> =>
+  invoke('set_compression', { gameId, settings });
+
+export const getEncryptionStatus = (): Promise<EncryptionStatus> =>
+  invoke('get_encryption_status');
+
+export const unlockEncryption = (passphrase: string): Promise<void> =>
+  invoke('unlock_encryption', { passphrase });
+
+export const setEncryption = (
+  gameId: string | null,
+  enabled: boolean | null,
+  passphrase?: string
+): Promise<void> =>
+  invoke('set_encryption', { gameId, enabled, passphrase });
+
+export const previewRetention = (
+  gameId: string,
+  policy?: RetentionPolicy
+): Promise<RetentionPlan> =>
+  invoke('preview_retention', { gameId, policy });
+
+export const getPlaytime = (gameId: string): Promise<PlaytimeSummary> =>
+  invoke('get_playtime', { gameId });
+
+export const listSessions = (gameId: string): Promise<Session[]> =>
+  invoke('list_sessions', { gameId });
 
 export const isProcessRunning = (processName: string): Promise<boolean> =>
   invoke('is_process_running', { processName });
@@ -51,8 +175,47 @@ export const isProcessRunning = (processName: string): Promise<boolean> =>
 export const selectFolder = (): Promise<string | null> =>
   invoke('select_folder');
 
-export const importSnapshot = (gameId: string, name: string, fileData: Uint8Array): Promise<Snapshot> =>
-  invoke('import_snapshot', { gameId, name, fileData });
+export const importSnapshot = (
+  gameId: string,
+  name: string | null,
+  fileData: Uint8Array,
+  operationId?: string,
+  passphrase?: string
+): Promise<Snapshot> =>
+  invoke('import_snapshot', { gameId, name, fileData, operationId, passphrase });
+
+export const cancelOperation = (operationId: string): Promise<boolean> =>
+  invoke('cancel_operation', { operationId });
+
+export const onOperationProgress = (handler: (progress: OperationProgress) => void): Promise<UnlistenFn> =>
+  listen<OperationProgress>('operation-progress', event => handler(event.payload));
+
+export const onAutoSnapshotCreated = (handler: (snapshot: Snapshot) => void): Promise<UnlistenFn> =>
+  listen<Snapshot>('auto-snapshot-created', event => handler(event.payload));
+
+export const onGameSessionStarted = (handler: (session: Session) => void): Promise<UnlistenFn> =>
+  listen<Session>('game-session-started', event => handler(event.payload));
+
+export const onGameSessionEnded = (handler: (session: Session) => void): Promise<UnlistenFn> =>
+  listen<Session>('game-session-ended', event => handler(event.payload));
+
+// encrypted snapshots are only exported with `decrypt`, the archive holds their files decrypted
+export const exportSnapshotZip = (
+  snapshotId: string,
+  gameId: string,
+  decrypt?: boolean
+): Promise<number[]> =>
+  invoke('export_snapshot_zip', { snapshotId, gameId, decrypt });
+
+export const exportSnapshot = (
+  snapshotId: string,
+  gameId: string,
+  path: string,
+  operationId?: string,
+  passphrase?: string,
+  decrypt?: boolean
+): Promise<void> =>
+  invoke('export_snapshot', { snapshotId, gameId, path, operationId, passphrase, decrypt });
 
 export const startOAuthServer = (): Promise<number> =>
   invoke('start_oauth_server');
@@ -63,8 +226,8 @@ export const waitForOAuthCode = (): Promise<string | null> =>
 export const stopOAuthServer = (): Promise<void> =>
   invoke('stop_oauth_server');
 
-export const resetCheckpoint = (): Promise<void> =>
-  invoke('reset_checkpoint');
+export const resetCheckpoint = (force?: boolean): Promise<void> =>
+  invoke('reset_checkpoint', { force });
 
 export const openFolder = (path: string): Promise<void> =>
   invoke('open_folder', { path });
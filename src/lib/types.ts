@@ -7,6 +7,52 @@ export interface Game {
   created_at: string;
   last_restored_snapshot_id?: string;
   last_restored_at?: string;
+  save_roots: SaveRoot[];
+  include_patterns: string[];
+  exclude_patterns: string[];
+  retention?: RetentionPolicy;
+  compression?: CompressionSettings;
+  encryption?: boolean;
+  // %APPDATA% and the like in save locations resolve inside it
+  wine_prefix?: string;
+  steam_app_id?: string;
+  watch_saves: boolean;
+  snapshot_on_exit: boolean;
+  snapshot_on_start: boolean;
+}
+
+export interface SaveRoot {
+  name: string;
+  path: string;
+}
+
+export type GameSource = 'steam' | 'heroic' | 'lutris';
+
+export interface DiscoveredGame {
+  source: GameSource;
+  source_id: string;
+  name: string;
+  install_dir: string;
+  exe_name?: string;
+  wine_prefix?: string;
+  // guesses, confirmed or edited by the user before adding
+  save_roots: SaveRoot[];
+  include_patterns: string[];
+  already_added: boolean;
+}
+
+export interface Discovery {
+  games: DiscoveredGame[];
+  // launcher installations and game files that couldn't be read
+  warnings: string[];
+}
+
+// save locations a Ludusavi manifest entry gives for a game
+export interface ManifestMatch {
+  name: string;
+  steam_app_id?: string;
+  save_roots: SaveRoot[];
+  include_patterns: string[];
 }
 
 export interface Snapshot {
@@ -16,12 +62,90 @@ export interface Snapshot {
   name: string;
   path: string;
   size: number;
+  stored_size: number;
   file_count: number;
+  kind: SnapshotKind;
+  pinned: boolean;
+  session_id?: string;
+  encrypted: boolean;
+  // encrypted and the passphrase wasn't entered yet, name is empty
+  locked: boolean;
 }
 
+export type SnapshotKind = 'manual' | 'auto' | 'auto_backup';
+
 export interface Config {
   games: Game[];
   backup_location: string;
+  retention: RetentionPolicy;
+  compression: CompressionSettings;
+  encryption: boolean;
+  watcher: WatcherSettings;
+  monitor: MonitorSettings;
+  path_variables?: Record<string, string>;
+}
+
+export interface PathVariable {
+  name: string;
+  path: string;
+  custom: boolean;
+}
+
+export interface MonitorSettings {
+  enabled: boolean;
+  poll_interval_secs: number;
+}
+
+export interface Session {
+  id: string;
+  game_id: string;
+  started_at: string;
+  ended_at?: string;
+  duration_secs?: number;
+}
+
+export interface PlaytimeSummary {
+  game_id: string;
+  total_secs: number;
+  session_count: number;
+  last_played?: string;
+}
+
+export interface WatcherSettings {
+  enabled: boolean;
+  quiet_period_secs: number;
+}
+
+export interface CompressionSettings {
+  enabled: boolean;
+  level: number;
+}
+
+export interface EncryptionStatus {
+  set_up: boolean;
+  unlocked: boolean;
+}
+
+export interface RetentionPolicy {
+  keep_last?: number;
+  keep_daily_days?: number;
+  keep_weekly_weeks?: number;
+  max_total_size?: number;
+  max_auto_backups?: number;
+}
+
+export interface PrunedSnapshot {
+  id: string;
+  name: string;
+  timestamp: string;
+  size: number;
+  reason: 'not_retained' | 'auto_backup_limit' | 'size_limit';
+}
+
+export interface RetentionPlan {
+  kept: string[];
+  pruned: PrunedSnapshot[];
+  pruned_size: number;
 }
 
 export interface CreateSnapshotRequest {
@@ -34,6 +158,14 @@ export interface AddGameRequest {
   save_location: string;
   exe_name?: string;
   cover_image?: string;
+  save_roots?: SaveRoot[];
+  include_patterns?: string[];
+  exclude_patterns?: string[];
+  wine_prefix?: string;
+  steam_app_id?: string;
+  watch_saves?: boolean;
+  snapshot_on_exit?: boolean;
+  snapshot_on_start?: boolean;
 }
 
 export interface UpdateGameRequest {
@@ -42,13 +174,78 @@ export interface UpdateGameRequest {
   save_location?: string;
   exe_name?: string;
   cover_image?: string;
+  save_roots?: SaveRoot[];
+  include_patterns?: string[];
+  exclude_patterns?: string[];
+  // an empty string clears it
+  wine_prefix?: string;
+  watch_saves?: boolean;
+  snapshot_on_exit?: boolean;
+  snapshot_on_start?: boolean;
 }
 
 export interface RestoreResult {
   success: boolean;
   backed_up_current: boolean;
   backup_snapshot_id?: string;
+  files_restored: number;
+  rolled_back: boolean;
   message: string;
+  unsaved_changes?: SnapshotDiff;
+}
+
+export interface RestoreOptions {
+  overwrite_unsaved?: boolean;
+  target_dir?: string;
+}
+
+export type ChangeKind = 'added' | 'removed' | 'modified';
+
+export interface FileChange {
+  path: string;
+  change: ChangeKind;
+  old_size?: number;
+  new_size?: number;
+  size_delta: number;
+}
+
+export interface SnapshotDiff {
+  changes: FileChange[];
+  added: number;
+  removed: number;
+  modified: number;
+  unchanged: number;
+  size_delta: number;
+}
+
+export interface VerificationReport {
+  status: 'valid' | 'corrupted' | 'unverifiable';
+  checked_files: number;
+  missing: string[];
+  extra: string[];
+  corrupted: string[];
+}
+
+export interface PreviewFile {
+  path: string;
+  size: number;
+}
+
+export interface FilterPreview {
+  included: PreviewFile[];
+  excluded: PreviewFile[];
+  included_size: number;
+  excluded_size: number;
+}
+
+export interface OperationProgress {
+  operation_id: string;
+  stage: string;
+  files_processed: number;
+  files_total: number;
+  bytes_processed: number;
+  bytes_total: number;
+  current_file?: string;
 }
 
 export interface UserProfile {